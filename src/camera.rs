@@ -1,16 +1,118 @@
-use glam::{Mat4, Vec3};
+use anyhow::{bail, Result};
+use glam::{Mat4, Quat, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::str::FromStr;
+
+/// Which screen axis the configured field of view spans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FovAxis {
+    #[default]
+    Vertical,
+    /// The horizontal FOV is kept fixed and the vertical one follows the aspect ratio.
+    Horizontal,
+}
+
+impl FromStr for FovAxis {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "vertical" | "v" | "y" => FovAxis::Vertical,
+            "horizontal" | "h" | "x" => FovAxis::Horizontal,
+            other => bail!("unknown FOV axis '{other}' (expected vertical or horizontal)"),
+        })
+    }
+}
+
+/// Height of the eye above the feet while walking, in world units.
+pub const EYE_HEIGHT: f32 = 1.62;
+
+/// Tunables for walking under gravity.
+#[derive(Debug, Clone, Copy)]
+pub struct Physics {
+    /// Downward acceleration in world units per second squared.
+    pub gravity: f32,
+    /// How high a jump lifts the feet, in world units.
+    pub jump_height: f32,
+    /// Fastest fall speed, in world units per second.
+    pub terminal_velocity: f32,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            gravity: 32.0,
+            jump_height: 1.25,
+            terminal_velocity: 50.0,
+        }
+    }
+}
+
+impl Physics {
+    /// Upward speed that reaches `jump_height` under `gravity`.
+    pub fn jump_speed(&self) -> f32 {
+        (2.0 * self.gravity * self.jump_height).sqrt()
+    }
+}
+
+/// Tunables for the head-bob while walking and the shake on landing. Both
+/// only move the rendered view, never the camera's logical position.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraEffects {
+    pub enabled: bool,
+    /// Peak vertical offset of the head-bob, in world units.
+    pub bob_amplitude: f32,
+    /// Head-bob cycles per world unit walked.
+    pub bob_frequency: f32,
+    /// Largest shake offset, reached when landing at terminal velocity.
+    pub shake_amplitude: f32,
+}
+
+impl Default for CameraEffects {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            bob_amplitude: 0.05,
+            bob_frequency: 0.5,
+            shake_amplitude: 0.2,
+        }
+    }
+}
+
+/// Seconds for the head-bob and shake to fade to about a third.
+const EFFECT_DECAY: f32 = 0.15;
 
 pub struct Camera {
     pub position: Vec3,
     pub forward: Vec3,
     pub up: Vec3,
     pub right: Vec3,
-    pub fovy_degrees: f32,
+    pub fov_degrees: f32,
+    pub fov_axis: FovAxis,
     pub aspect: f32,
     pub near: f32,
     pub far: f32,
+    /// Vertical speed while walking, positive upwards.
+    pub vertical_velocity: f32,
+    /// Whether the feet rested on the ground after the last gravity step.
+    pub on_ground: bool,
+    /// Drift while flying with inertia, in world units per second.
+    pub fly_velocity: Vec3,
+    /// Transient offset from head-bob and shake, added to the eye when
+    /// rendering only.
+    pub view_offset: Vec3,
+    /// Sub-pixel shift of the projection in normalized device coordinates,
+    /// for temporal anti-aliasing.
+    pub jitter: Vec2,
+    bob_phase: f32,
+    bob_strength: f32,
+    shake: f32,
+    effect_time: f32,
     pitch: f32,
     yaw: f32,
+    roll: f32,
 }
 
 impl Camera {
@@ -20,26 +122,44 @@ impl Camera {
             forward: Vec3::NEG_Z,
             up: Vec3::Y,
             right: Vec3::X,
-            fovy_degrees: 70.0,
+            fov_degrees: 70.0,
+            fov_axis: FovAxis::Vertical,
             aspect,
             near: 0.1,
             far: 100.0,
+            vertical_velocity: 0.0,
+            on_ground: false,
+            fly_velocity: Vec3::ZERO,
+            view_offset: Vec3::ZERO,
+            jitter: Vec2::ZERO,
+            bob_phase: 0.0,
+            bob_strength: 0.0,
+            shake: 0.0,
+            effect_time: 0.0,
             pitch: 0.0,  // Start level
             yaw: 0.0,    // Start facing forward
+            roll: 0.0,
         };
         camera.update_vectors();
         camera
     }
 
+    /// Vertical field of view in radians, derived from the configured axis.
+    pub fn fovy(&self) -> f32 {
+        let fov = self.fov_degrees.to_radians();
+        match self.fov_axis {
+            FovAxis::Vertical => fov,
+            FovAxis::Horizontal => 2.0 * ((fov * 0.5).tan() / self.aspect).atan(),
+        }
+    }
+
     pub fn get_proj_view_matrix(&self) -> Mat4 {
-        let proj = Mat4::perspective_rh(
-            self.fovy_degrees.to_radians(),
-            self.aspect,
-            self.near,
-            self.far,
-        );
-        let view = Mat4::look_at_rh(self.position, self.position + self.forward, self.up);
-        proj * view
+        let proj = Mat4::perspective_rh(self.fovy(), self.aspect, self.near, self.far);
+        let eye = self.position + self.view_offset;
+        // Roll only tilts the picture; movement keeps to the level axes
+        let up = Quat::from_axis_angle(self.forward, self.roll.to_radians()) * self.up;
+        let view = Mat4::look_at_rh(eye, eye + self.forward, up);
+        Mat4::from_translation(self.jitter.extend(0.0)) * proj * view
     }
 
     pub fn move_forward(&mut self, distance: f32) {
@@ -54,6 +174,106 @@ impl Camera {
         self.position += self.up * distance;
     }
 
+    /// Moves along the world's vertical axis, whatever the pitch.
+    pub fn move_vertical(&mut self, distance: f32) {
+        self.position += Vec3::Y * distance;
+    }
+
+    /// Moves along the heading, ignoring pitch, so walking stays level.
+    pub fn walk_forward(&mut self, distance: f32) {
+        let heading = Vec3::new(self.forward.x, 0.0, self.forward.z).normalize_or_zero();
+        self.position += heading * distance;
+    }
+
+    /// Turns a movement into drift. The push fades at `damping` per second,
+    /// so the glide covers `displacement` in total, just spread out in time.
+    pub fn push(&mut self, displacement: Vec3, damping: f32) {
+        self.fly_velocity += displacement * damping;
+    }
+
+    /// How far the drift carries the camera in `dt` seconds, slowing it down
+    /// by `damping` per second. The caller moves the camera, so it can stop
+    /// at obstacles.
+    pub fn glide(&mut self, dt: f32, damping: f32) -> Vec3 {
+        let decay = (-damping * dt).exp();
+        let moved = self.fly_velocity * (1.0 - decay) / damping;
+        self.fly_velocity *= decay;
+        moved
+    }
+
+    /// Advances the fall by `dt` seconds. `ground` is the height of the
+    /// highest surface at or below the feet; it has to be found before
+    /// moving, so a fast fall lands on it instead of passing through.
+    pub fn apply_gravity(&mut self, dt: f32, physics: &Physics, ground: Option<f32>) {
+        self.vertical_velocity =
+            (self.vertical_velocity - physics.gravity * dt).max(-physics.terminal_velocity);
+        let feet = self.position.y - EYE_HEIGHT + self.vertical_velocity * dt;
+
+        self.on_ground = ground.is_some_and(|ground| feet <= ground);
+        self.position.y = match ground {
+            Some(ground) if self.on_ground => {
+                self.vertical_velocity = 0.0;
+                ground + EYE_HEIGHT
+            }
+            _ => feet + EYE_HEIGHT,
+        };
+    }
+
+    /// Starts a jump if the feet are on the ground.
+    pub fn jump(&mut self, physics: &Physics) {
+        if self.on_ground {
+            self.vertical_velocity = physics.jump_speed();
+            self.on_ground = false;
+        }
+    }
+
+    /// Advances the head-bob by a walked `distance`.
+    pub fn bob(&mut self, distance: f32, effects: &CameraEffects) {
+        self.bob_phase = (self.bob_phase + distance.abs() * effects.bob_frequency * TAU) % TAU;
+        self.bob_strength = 1.0;
+    }
+
+    /// Starts a shake scaled by how fast the feet hit the ground.
+    pub fn shake(&mut self, impact_speed: f32, effects: &CameraEffects, physics: &Physics) {
+        let impact = (impact_speed / physics.terminal_velocity).clamp(0.0, 1.0);
+        self.shake = self.shake.max(effects.shake_amplitude * impact);
+    }
+
+    /// Fades the head-bob and shake over `dt` seconds and updates `view_offset`.
+    pub fn update_effects(&mut self, dt: f32, effects: &CameraEffects) {
+        let decay = (-dt / EFFECT_DECAY).exp();
+        self.bob_strength *= decay;
+        self.shake *= decay;
+        // The shake frequencies are whole numbers, so wrapping the clock at
+        // a full turn doesn't make it jump
+        self.effect_time = (self.effect_time + dt) % TAU;
+
+        if !effects.enabled {
+            self.view_offset = Vec3::ZERO;
+            return;
+        }
+        let bob = effects.bob_amplitude * self.bob_strength * self.bob_phase.sin();
+        let t = self.effect_time;
+        let jitter = Vec3::new((t * 71.0).sin(), (t * 53.0).sin(), (t * 37.0).sin());
+        self.view_offset = Vec3::Y * bob + jitter * self.shake;
+    }
+
+    /// Heading in degrees; 0 faces +X and 90 faces +Z.
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    /// Degrees above the horizon, -89 to 89.
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Degrees the view leans to the right around its forward axis, -180 to
+    /// 180.
+    pub fn roll(&self) -> f32 {
+        self.roll
+    }
+
     pub fn rotate_x(&mut self, degrees: f32) {
         self.pitch += degrees;
         self.pitch = self.pitch.clamp(-89.0, 89.0);
@@ -61,10 +281,17 @@ impl Camera {
     }
 
     pub fn rotate_y(&mut self, degrees: f32) {
-        self.yaw += degrees;
+        // Wrap into [-180, 180) so long sessions of turning don't grow the
+        // angle until it loses precision
+        self.yaw = (self.yaw + degrees + 180.0).rem_euclid(360.0) - 180.0;
         self.update_vectors();
     }
 
+    /// Leans the view right (positive) or left; wraps like the yaw.
+    pub fn rotate_z(&mut self, degrees: f32) {
+        self.roll = (self.roll + degrees + 180.0).rem_euclid(360.0) - 180.0;
+    }
+
     fn update_vectors(&mut self) {
         let pitch_rad = self.pitch.to_radians();
         let yaw_rad = self.yaw.to_radians();
@@ -80,3 +307,165 @@ impl Camera {
         self.up = self.right.cross(self.forward).normalize();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+
+    /// A camera at (0, 10, 0) facing +X, with +Z to its right.
+    fn camera_facing_x() -> Camera {
+        let camera = Camera::new(2.0, Vec3::new(0.0, 10.0, 0.0));
+        assert!(camera.forward.abs_diff_eq(Vec3::X, 1e-6));
+        assert!(camera.right.abs_diff_eq(Vec3::Z, 1e-6));
+        camera
+    }
+
+    fn clip(camera: &Camera, point: Vec3) -> Vec4 {
+        camera.get_proj_view_matrix() * point.extend(1.0)
+    }
+
+    #[test]
+    fn point_ahead_projects_to_screen_center() {
+        let camera = camera_facing_x();
+        let clip = clip(&camera, Vec3::new(5.0, 10.0, 0.0));
+        assert!(clip.w > 0.0);
+        let ndc = clip.truncate() / clip.w;
+        assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5);
+        // wgpu's depth range is 0..1
+        assert!((0.0..1.0).contains(&ndc.z));
+    }
+
+    #[test]
+    fn projection_keeps_right_and_up_on_screen() {
+        let camera = camera_facing_x();
+        let right = clip(&camera, Vec3::new(5.0, 10.0, 1.0));
+        let up = clip(&camera, Vec3::new(5.0, 11.0, 0.0));
+        assert!(right.x / right.w > 0.0 && (right.y / right.w).abs() < 1e-5);
+        assert!(up.y / up.w > 0.0 && (up.x / up.w).abs() < 1e-5);
+
+        // The edge of a horizontal FOV lands on the edge of the screen
+        let mut camera = camera;
+        camera.fov_axis = FovAxis::Horizontal;
+        let half_width = (camera.fov_degrees.to_radians() / 2.0).tan() * 5.0;
+        let edge = clip(&camera, Vec3::new(5.0, 10.0, half_width));
+        assert!((edge.x / edge.w - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn points_behind_or_beyond_far_plane_are_clipped() {
+        let camera = camera_facing_x();
+        let behind = clip(&camera, Vec3::new(-5.0, 10.0, 0.0));
+        assert!(behind.w < 0.0);
+        let too_far = clip(&camera, Vec3::new(camera.far + 1.0, 10.0, 0.0));
+        assert!(too_far.z > too_far.w);
+        let too_near = clip(&camera, Vec3::new(camera.near / 2.0, 10.0, 0.0));
+        assert!(too_near.z < 0.0);
+    }
+
+    #[test]
+    fn spinning_keeps_yaw_bounded() {
+        let mut camera = Camera::new(1.0, Vec3::ZERO);
+        camera.rotate_y(30.0);
+        let start = camera.forward;
+
+        // Ten thousand full turns in 7.5 degree steps
+        for _ in 0..10_000 * 48 {
+            camera.rotate_y(7.5);
+        }
+        assert!((-180.0..180.0).contains(&camera.yaw));
+        assert!(camera.forward.abs_diff_eq(start, 1e-4));
+
+        for _ in 0..10_000 * 48 {
+            camera.rotate_y(-7.5);
+        }
+        assert!((-180.0..180.0).contains(&camera.yaw));
+        assert!(camera.forward.abs_diff_eq(start, 1e-4));
+    }
+
+    #[test]
+    fn yaw_wraps_at_half_turn() {
+        let mut camera = Camera::new(1.0, Vec3::ZERO);
+        camera.rotate_y(190.0);
+        assert!((camera.yaw - -170.0).abs() < 1e-4);
+        camera.rotate_y(-20.0);
+        assert!((camera.yaw - 170.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn roll_turns_the_picture_around_its_center() {
+        let mut camera = camera_facing_x();
+        camera.rotate_z(90.0);
+        // Leaning right a quarter turn puts the world's up on the screen's left
+        let up = clip(&camera, Vec3::new(5.0, 11.0, 0.0));
+        assert!(up.x / up.w < 0.0 && (up.y / up.w).abs() < 1e-5);
+        let ahead = clip(&camera, Vec3::new(5.0, 10.0, 0.0));
+        assert!((ahead.x / ahead.w).abs() < 1e-5 && (ahead.y / ahead.w).abs() < 1e-5);
+        // Movement stays level
+        assert!(camera.right.abs_diff_eq(Vec3::Z, 1e-6));
+
+        camera.rotate_z(100.0);
+        assert!((camera.roll() - -170.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn effects_move_the_view_but_not_the_camera() {
+        let effects = CameraEffects::default();
+        let mut camera = Camera::new(1.0, Vec3::new(0.0, 10.0, 0.0));
+        let still = camera.get_proj_view_matrix();
+
+        camera.bob(0.5, &effects);
+        camera.shake(20.0, &effects, &Physics::default());
+        camera.update_effects(0.01, &effects);
+        assert_eq!(camera.position, Vec3::new(0.0, 10.0, 0.0));
+        assert!(camera.view_offset.length() > 0.0);
+        assert!(camera.view_offset.length() <= effects.bob_amplitude + effects.shake_amplitude);
+        assert!(!camera.get_proj_view_matrix().abs_diff_eq(still, 1e-6));
+
+        for _ in 0..100 {
+            camera.update_effects(0.05, &effects);
+        }
+        assert!(camera.view_offset.length() < 1e-4);
+    }
+
+    #[test]
+    fn glide_covers_the_pushed_distance() {
+        let mut camera = Camera::new(1.0, Vec3::ZERO);
+        camera.push(Vec3::new(0.5, 0.0, -0.5), 3.0);
+        let mut moved = Vec3::ZERO;
+        let mut steps = 0;
+        while camera.fly_velocity.length() > 1e-4 {
+            moved += camera.glide(1.0 / 30.0, 3.0);
+            steps += 1;
+        }
+        // Spread over a few seconds of frames, adding up to the push
+        assert!(steps > 30);
+        assert!(moved.abs_diff_eq(Vec3::new(0.5, 0.0, -0.5), 1e-4));
+        // Frame rate doesn't change where the glide ends
+        camera.fly_velocity = Vec3::X * 3.0;
+        let coarse = camera.glide(0.5, 3.0) + camera.glide(0.5, 3.0);
+        camera.fly_velocity = Vec3::X * 3.0;
+        let fine: Vec3 = (0..100).map(|_| camera.glide(0.01, 3.0)).sum();
+        assert!(coarse.abs_diff_eq(fine, 1e-5));
+    }
+
+    #[test]
+    fn fast_fall_lands_on_ground() {
+        let physics = Physics {
+            gravity: 1000.0,
+            ..Default::default()
+        };
+        let mut camera = Camera::new(1.0, Vec3::new(0.0, 100.0, 0.0));
+        for _ in 0..100 {
+            camera.apply_gravity(0.1, &physics, Some(8.0));
+            assert!(camera.vertical_velocity >= -physics.terminal_velocity);
+            assert!(camera.position.y >= 8.0 + EYE_HEIGHT);
+        }
+        assert!(camera.on_ground);
+        assert_eq!(camera.position.y, 8.0 + EYE_HEIGHT);
+
+        camera.jump(&physics);
+        camera.apply_gravity(0.01, &physics, Some(8.0));
+        assert!(camera.position.y > 8.0 + EYE_HEIGHT);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::geometry::Vertex;
+use crate::world_gen::{CHUNK_HEIGHT, CHUNK_SIZE_X, CHUNK_SIZE_Z, WORLD_HEIGHT};
+use glam::{IVec2, Vec3};
+use wgpu::util::DeviceExt;
+
+/// Chunks around the camera's own whose corners get a line, on each side.
+const GRID_RADIUS: i32 = 2;
+
+/// Linear colors of the corner lines and of the outline of the camera's
+/// chunk.
+const CORNER_COLOR: Vec3 = Vec3::new(1.0, 0.1, 0.8);
+const OUTLINE_COLOR: Vec3 = Vec3::new(1.0, 0.8, 0.05);
+
+/// Debug lines in the world marking chunk boundaries: a vertical line up
+/// every chunk corner near the camera, and the edges of the camera's chunk
+/// at every `CHUNK_HEIGHT` blocks. Rebuilt when the camera enters another
+/// chunk.
+pub struct ChunkGrid {
+    vertex_buffer: Option<wgpu::Buffer>,
+    vertex_count: u32,
+    center: Option<IVec2>,
+}
+
+impl ChunkGrid {
+    pub fn new() -> Self {
+        Self {
+            vertex_buffer: None,
+            vertex_count: 0,
+            center: None,
+        }
+    }
+
+    /// Centers the grid on `camera_chunk`.
+    pub fn update(&mut self, device: &wgpu::Device, camera_chunk: IVec2, block_size: f32) {
+        if self.center == Some(camera_chunk) {
+            return;
+        }
+        let vertices = grid_lines(camera_chunk, block_size);
+        self.vertex_buffer = Some(
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Chunk Grid Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+        );
+        self.vertex_count = vertices.len() as u32;
+        self.center = Some(camera_chunk);
+    }
+
+    /// Draws the lines with a line-list pipeline already set.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if let Some(vertex_buffer) = &self.vertex_buffer {
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.draw(0..self.vertex_count, 0..1);
+        }
+    }
+}
+
+/// Line-list vertices of the grid around the chunk at `center`.
+fn grid_lines(center: IVec2, block_size: f32) -> Vec<Vertex> {
+    let chunk_size = Vec3::new(CHUNK_SIZE_X as f32, 0.0, CHUNK_SIZE_Z as f32) * block_size;
+    let corner = |x: i32, z: i32, y: f32| {
+        Vec3::new(x as f32, 0.0, z as f32) * chunk_size + Vec3::Y * y * block_size
+    };
+    let mut vertices = Vec::new();
+    let mut line = |from: Vec3, to: Vec3, color: Vec3| {
+        vertices.push(Vertex::new(from, color, [0.0; 2]));
+        vertices.push(Vertex::new(to, color, [0.0; 2]));
+    };
+
+    let top = WORLD_HEIGHT as f32;
+    for x in center.x - GRID_RADIUS..=center.x + GRID_RADIUS + 1 {
+        for z in center.y - GRID_RADIUS..=center.y + GRID_RADIUS + 1 {
+            line(corner(x, z, 0.0), corner(x, z, top), CORNER_COLOR);
+        }
+    }
+
+    let (x0, z0, x1, z1) = (center.x, center.y, center.x + 1, center.y + 1);
+    for layer in (0..=WORLD_HEIGHT).step_by(CHUNK_HEIGHT) {
+        let y = layer as f32;
+        line(corner(x0, z0, y), corner(x1, z0, y), OUTLINE_COLOR);
+        line(corner(x1, z0, y), corner(x1, z1, y), OUTLINE_COLOR);
+        line(corner(x1, z1, y), corner(x0, z1, y), OUTLINE_COLOR);
+        line(corner(x0, z1, y), corner(x0, z0, y), OUTLINE_COLOR);
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_lines_run_along_chunk_boundaries() {
+        let vertices = grid_lines(IVec2::new(-3, 5), 0.5);
+        let corners = (2 * GRID_RADIUS + 2).pow(2) as usize;
+        let layers = WORLD_HEIGHT / CHUNK_HEIGHT + 1;
+        assert_eq!(vertices.len(), (corners + layers * 4) * 2);
+        for vertex in vertices {
+            let [x, _, z] = vertex.position;
+            // Every endpoint is a chunk corner
+            assert_eq!((x / (CHUNK_SIZE_X as f32 * 0.5)).fract(), 0.0);
+            assert_eq!((z / (CHUNK_SIZE_Z as f32 * 0.5)).fract(), 0.0);
+        }
+    }
+}
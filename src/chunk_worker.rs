@@ -0,0 +1,158 @@
+use crate::geometry::ChunkMesh;
+use crate::world_gen::{build_chunk_mesh, generate_chunk, Chunk, WorldGenConfig};
+use glam::IVec2;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// A chunk generated and meshed off the main thread, ready for GPU upload.
+pub struct GeneratedChunk {
+    pub chunk: Chunk,
+    /// Config the chunk was generated with; results for an outdated seed can
+    /// be told apart and dropped.
+    pub config: WorldGenConfig,
+    /// `None` with `sky_shadow`: its light spreads in from neighbouring
+    /// chunks only the caller has, so the caller meshes it instead.
+    pub mesh: Option<ChunkMesh>,
+}
+
+/// Generates and, unless it's left for the caller, meshes the chunk at
+/// `position`.
+fn generate(position: IVec2, config: WorldGenConfig) -> GeneratedChunk {
+    let started = Instant::now();
+    let chunk = generate_chunk(position, &config);
+    let mesh = config
+        .sky_shadow
+        .is_none()
+        .then(|| build_chunk_mesh(&chunk, &config));
+    log::trace!("generated chunk {position} in {:.2?}", started.elapsed());
+    GeneratedChunk {
+        chunk,
+        config,
+        mesh,
+    }
+}
+
+/// Generates and meshes chunks on background threads. Requests go out over
+/// one channel and finished chunks come back over another; GPU uploads stay
+/// with the caller, since they have to happen on the main thread.
+///
+/// With no threads, requests are generated by `drain` on the caller's
+/// thread instead, as many per call as fit in a time budget.
+pub struct ChunkWorker {
+    requests: Option<Sender<(IVec2, WorldGenConfig)>>,
+    results: Receiver<GeneratedChunk>,
+    threads: Vec<JoinHandle<()>>,
+    in_flight: usize,
+    /// Requests waiting to be generated without threads.
+    queued: VecDeque<(IVec2, WorldGenConfig)>,
+    /// Time each `drain` may spend generating without threads.
+    budget: Duration,
+}
+
+impl ChunkWorker {
+    /// Starts `threads` workers, or none to generate on the caller's thread
+    /// for up to `budget` per `drain`.
+    pub fn spawn(threads: usize, budget: Duration) -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<(IVec2, WorldGenConfig)>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let request_rx = Arc::new(Mutex::new(request_rx));
+
+        let threads = (0..threads)
+            .map(|i| {
+                let requests = Arc::clone(&request_rx);
+                let results = result_tx.clone();
+                std::thread::Builder::new()
+                    .name(format!("chunk-worker-{i}"))
+                    .spawn(move || loop {
+                        // The lock is only held while waiting for the next
+                        // request, so workers generate in parallel
+                        let request = requests.lock().unwrap().recv();
+                        let Ok((position, config)) = request else {
+                            break;
+                        };
+                        if results.send(generate(position, config)).is_err() {
+                            break;
+                        }
+                    })
+                    .expect("failed to spawn chunk worker thread")
+            })
+            .collect();
+
+        Self {
+            requests: Some(request_tx),
+            results: result_rx,
+            threads,
+            in_flight: 0,
+            queued: VecDeque::new(),
+            budget,
+        }
+    }
+
+    /// Queues generation of the chunk at `position`.
+    pub fn request(&mut self, position: IVec2, config: &WorldGenConfig) {
+        if self.threads.is_empty() {
+            self.queued.push_back((position, config.clone()));
+            return;
+        }
+        if let Some(requests) = &self.requests {
+            if requests.send((position, config.clone())).is_ok() {
+                self.in_flight += 1;
+            }
+        }
+    }
+
+    /// Returns the chunks finished since the last call without blocking.
+    /// Without threads, generates queued chunks until the budget is used up
+    /// instead, at least one per call so loading always moves on.
+    pub fn drain(&mut self) -> Vec<GeneratedChunk> {
+        if self.threads.is_empty() {
+            let started = Instant::now();
+            let mut finished = Vec::new();
+            while let Some((position, config)) = self.queued.pop_front() {
+                finished.push(generate(position, config));
+                if started.elapsed() >= self.budget {
+                    break;
+                }
+            }
+            return finished;
+        }
+        let finished: Vec<_> = self.results.try_iter().collect();
+        self.in_flight -= finished.len();
+        finished
+    }
+
+    /// Blocks until every requested chunk is finished and returns them.
+    pub fn wait_all(&mut self) -> Vec<GeneratedChunk> {
+        if self.threads.is_empty() {
+            return self
+                .queued
+                .drain(..)
+                .map(|(position, config)| generate(position, config))
+                .collect();
+        }
+        let mut finished = Vec::with_capacity(self.in_flight);
+        while self.in_flight > 0 {
+            match self.results.recv() {
+                Ok(result) => finished.push(result),
+                // Every worker is gone, nothing else will arrive
+                Err(_) => break,
+            }
+            self.in_flight -= 1;
+        }
+        finished
+    }
+}
+
+impl Drop for ChunkWorker {
+    fn drop(&mut self) {
+        // Closing the request channel makes each worker's `recv` fail, ending
+        // its loop once the chunk it's working on is done
+        self.requests = None;
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,1020 @@
+use crate::camera::{CameraEffects, FovAxis, Physics};
+use crate::horizon::Horizon;
+use crate::hud::Crosshair;
+use crate::input::InputProfile;
+use crate::interaction::Hardness;
+use crate::material::DEFAULT_OVERLAY_DEPTH_BIAS;
+use crate::present::{Glyph, OutputBuffering, Viewport};
+use crate::render_distance::AutoDistance;
+use crate::renderer::{self, GpuOptions};
+use crate::sky::{self, Fog, Sun};
+use crate::state::{State, ViewToggles};
+use crate::world::BlockEdit;
+use crate::world_gen::{
+    seed_from_str, BakedLighting, FaceBrightness, WorldGenConfig, CHUNK_SIZE_X, CHUNK_SIZE_Z,
+    MAX_CHUNK_VERTICES, WORLD_HEIGHT,
+};
+use anyhow::{anyhow, bail, Result};
+use crossterm::terminal;
+use glam::{Vec2, Vec3};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Largest accepted `--chunks` grid. Each chunk's mesh takes around 0.1 MB,
+/// so this already needs about 400 MB and a long wait at startup.
+pub const MAX_CHUNKS: u32 = 64;
+
+/// Largest render distance `--target-frame-time` tunes up to without
+/// `--render-distance`.
+const DEFAULT_AUTO_MAX_DISTANCE: u32 = 8;
+
+/// Smallest render distance `--target-frame-time` tunes down to without
+/// `--min-render-distance`.
+const DEFAULT_AUTO_MIN_DISTANCE: u32 = 2;
+
+/// Largest `--render-size` side, the texture size wgpu's default limits
+/// guarantee.
+const MAX_RENDER_SIZE: u32 = 8192;
+
+/// Longest `--generation-budget` and `--target-frame-time`, in milliseconds.
+/// A frame taking longer than a second is no longer interactive.
+const MAX_FRAME_MILLIS: f32 = 1000.0;
+
+/// Largest `--crosshair-size`, already taller than any real terminal.
+const MAX_CROSSHAIR_SIZE: u32 = 100;
+
+/// Largest `--cell-pixels` side.
+const MAX_CELL_PIXELS: u32 = 8;
+
+/// Largest `--erosion` radius. Each chunk samples the raw heights of a border
+/// this wide around it, which grows generation time with its square.
+const MAX_EROSION_RADIUS: usize = 16;
+
+/// Biome width used by `--biomes` without `--biome-size`.
+const DEFAULT_BIOME_SIZE: f32 = 64.0;
+
+const USAGE: &str = "\
+Usage: minecraftty [OPTIONS]
+
+Options:
+  --debug-gpu           Enable wgpu validation and backend logging
+                        (also enabled by MINECRAFTTY_DEBUG_GPU=1)
+  --backend <NAME>      Graphics backend: vulkan, metal, dx12, gl or all
+  --gpu <INDEX>         Use the adapter at INDEX (see --list-gpus)
+  --power <PREF>        Prefer a high-performance or low-power GPU: high, low
+  --readback <FORMAT>   Copy frames back from the GPU as rgba (default) or
+                        rgb, which packs out the alpha bytes first
+  --frame-latency <N>   Frames the GPU may run ahead of the terminal: 0
+                        (default) or 1, which shows each frame one late but
+                        renders the next one while it's drawn
+  --list-gpus           List available GPU adapters and exit
+  --screenshot <FILE>   Render a single frame to an image file and exit
+                        without using the terminal
+  --stream <PATH>       Also send every frame's terminal output to a Unix
+                        socket or named pipe, for viewing elsewhere
+  --export-obj <FILE>   Write the starting chunks' meshes to a Wavefront OBJ
+                        file and exit (unless --screenshot is also given)
+  --viewport <ROW,COL,COLS,ROWS>
+                        Draw into this rectangle of terminal cells instead of
+                        the top-left 100x30 (rows and columns start at 0,
+                        the rectangle must end within 8192 of each)
+  --max-width <COLS>    Fill the terminal (or --viewport) up to this many
+                        columns (at most 8192), centering the view
+  --max-height <ROWS>   Fill the terminal (or --viewport) up to this many rows
+                        (at most 8192), centering the view
+  --render-size <WxH>   Render at this resolution and box-filter it down (or
+                        stretch it up) to the view's pixel grid
+  --render-scale <RATIO>
+                        Render at RATIO times the view's pixel grid, e.g. 0.5
+                        for a quarter of the pixels on slow machines
+  --glyph <GLYPH>       Cell glyph: upper (▀), lower (▄), full (solid cell) or
+                        quadrant (▘▝▀▖...)
+  --cell-pixels <WxH>   Pixel columns and rows each cell shows (default 1x2);
+                        match the font's cell shape, e.g. 2x2 with the
+                        quadrant glyph or 1x1 for square cells
+  --fps <N>             Frame rate cap; lower values use less CPU (default 30)
+  --frames <N>          Quit after rendering N frames, e.g. to profile a fixed
+                        workload or capture a bounded --stream
+  --stats               Print min, average, max and 95th percentile frame
+                        times per stage to stderr on exit
+  --no-frame-skip       Present every frame even when the terminal can't
+                        keep up with the frame rate
+  --output-buffering <MODE>
+                        Write each frame to the terminal in one go (frame,
+                        the default) or in 64 KiB pieces as it's built
+                        (incremental)
+  --no-confirm-quit     Quit without offering to save unsaved block edits
+  --no-splash           Start without the title screen listing the controls,
+                        e.g. for scripted runs
+  --level-flight        Fly like walking: W/S stay level and Q/E go straight
+                        up and down, whatever the pitch
+  --inertia             Start with flying inertia on (toggle with Shift+I)
+  --fly-damping <RATE>  How fast flying drift slows down with inertia, per
+                        second; lower glides longer (default 3)
+  --key-repeat <RATE>   Steps per second a held movement key moves, where the
+                        terminal reports key releases (default 20); 0 steps
+                        once per key event instead
+  --log-block-events    Log the block stepped onto while walking and the block
+                        under the crosshair as they change (at info level,
+                        see RUST_LOG)
+  --reach <BLOCKS>      How far away the crosshair can target a block to break
+                        or place against (default 5)
+  --hover-highlight     Start with the targeted block brightened (toggle with
+                        Shift+H)
+  --hover-brightness <AMOUNT>
+                        How much brighter the highlight draws the targeted
+                        block, e.g. 0.35 for 35% (default)
+  --crosshair <STYLE>   Crosshair shape: plus (default), lines for a
+                        box-drawing cross, dot, or circle
+  --crosshair-size <ROWS>
+                        Arm length of the lines crosshair and radius of the
+                        circle, in rows (default 1, at most 100)
+  --crosshair-color <RRGGBB>
+                        Crosshair color as hex (default ffffff)
+  --build-height <Y>    Stop block placement at layer Y (default and maximum
+                        32, the world height)
+  --clamp-to-loaded     Start with movement stopped at the edge of the
+                        loaded chunks (toggle with R)
+  --smooth <RADIUS>     Blend each cell with neighbouring pixels to reduce
+                        crawling edges while moving (default 0, off)
+  --taa <BLEND>         Temporal anti-aliasing: jitter the view by sub-pixel
+                        offsets and blend in BLEND of the previous frame while
+                        the camera is still (0 to 0.95, default 0, off)
+  --color-step <N>      Round color channels to multiples of N so similar
+                        cells share escape codes (default 1, exact; 4 trims
+                        a few percent off each frame)
+  --white-balance <R,G,B>
+                        Per-channel gains applied to terminal output to
+                        cancel a tinted color scheme (default 1,1,1)
+  --input <PROFILE>     Starting key profile: game, or inspection to orbit with
+                        H/J/K/L and pan with the arrows (Tab switches)
+  --sensitivity-x <N>   Multiplier for how far H/L turn (default 1)
+  --sensitivity-y <N>   Multiplier for how far J/K tilt (default 1)
+  --invert-y            Swap the J/K look directions
+  --gravity <N>         Fall acceleration while walking, in blocks/s² (default 32)
+  --jump-height <N>     How high a jump reaches while walking (default 1.25)
+  --head-bob <N>        Height of the head-bob while walking (default 0.05)
+  --head-bob-frequency <N>
+                        Head-bob cycles per block walked (default 0.5)
+  --shake <N>           Camera shake on a hard landing (default 0.2)
+  --no-camera-effects   Disable the head-bob and landing shake
+  --fov <DEGREES>       Field of view (default 70)
+  --fov-axis <AXIS>     Axis the FOV applies to: vertical or horizontal
+  --pos <X,Y,Z>         Starting camera position (default 0,10,0)
+  --yaw <DEGREES>       Starting heading; 0 faces +X, 90 faces +Z
+  --pitch <DEGREES>     Starting pitch, -89 (down) to 89 (up)
+  --roll <DEGREES>      Starting roll; positive leans the view right
+  --detail-map          Shade blocks with a baked ambient-occlusion texture
+                        (costs an extra texture)
+  --overlay-depth-bias <UNITS>
+                        Depth bias pulling the selection frame in front of
+                        the faces it lies on; more negative if it flickers
+                        (default -4)
+  --chunk-grid          Start with lines marking chunk corners and the edges
+                        of the current chunk drawn in the world (toggle with
+                        Shift+G)
+  --debug-chunk-seams   Darken every other chunk in a checkerboard to make
+                        gaps and culling mistakes at chunk seams visible
+  --dedup-vertices      Merge identical vertices in chunk meshes
+  --rotate-textures     Turn stone, dirt, ore, leaves and bedrock textures by
+                        a random quarter turn per face to hide tiling
+  --max-chunk-vertices <N>
+                        Drop faces past N vertices in a chunk's mesh, with a
+                        warning (default and maximum 65536)
+  --baked-lighting      Bake sunlight into vertex colors when meshing instead
+                        of lighting every pixel
+  --face-lighting       Light faces with fixed brightness per direction, like
+                        classic Minecraft, instead of following the sun
+  --face-brightness <TOP,NS,EW,BOTTOM>
+                        Brightness of each face direction for --face-lighting
+                        (default 1,0.8,0.6,0.5; implies --face-lighting)
+  --sky-shadow <BRIGHTNESS>
+                        Bake sky light: faces it doesn't reach, under a block
+                        and away from any opening, are lit at BRIGHTNESS, 0 to
+                        1 (implies --baked-lighting unless --face-lighting is
+                        given)
+  --sun-size <DEGREES>  Apparent diameter of the sun (default 8, 0 hides it)
+  --sun-color <RRGGBB>  Sun color as hex (default fff4c8)
+  --fog <START,END>     Fade terrain into the sky between these distances;
+                        auto (default) follows the far plane and render
+                        distance, off disables it
+  --horizon <BLOCKS>    Draw a sea-level plane out to this distance so the
+                        world seems to reach the horizon (default 0, off)
+  --horizon-color <RRGGBB>
+                        Color of the horizon plane (default 77c84a)
+  --chunks <N>          Generate an N x N grid of chunks around the origin
+                        and frame it with the camera (default 2, max 64)
+  --render-distance <N> Keep generating chunks up to N chunks around the camera
+                        while playing, and only draw those (default off)
+  --unload-margin <N>   Drop chunks once they're N chunks past the render
+                        distance, so hovering at its edge doesn't reload
+                        them (default 2)
+  --target-frame-time <MS>
+                        Tune the render distance to keep rendering a frame
+                        under MS milliseconds, scaled by the FOV; it ranges up
+                        to --render-distance (default 8)
+  --min-render-distance <N>
+                        Lowest render distance --target-frame-time goes down
+                        to (default 2)
+  --chunk-threads <N>   Threads generating chunks in the background (default 1);
+                        0 generates them between frames on the main thread
+  --generation-budget <MS>
+                        With --chunk-threads 0, time each frame may spend
+                        generating chunks (default 8)
+  --uploads-per-frame <N>
+                        Upload at most N finished chunk meshes to the GPU per
+                        frame, spreading bursts out (default: all of them),
+                        and re-mesh N chunks per frame after M (default 4)
+  --block-size <SIZE>   Edge length of a block in world units (default 1)
+  --ore-size <BLOCKS>   Rough diameter of ore clusters (default 3)
+  --ore-rarity <LEVEL>  Higher values make ore rarer; 0 turns about half of
+                        the deep stone into ore (default 0.45)
+  --soil-depth <N>      Layer the terrain: N blocks of soil over stone
+                        (default: the reference world's noise-mixed blocks)
+  --no-grass            Leave soil bare dirt instead of growing grass
+  --trees <DENSITY>     Grow trees on grass; DENSITY is the chance per column,
+                        e.g. 0.05
+  --decorations <DENSITY>
+                        Scatter tall grass and flowers on open grass;
+                        DENSITY is the chance per column, e.g. 0.3
+  --flowers <SHARE>     Share of the plants that are flowers (default 0.25)
+  --biomes              Vary the terrain by biome, picked from temperature and
+                        humidity noise
+  --biome-size <BLOCKS> Rough width of a biome (default 64; implies --biomes)
+  --erosion <RADIUS>    Smooth the terrain by averaging column heights within
+                        RADIUS columns, for gentler slopes (default 0, off)
+  --erosion-strength <AMOUNT>
+                        How far heights move towards that average, 0 to 1
+                        (default 1)
+  --hardness <LIST>     Override break times per block, e.g. stone=3,dirt=0.2
+                        (1 takes ten break-key presses, 0 breaks instantly)
+  --load-state <FILE>   Start from a state saved with Shift+S: camera, world
+                        settings, block edits and view toggles (later options
+                        override it)
+  --seed <SEED>         World seed; text seeds are hashed, so the same
+                        string always generates the same world
+  -h, --help            Print this help and exit
+
+GPU log output is written to stderr; redirect it (2>gpu.log) to keep the
+terminal view clean.";
+
+/// Runtime options collected from the command line and environment.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub gpu: GpuOptions,
+    pub glyph: Glyph,
+    /// Whether frames reach the terminal whole or in pieces.
+    pub output_buffering: OutputBuffering,
+    /// Terminal cells the view is drawn into; the render size follows it.
+    pub viewport: Viewport,
+    /// Resolution to render at when it differs from the viewport's pixel
+    /// grid; frames are resampled to the grid before drawing.
+    pub render_size: Option<(u32, u32)>,
+    /// Pixel columns and rows behind each cell. Pixels are square, so this
+    /// is also the shape the cells are assumed to have.
+    pub cell_pixels: (u32, u32),
+    pub world: WorldGenConfig,
+    /// Block edits restored by `--load-state`, applied as their chunks load.
+    pub edits: Vec<BlockEdit>,
+    pub fov_degrees: f32,
+    pub fov_axis: FovAxis,
+    pub sun: Sun,
+    /// Multiply block textures by the baked detail texture.
+    pub detail_map: bool,
+    /// Constant depth bias of overlays drawn flat on block faces.
+    pub overlay_depth_bias: i32,
+    /// Start with the chunk grid lines shown.
+    pub chunk_grid: bool,
+    pub hardness: Hardness,
+    pub horizon: Horizon,
+    pub fog: Fog,
+    /// Side length of the square grid of chunks generated at startup,
+    /// centered on the origin.
+    pub chunks: u32,
+    /// Chunks around the camera to generate and draw while playing. `None`
+    /// keeps just the startup grid.
+    pub render_distance: Option<u32>,
+    /// Chunks past the render distance that loaded chunks are kept for.
+    pub unload_margin: u32,
+    /// Tune the render distance to a frame-time target, up to
+    /// `render_distance`.
+    pub auto_distance: Option<AutoDistance>,
+    /// Number of background threads generating and meshing chunks. With
+    /// none, chunks are generated on the main thread.
+    pub chunk_threads: usize,
+    /// Time a frame may spend generating chunks on the main thread.
+    pub generation_budget: Duration,
+    /// Most chunk meshes uploaded to the GPU per frame while playing. `None`
+    /// uploads each chunk as soon as it's generated.
+    pub uploads_per_frame: Option<usize>,
+    /// Initial camera position and orientation, in world units and degrees.
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+    /// Key profile to start with.
+    pub input_profile: InputProfile,
+    /// Runtime toggles to start with.
+    pub view: ViewToggles,
+    /// Multipliers for the horizontal and vertical look speed.
+    pub sensitivity_x: f32,
+    pub sensitivity_y: f32,
+    /// Swap the up and down look directions.
+    pub invert_y: bool,
+    /// Gravity and jumping in walk mode.
+    pub physics: Physics,
+    /// Head-bob and landing shake in walk mode.
+    pub effects: CameraEffects,
+    /// Target frames per second for the interactive loop.
+    pub fps: u32,
+    /// Quit after rendering this many frames.
+    pub frames: Option<u64>,
+    /// Print a summary of frame timings to stderr on a clean exit.
+    pub stats: bool,
+    /// Skip presenting frames while the terminal is slower than the frame rate.
+    pub frame_skip: bool,
+    /// Offer to save before quitting with unsaved block edits.
+    pub confirm_quit: bool,
+    /// Show the title screen with the controls while the world generates.
+    pub splash: bool,
+    /// Keep flying movement level and vertical, as when walking.
+    pub level_flight: bool,
+    /// Start with flying inertia on.
+    pub inertia: bool,
+    /// Rate at which flying drift decays with inertia, per second.
+    pub fly_damping: f32,
+    /// Steps per second of a held movement key; 0 steps once per key event.
+    pub key_repeat_rate: f32,
+    /// Log block events through a `LogObserver`.
+    pub log_block_events: bool,
+    /// How far away, in blocks, the crosshair can target a block.
+    pub reach: f32,
+    /// Extra brightness of the targeted block with the hover highlight on.
+    pub hover_brightness: f32,
+    /// Shape, size and color of the crosshair.
+    pub crosshair: Crosshair,
+    /// Blocks can't be placed at or above this layer.
+    pub build_height: u32,
+    /// Start with movement clamped to the loaded chunks.
+    pub clamp_to_loaded: bool,
+    /// Radius in pixels of the tent filter applied before presenting; 0 disables it.
+    pub smoothing: u32,
+    /// Weight of the previous frame for temporal anti-aliasing; 0 disables it.
+    pub taa_blend: f32,
+    /// Frames rendered ahead of the one being presented, 0 or 1.
+    pub frame_latency: u32,
+    /// Terminal colors are rounded to multiples of this; 1 keeps them exact.
+    pub color_step: u8,
+    /// Red, green and blue gains applied to the terminal output.
+    pub white_balance: [f32; 3],
+    /// Render one frame to this file instead of running interactively.
+    pub screenshot: Option<PathBuf>,
+    /// Also write each frame's terminal output here.
+    pub stream: Option<PathBuf>,
+    /// Write the loaded chunks' meshes to this OBJ file instead of running
+    /// interactively.
+    pub export_obj: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            gpu: GpuOptions::default(),
+            glyph: Glyph::default(),
+            output_buffering: OutputBuffering::default(),
+            viewport: Viewport::default(),
+            render_size: None,
+            cell_pixels: (1, 2),
+            world: WorldGenConfig::default(),
+            edits: Vec::new(),
+            fov_degrees: 70.0,
+            fov_axis: FovAxis::default(),
+            sun: Sun::default(),
+            detail_map: false,
+            overlay_depth_bias: DEFAULT_OVERLAY_DEPTH_BIAS,
+            chunk_grid: false,
+            hardness: Hardness::default(),
+            horizon: Horizon::default(),
+            fog: Fog::default(),
+            chunks: 2,
+            render_distance: None,
+            unload_margin: 2,
+            auto_distance: None,
+            chunk_threads: 1,
+            generation_budget: Duration::from_millis(8),
+            uploads_per_frame: None,
+            position: Vec3::new(0.0, 10.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            input_profile: InputProfile::default(),
+            view: ViewToggles::default(),
+            sensitivity_x: 1.0,
+            sensitivity_y: 1.0,
+            invert_y: false,
+            physics: Physics::default(),
+            effects: CameraEffects::default(),
+            fps: 30,
+            frames: None,
+            stats: false,
+            frame_skip: true,
+            confirm_quit: true,
+            splash: true,
+            level_flight: false,
+            inertia: false,
+            fly_damping: 3.0,
+            key_repeat_rate: 20.0,
+            log_block_events: false,
+            reach: 5.0,
+            hover_brightness: 0.35,
+            crosshair: Crosshair::default(),
+            build_height: WORLD_HEIGHT as u32,
+            clamp_to_loaded: false,
+            smoothing: 0,
+            taa_blend: 0.0,
+            frame_latency: 0,
+            color_step: 1,
+            white_balance: [1.0; 3],
+            screenshot: None,
+            stream: None,
+            export_obj: None,
+        }
+    }
+}
+
+impl Config {
+    /// Pixels the viewport's cells show, the size frames are drawn at.
+    pub fn grid_size(&self) -> (u32, u32) {
+        let (cell_width, cell_height) = self.cell_pixels;
+        (
+            self.viewport.cols * cell_width,
+            self.viewport.rows * cell_height,
+        )
+    }
+
+    pub fn from_args() -> Result<Option<Self>> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// `None` when the arguments only asked for information, like `--help`
+    /// or `--list-gpus`, which has already been printed.
+    pub fn parse<I>(args: I) -> Result<Option<Self>>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut config = Self::default();
+
+        if let Some(backends) = wgpu::util::backend_bits_from_env() {
+            config.gpu.backends = backends;
+        }
+        if std::env::var("MINECRAFTTY_DEBUG_GPU").is_ok_and(|v| v != "0") {
+            config.gpu.debug = true;
+        }
+
+        if let Some(power) = wgpu::util::power_preference_from_env() {
+            config.gpu.power_preference = power;
+        }
+
+        let mut list_gpus = false;
+        let mut chunks_set = false;
+        let mut position_set = false;
+        let mut pitch_set = false;
+        let mut baked_lighting = false;
+        let mut face_lighting: Option<FaceBrightness> = None;
+        let mut viewport_set = false;
+        let mut max_width = None;
+        let mut max_height = None;
+        let mut render_scale: Option<f32> = None;
+        let mut target_frame_time: Option<f32> = None;
+        let mut min_render_distance = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--debug-gpu" => config.gpu.debug = true,
+                "--backend" => config.gpu.backends = parse_backend(&value(&mut args, &arg)?)?,
+                "--gpu" => config.gpu.adapter_index = Some(parse(&value(&mut args, &arg)?, &arg)?),
+                "--power" => config.gpu.power_preference = parse_power(&value(&mut args, &arg)?)?,
+                "--readback" => config.gpu.readback = value(&mut args, &arg)?.parse()?,
+                "--frame-latency" => config.frame_latency = parse(&value(&mut args, &arg)?, &arg)?,
+                "--list-gpus" => list_gpus = true,
+                "--glyph" => config.glyph = value(&mut args, &arg)?.parse()?,
+                "--viewport" => {
+                    config.viewport = value(&mut args, &arg)?.parse()?;
+                    viewport_set = true;
+                }
+                "--max-width" => max_width = Some(parse(&value(&mut args, &arg)?, &arg)?),
+                "--max-height" => max_height = Some(parse(&value(&mut args, &arg)?, &arg)?),
+                "--render-size" => {
+                    config.render_size = Some(parse_size(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--cell-pixels" => config.cell_pixels = parse_size(&value(&mut args, &arg)?, &arg)?,
+                "--render-scale" => render_scale = Some(parse(&value(&mut args, &arg)?, &arg)?),
+                "--input" => config.input_profile = value(&mut args, &arg)?.parse()?,
+                "--sensitivity-x" => config.sensitivity_x = parse(&value(&mut args, &arg)?, &arg)?,
+                "--sensitivity-y" => config.sensitivity_y = parse(&value(&mut args, &arg)?, &arg)?,
+                "--invert-y" => config.invert_y = true,
+                "--gravity" => config.physics.gravity = parse(&value(&mut args, &arg)?, &arg)?,
+                "--jump-height" => {
+                    config.physics.jump_height = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--head-bob" => {
+                    config.effects.bob_amplitude = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--head-bob-frequency" => {
+                    config.effects.bob_frequency = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--shake" => {
+                    config.effects.shake_amplitude = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--no-camera-effects" => config.effects.enabled = false,
+                "--fov" => config.fov_degrees = parse(&value(&mut args, &arg)?, &arg)?,
+                "--fov-axis" => config.fov_axis = value(&mut args, &arg)?.parse()?,
+                "--fps" => config.fps = parse(&value(&mut args, &arg)?, &arg)?,
+                "--frames" => config.frames = Some(parse(&value(&mut args, &arg)?, &arg)?),
+                "--stats" => config.stats = true,
+                "--output-buffering" => {
+                    config.output_buffering = value(&mut args, &arg)?.parse()?
+                }
+                "--no-frame-skip" => config.frame_skip = false,
+                "--no-confirm-quit" => config.confirm_quit = false,
+                "--no-splash" => config.splash = false,
+                "--level-flight" => config.level_flight = true,
+                "--inertia" => config.inertia = true,
+                "--fly-damping" => config.fly_damping = parse(&value(&mut args, &arg)?, &arg)?,
+                "--key-repeat" => config.key_repeat_rate = parse(&value(&mut args, &arg)?, &arg)?,
+                "--log-block-events" => config.log_block_events = true,
+                "--reach" => config.reach = parse(&value(&mut args, &arg)?, &arg)?,
+                "--hover-highlight" => config.view.hover_highlight = true,
+                "--hover-brightness" => {
+                    config.hover_brightness = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--crosshair" => config.crosshair.style = value(&mut args, &arg)?.parse()?,
+                "--crosshair-size" => {
+                    config.crosshair.size = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--crosshair-color" => {
+                    config.crosshair.color = sky::parse_color(&value(&mut args, &arg)?)?
+                }
+                "--build-height" => config.build_height = parse(&value(&mut args, &arg)?, &arg)?,
+                "--clamp-to-loaded" => config.clamp_to_loaded = true,
+                "--smooth" => config.smoothing = parse(&value(&mut args, &arg)?, &arg)?,
+                "--taa" => config.taa_blend = parse(&value(&mut args, &arg)?, &arg)?,
+                "--color-step" => config.color_step = parse(&value(&mut args, &arg)?, &arg)?,
+                "--white-balance" => {
+                    config.white_balance = parse_vec3(&value(&mut args, &arg)?, &arg)?.to_array();
+                }
+                "--pos" => {
+                    config.position = parse_vec3(&value(&mut args, &arg)?, &arg)?;
+                    position_set = true;
+                }
+                "--yaw" => config.yaw = parse(&value(&mut args, &arg)?, &arg)?,
+                "--roll" => config.roll = parse(&value(&mut args, &arg)?, &arg)?,
+                "--pitch" => {
+                    config.pitch = parse(&value(&mut args, &arg)?, &arg)?;
+                    pitch_set = true;
+                }
+                "--screenshot" => config.screenshot = Some(value(&mut args, &arg)?.into()),
+                "--stream" => config.stream = Some(value(&mut args, &arg)?.into()),
+                "--export-obj" => config.export_obj = Some(value(&mut args, &arg)?.into()),
+                "--detail-map" => config.detail_map = true,
+                "--overlay-depth-bias" => {
+                    config.overlay_depth_bias = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--chunk-grid" => config.chunk_grid = true,
+                "--debug-chunk-seams" => config.world.chunk_checkerboard = true,
+                "--dedup-vertices" => config.world.dedup_vertices = true,
+                "--rotate-textures" => config.world.rotate_textures = true,
+                "--max-chunk-vertices" => {
+                    config.world.max_chunk_vertices = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--baked-lighting" => baked_lighting = true,
+                "--face-lighting" => {
+                    face_lighting.get_or_insert_with(FaceBrightness::default);
+                }
+                "--face-brightness" => face_lighting = Some(value(&mut args, &arg)?.parse()?),
+                "--sky-shadow" => {
+                    config.world.sky_shadow = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--sun-size" => config.sun.size_degrees = parse(&value(&mut args, &arg)?, &arg)?,
+                "--sun-color" => config.sun.color = sky::parse_color(&value(&mut args, &arg)?)?,
+                "--fog" => config.fog = value(&mut args, &arg)?.parse()?,
+                "--horizon" => config.horizon.distance = parse(&value(&mut args, &arg)?, &arg)?,
+                "--horizon-color" => {
+                    config.horizon.color = sky::parse_color(&value(&mut args, &arg)?)?
+                }
+                "--chunks" => {
+                    config.chunks = parse(&value(&mut args, &arg)?, &arg)?;
+                    chunks_set = true;
+                }
+                "--render-distance" => {
+                    config.render_distance = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--unload-margin" => config.unload_margin = parse(&value(&mut args, &arg)?, &arg)?,
+                "--target-frame-time" => {
+                    target_frame_time = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--min-render-distance" => {
+                    min_render_distance = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--chunk-threads" => config.chunk_threads = parse(&value(&mut args, &arg)?, &arg)?,
+                "--generation-budget" => {
+                    let millis: f32 = parse(&value(&mut args, &arg)?, &arg)?;
+                    if !(0.0..=MAX_FRAME_MILLIS).contains(&millis) {
+                        bail!("--generation-budget must be between 0 and {MAX_FRAME_MILLIS}");
+                    }
+                    config.generation_budget = Duration::from_secs_f32(millis / 1000.0);
+                }
+                "--uploads-per-frame" => {
+                    config.uploads_per_frame = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--block-size" => config.world.block_size = parse(&value(&mut args, &arg)?, &arg)?,
+                "--ore-size" => config.world.ore_size = parse(&value(&mut args, &arg)?, &arg)?,
+                "--ore-rarity" => config.world.ore_rarity = parse(&value(&mut args, &arg)?, &arg)?,
+                "--soil-depth" => {
+                    config.world.soil_depth = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--no-grass" => config.world.grass_top = false,
+                "--decorations" => {
+                    config.world.decoration_density = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--flowers" => config.world.flower_share = parse(&value(&mut args, &arg)?, &arg)?,
+                "--trees" => {
+                    config.world.tree_density = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--biomes" => {
+                    config.world.biome_size.get_or_insert(DEFAULT_BIOME_SIZE);
+                }
+                "--biome-size" => {
+                    config.world.biome_size = Some(parse(&value(&mut args, &arg)?, &arg)?)
+                }
+                "--erosion" => config.world.erosion_radius = parse(&value(&mut args, &arg)?, &arg)?,
+                "--erosion-strength" => {
+                    config.world.erosion_strength = parse(&value(&mut args, &arg)?, &arg)?
+                }
+                "--hardness" => config.hardness = value(&mut args, &arg)?.parse()?,
+                "--load-state" => {
+                    State::load(Path::new(&value(&mut args, &arg)?))?.apply(&mut config);
+                    position_set = true;
+                    pitch_set = true;
+                }
+                "--seed" => config.world.seed = seed_from_str(&value(&mut args, &arg)?),
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    return Ok(None);
+                }
+                other => bail!("unknown argument '{other}'\n\n{USAGE}"),
+            }
+        }
+
+        if !(1.0..180.0).contains(&config.fov_degrees) {
+            bail!("--fov must be between 1 and 179 degrees");
+        }
+
+        if !(0.0..180.0).contains(&config.sun.size_degrees) {
+            bail!("--sun-size must be between 0 and 179 degrees");
+        }
+
+        if config.world.biome_size.is_some_and(|size| size < 1.0) {
+            bail!("--biome-size must be at least 1");
+        }
+
+        if config.world.erosion_radius > MAX_EROSION_RADIUS {
+            bail!("--erosion must be at most {MAX_EROSION_RADIUS}");
+        }
+
+        if !(0.0..=1.0).contains(&config.world.erosion_strength) {
+            bail!("--erosion-strength must be between 0 and 1");
+        }
+
+        if config.frames == Some(0) {
+            bail!("--frames must be at least 1");
+        }
+
+        if config.uploads_per_frame == Some(0) {
+            bail!("--uploads-per-frame must be at least 1");
+        }
+
+        if config
+            .world
+            .sky_shadow
+            .is_some_and(|brightness| !(0.0..=1.0).contains(&brightness))
+        {
+            bail!("--sky-shadow must be between 0 and 1");
+        }
+
+        // Also checks a block size restored by --load-state
+        if !(config.world.block_size.is_finite() && config.world.block_size > 0.0) {
+            bail!("--block-size must be positive and finite");
+        }
+
+        if config.world.ore_size <= 0.0 {
+            bail!("--ore-size must be positive");
+        }
+
+        if config
+            .world
+            .tree_density
+            .is_some_and(|density| !(0.0..=1.0).contains(&density))
+        {
+            bail!("--trees must be between 0 and 1");
+        }
+        if config
+            .world
+            .decoration_density
+            .is_some_and(|density| !(0.0..=1.0).contains(&density))
+        {
+            bail!("--decorations must be between 0 and 1");
+        }
+        if !(0.0..=1.0).contains(&config.world.flower_share) {
+            bail!("--flowers must be between 0 and 1");
+        }
+
+        if config.sensitivity_x <= 0.0 || config.sensitivity_y <= 0.0 {
+            bail!("--sensitivity-x and --sensitivity-y must be positive");
+        }
+
+        if config.fly_damping <= 0.0 {
+            bail!("--fly-damping must be positive");
+        }
+
+        if config.key_repeat_rate < 0.0 {
+            bail!("--key-repeat must not be negative");
+        }
+        if config.physics.gravity <= 0.0 {
+            bail!("--gravity must be positive");
+        }
+
+        if config.physics.jump_height < 0.0 {
+            bail!("--jump-height must not be negative");
+        }
+
+        if config.effects.bob_amplitude < 0.0 || config.effects.shake_amplitude < 0.0 {
+            bail!("--head-bob and --shake must not be negative");
+        }
+
+        if config.effects.bob_frequency <= 0.0 {
+            bail!("--head-bob-frequency must be positive");
+        }
+
+        if config.horizon.distance < 0.0 {
+            bail!("--horizon must not be negative");
+        }
+
+        if !(0.0..=0.95).contains(&config.taa_blend) {
+            bail!("--taa must be between 0 and 0.95");
+        }
+
+        if config.frame_latency > 1 {
+            bail!("--frame-latency must be 0 or 1");
+        }
+
+        // TAA blends against the camera of the frame being rendered, not the
+        // one being presented
+        if config.frame_latency > 0 && config.taa_blend > 0.0 {
+            bail!("--frame-latency can't be combined with --taa");
+        }
+
+        if !(4..=MAX_CHUNK_VERTICES).contains(&config.world.max_chunk_vertices) {
+            bail!("--max-chunk-vertices must be between 4 and {MAX_CHUNK_VERTICES}");
+        }
+
+        if config.reach <= 0.0 {
+            bail!("--reach must be positive");
+        }
+
+        if !(1..=WORLD_HEIGHT as u32).contains(&config.build_height) {
+            bail!("--build-height must be between 1 and {WORLD_HEIGHT}");
+        }
+
+        if !(1..=128).contains(&config.color_step) {
+            bail!("--color-step must be between 1 and 128");
+        }
+
+        for gain in config.white_balance {
+            if !(0.0..=4.0).contains(&gain) {
+                bail!("--white-balance gains must be between 0 and 4");
+            }
+        }
+
+        if max_width == Some(0) || max_height == Some(0) {
+            bail!("--max-width and --max-height must be positive");
+        }
+        // Every cell is at least a pixel, so no larger view can be rendered
+        if [max_width, max_height]
+            .into_iter()
+            .flatten()
+            .any(|cells| cells > MAX_RENDER_SIZE)
+        {
+            bail!("--max-width and --max-height can be at most {MAX_RENDER_SIZE}");
+        }
+        let Viewport {
+            row,
+            col,
+            cols,
+            rows,
+        } = config.viewport;
+        let fits = |start: u32, len: u32| {
+            start
+                .checked_add(len)
+                .is_some_and(|end| end <= MAX_RENDER_SIZE)
+        };
+        if !fits(col, cols) || !fits(row, rows) {
+            bail!("--viewport must lie within the first {MAX_RENDER_SIZE} rows and columns");
+        }
+
+        // A size cap switches from the fixed default to filling the terminal,
+        // letterboxed around the capped view
+        if max_width.is_some() || max_height.is_some() {
+            if !viewport_set {
+                if let Ok((cols, rows)) = terminal::size() {
+                    config.viewport = Viewport {
+                        row: 0,
+                        col: 0,
+                        cols: cols as u32,
+                        rows: rows as u32,
+                    };
+                }
+            }
+            config.viewport = config.viewport.cap(
+                max_width.unwrap_or(u32::MAX),
+                max_height.unwrap_or(u32::MAX),
+            );
+        }
+
+        // Checked before the cell size multiplies the viewport into pixels
+        let (cell_width, cell_height) = config.cell_pixels;
+        if cell_width > MAX_CELL_PIXELS || cell_height > MAX_CELL_PIXELS {
+            bail!("--cell-pixels can be at most {MAX_CELL_PIXELS}x{MAX_CELL_PIXELS}");
+        }
+
+        // Follows the final viewport size
+        if let Some(scale) = render_scale {
+            if config.render_size.is_some() {
+                bail!("--render-size and --render-scale can't be combined");
+            }
+            if !(scale > 0.0 && scale <= 8.0) {
+                bail!("--render-scale must be above 0 and at most 8");
+            }
+            let scaled = |pixels: u32| ((pixels as f32 * scale).round() as u32).max(1);
+            let (width, height) = config.grid_size();
+            config.render_size = Some((scaled(width), scaled(height)));
+        }
+        let (width, height) = config.render_size.unwrap_or(config.grid_size());
+        if width > MAX_RENDER_SIZE || height > MAX_RENDER_SIZE {
+            bail!(
+                "the render size can be at most {MAX_RENDER_SIZE}x{MAX_RENDER_SIZE} pixels \
+                 (lower --render-size, --render-scale, --viewport or --cell-pixels)"
+            );
+        }
+
+        // Baked after parsing so it uses the final sun direction
+        let sun = BakedLighting::Sun(config.sun.direction.normalize_or_zero());
+        config.world.baked_lighting = match face_lighting {
+            Some(_) if baked_lighting => {
+                bail!("--baked-lighting and --face-lighting can't be combined")
+            }
+            Some(faces) => Some(BakedLighting::Faces(faces)),
+            None if baked_lighting => Some(sun),
+            // Lighting restored by --load-state stays, and --sky-shadow needs
+            // some baked lighting
+            None => config
+                .world
+                .baked_lighting
+                .or(config.world.sky_shadow.map(|_| sun)),
+        };
+
+        if !(1..=MAX_CHUNKS).contains(&config.chunks) {
+            bail!("--chunks must be between 1 and {MAX_CHUNKS}");
+        }
+
+        if config
+            .render_distance
+            .is_some_and(|distance| distance > MAX_CHUNKS / 2)
+        {
+            bail!("--render-distance must be at most {}", MAX_CHUNKS / 2);
+        }
+
+        config.auto_distance = match target_frame_time {
+            Some(millis) => {
+                if !(millis > 0.0 && millis <= MAX_FRAME_MILLIS) {
+                    bail!("--target-frame-time must be positive and at most {MAX_FRAME_MILLIS}");
+                }
+                let max = *config
+                    .render_distance
+                    .get_or_insert(DEFAULT_AUTO_MAX_DISTANCE);
+                let min = min_render_distance.unwrap_or(DEFAULT_AUTO_MIN_DISTANCE.min(max));
+                if min > max {
+                    bail!("--min-render-distance must not exceed --render-distance");
+                }
+                Some(AutoDistance {
+                    target: Duration::from_secs_f32(millis / 1000.0),
+                    min,
+                    max,
+                })
+            }
+            None if min_render_distance.is_some() => {
+                bail!("--min-render-distance needs --target-frame-time")
+            }
+            None => None,
+        };
+
+        // Unless told where to start, pull the camera back to overlook the
+        // whole grid from its -X edge, looking across it
+        if chunks_set && !position_set {
+            let chunk_size = Vec2::new(CHUNK_SIZE_X as f32, CHUNK_SIZE_Z as f32);
+            let half_extent = config.chunks as f32 * chunk_size / 2.0;
+            // Odd grids have the origin chunk in the middle, so their center
+            // sits half a chunk off the origin
+            let center = (config.chunks % 2) as f32 * chunk_size / 2.0;
+            config.position = Vec3::new(
+                center.x - half_extent.x - 4.0,
+                16.0 + half_extent.x * 0.5,
+                center.y,
+            );
+            if !pitch_set {
+                config.pitch = -30.0;
+            }
+        }
+        // Default starts are picked in blocks, so they stay the same spot in
+        // the terrain at any --block-size
+        if !position_set {
+            config.position *= config.world.block_size;
+        }
+
+        if config.hover_brightness < 0.0 {
+            bail!("--hover-brightness must not be negative");
+        }
+
+        if config.crosshair.size > MAX_CROSSHAIR_SIZE {
+            bail!("--crosshair-size must be at most {MAX_CROSSHAIR_SIZE}");
+        }
+
+        if config.fps == 0 {
+            bail!("--fps must be at least 1");
+        }
+
+        if list_gpus {
+            for (index, info) in renderer::list_adapters(&config.gpu).iter().enumerate() {
+                println!(
+                    "{index}: {} ({:?}, {:?})",
+                    info.name, info.backend, info.device_type
+                );
+            }
+            return Ok(None);
+        }
+
+        Ok(Some(config))
+    }
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String> {
+    args.next()
+        .ok_or_else(|| anyhow!("missing value for '{flag}'"))
+}
+
+fn parse<T: std::str::FromStr>(value: &str, flag: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid value '{value}' for '{flag}'"))
+}
+
+fn parse_vec3(value: &str, flag: &str) -> Result<Vec3> {
+    let components = value
+        .split(',')
+        .map(|c| parse::<f32>(c.trim(), flag))
+        .collect::<Result<Vec<_>>>()?;
+    match components[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => bail!("invalid value '{value}' for '{flag}' (expected x,y,z)"),
+    }
+}
+
+/// Parses a `WIDTHxHEIGHT` size with both sides positive.
+fn parse_size(value: &str, flag: &str) -> Result<(u32, u32)> {
+    let size = value
+        .split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)));
+    match size {
+        Some((width, height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => bail!("invalid value '{value}' for '{flag}' (expected WIDTHxHEIGHT)"),
+    }
+}
+
+fn parse_power(name: &str) -> Result<wgpu::PowerPreference> {
+    Ok(match name.to_lowercase().as_str() {
+        "high" | "high-performance" => wgpu::PowerPreference::HighPerformance,
+        "low" | "low-power" => wgpu::PowerPreference::LowPower,
+        other => bail!("unknown power preference '{other}' (expected high or low)"),
+    })
+}
+
+fn parse_backend(name: &str) -> Result<wgpu::Backends> {
+    Ok(match name.to_lowercase().as_str() {
+        "vulkan" | "vk" => wgpu::Backends::VULKAN,
+        "metal" | "mtl" => wgpu::Backends::METAL,
+        "dx12" | "d3d12" => wgpu::Backends::DX12,
+        "gl" | "opengl" | "gles" => wgpu::Backends::GL,
+        "all" => wgpu::Backends::all(),
+        other => bail!("unknown backend '{other}' (expected vulkan, metal, dx12, gl or all)"),
+    })
+}
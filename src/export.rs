@@ -0,0 +1,96 @@
+use crate::geometry::{ChunkMesh, Vertex};
+use glam::Vec3;
+use std::io::Write;
+
+/// Writes meshes as one Wavefront OBJ with positions, normals, texture
+/// coordinates and triangle faces. Triangles are written counter-clockwise
+/// as seen from outside, whatever their winding in the mesh, so the model
+/// isn't inside-out in Blender or a slicer.
+pub fn write_obj(out: &mut impl Write, meshes: &[ChunkMesh]) -> std::io::Result<()> {
+    writeln!(out, "# Exported by minecraftty")?;
+
+    // OBJ indices are 1-based and shared across the whole file
+    let mut offset = 1;
+    for ChunkMesh {
+        vertices, indices, ..
+    } in meshes
+    {
+        for vertex in vertices {
+            let [x, y, z] = vertex.position;
+            writeln!(out, "v {x} {y} {z}")?;
+        }
+        for vertex in vertices {
+            // OBJ texture coordinates start at the bottom of the image
+            let [u, v] = vertex.tex_coord;
+            writeln!(out, "vt {u} {}", 1.0 - v)?;
+        }
+        for vertex in vertices {
+            let [x, y, z] = vertex.normal;
+            writeln!(out, "vn {x} {y} {z}")?;
+        }
+
+        for triangle in indices.chunks_exact(3) {
+            let [a, mut b, mut c] = [triangle[0], triangle[1], triangle[2]].map(usize::from);
+            if !faces_outward(vertices, a, b, c) {
+                std::mem::swap(&mut b, &mut c);
+            }
+            let [a, b, c] = [a, b, c].map(|i| i + offset);
+            writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+        }
+        offset += vertices.len();
+    }
+    Ok(())
+}
+
+/// Whether triangle `a`, `b`, `c` is counter-clockwise when viewed from the
+/// side its face normal points to.
+fn faces_outward(vertices: &[Vertex], a: usize, b: usize, c: usize) -> bool {
+    let position = |i: usize| Vec3::from(vertices[i].position);
+    let winding = (position(b) - position(a)).cross(position(c) - position(a));
+    winding.dot(Vec3::from(vertices[a].normal)) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_gen::{build_chunk_mesh, Block, BlockType, Chunk, WorldGenConfig};
+    use glam::IVec2;
+
+    #[test]
+    fn exported_cube_faces_outward() {
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(0, 0, 0, Some(Block::new(BlockType::Stone)));
+        let mesh = build_chunk_mesh(&chunk, &WorldGenConfig::default());
+
+        let mut out = Vec::new();
+        write_obj(&mut out, &[mesh]).unwrap();
+        let obj = String::from_utf8(out).unwrap();
+
+        let positions: Vec<Vec3> = obj
+            .lines()
+            .filter_map(|line| line.strip_prefix("v "))
+            .map(|line| {
+                let xyz: Vec<f32> = line.split(' ').map(|n| n.parse().unwrap()).collect();
+                Vec3::new(xyz[0], xyz[1], xyz[2])
+            })
+            .collect();
+        let faces: Vec<[usize; 3]> = obj
+            .lines()
+            .filter_map(|line| line.strip_prefix("f "))
+            .map(|line| {
+                let mut corners = line
+                    .split(' ')
+                    .map(|corner| corner.split('/').next().unwrap().parse::<usize>().unwrap() - 1);
+                [(); 3].map(|_| corners.next().unwrap())
+            })
+            .collect();
+        assert_eq!(faces.len(), 12);
+
+        // Every triangle's front side points away from the cube's center
+        let center = Vec3::splat(0.5);
+        for [a, b, c] in faces {
+            let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+            assert!(normal.dot(positions[a] - center) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How long each stage of one presented frame took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTiming {
+    /// Recording and submitting the GPU work.
+    pub render: Duration,
+    /// Waiting for the GPU and copying the frame back, with CPU-side effects.
+    pub readback: Duration,
+    /// Converting the frame to terminal output and writing it.
+    pub present: Duration,
+}
+
+impl FrameTiming {
+    pub fn total(&self) -> Duration {
+        self.render + self.readback + self.present
+    }
+}
+
+/// Frame timings collected over a run for `--stats`, summarized on exit.
+pub struct FrameStats {
+    /// When the first frame started, so time on the title screen and world
+    /// generation before it doesn't count.
+    started: Option<Instant>,
+    frames: Vec<FrameTiming>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            started: None,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, timing: FrameTiming) {
+        self.started
+            .get_or_insert_with(|| Instant::now() - timing.total());
+        self.frames.push(timing);
+    }
+
+    /// One stage's time for every frame.
+    fn times(&self, stage: impl Fn(&FrameTiming) -> Duration) -> Vec<Duration> {
+        self.frames.iter().map(stage).collect()
+    }
+
+    /// Frames presented per second of wall-clock time since the first one
+    /// started, so time spent waiting for input between frames counts too.
+    fn fps(&self) -> f64 {
+        let elapsed = self
+            .started
+            .map_or(Duration::ZERO, |started| started.elapsed());
+        self.frames.len() as f64 / elapsed.as_secs_f64()
+    }
+}
+
+/// Min, average, max and 95th percentile of `times`, which must not be empty.
+fn summarize(mut times: Vec<Duration>) -> [Duration; 4] {
+    times.sort_unstable();
+    let sum: Duration = times.iter().sum();
+    // Nearest rank: the smallest time at least 95% of frames don't exceed
+    let p95 = times[(times.len() * 95).div_ceil(100) - 1];
+    [
+        times[0],
+        sum / times.len() as u32,
+        times[times.len() - 1],
+        p95,
+    ]
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.frames.is_empty() {
+            return writeln!(f, "No frames presented");
+        }
+        writeln!(f, "{} frames, {:.1} FPS", self.frames.len(), self.fps())?;
+        writeln!(
+            f,
+            "{:<10}{:>10}{:>10}{:>10}{:>10}",
+            "ms", "min", "avg", "max", "p95"
+        )?;
+        let stages = [
+            ("render", self.times(|timing| timing.render)),
+            ("readback", self.times(|timing| timing.readback)),
+            ("present", self.times(|timing| timing.present)),
+            ("total", self.times(FrameTiming::total)),
+        ];
+        for (name, times) in stages {
+            write!(f, "{name:<10}")?;
+            for time in summarize(times) {
+                write!(f, "{:>10.2}", time.as_secs_f64() * 1000.0)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_reports_min_avg_max_and_p95() {
+        let times = (1..=20).map(Duration::from_millis).collect();
+        let [min, avg, max, p95] = summarize(times);
+        assert_eq!(min, Duration::from_millis(1));
+        assert_eq!(avg, Duration::from_micros(10_500));
+        assert_eq!(max, Duration::from_millis(20));
+        assert_eq!(p95, Duration::from_millis(19));
+
+        let mut stats = FrameStats::new();
+        assert_eq!(stats.to_string(), "No frames presented\n");
+        stats.record(FrameTiming {
+            render: Duration::from_millis(1),
+            readback: Duration::from_millis(2),
+            present: Duration::from_millis(3),
+        });
+        let summary = stats.to_string();
+        let total = summary.lines().last().unwrap();
+        assert!(
+            total.starts_with("total") && total.ends_with("6.00"),
+            "{summary}"
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use bytemuck::{Pod, Zeroable};
 use glam::Vec3;
+use std::collections::HashMap;
+use std::ops::Range;
 use wgpu::util::DeviceExt;
 
 #[repr(C)]
@@ -9,6 +11,10 @@ pub struct Vertex {
     pub position: [f32; 3],
     pub color: [f32; 3],
     pub tex_coord: [f32; 2],
+    /// Normal of the face this vertex belongs to.
+    pub normal: [f32; 3],
+    /// Average normal of the exposed faces meeting at this vertex's position.
+    pub smooth_normal: [f32; 3],
 }
 
 impl Vertex {
@@ -17,6 +23,8 @@ impl Vertex {
             position: position.to_array(),
             color: color.to_array(),
             tex_coord,
+            normal: [0.0; 3],
+            smooth_normal: [0.0; 3],
         }
     }
 
@@ -40,35 +48,290 @@ impl Vertex {
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
             ],
         }
     }
 }
 
+/// CPU-side mesh of a chunk. Opaque faces come first in `indices`, then the
+/// decorations from `decoration_start` and the translucent faces from
+/// `translucent_start`, so each group can be drawn with its own pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+    pub decoration_start: usize,
+    pub translucent_start: usize,
+}
+
+/// Merges bit-identical vertices and points the indices at the survivors.
+/// The triangles drawn are unchanged; only the vertex buffer shrinks.
+pub fn dedup_vertices(vertices: &[Vertex], indices: &[u16]) -> (Vec<Vertex>, Vec<u16>) {
+    let mut unique = Vec::new();
+    let mut seen: HashMap<[u32; 14], u16> = HashMap::new();
+    let remap: Vec<u16> = vertices
+        .iter()
+        .map(|vertex| {
+            *seen.entry(bytemuck::cast(*vertex)).or_insert_with(|| {
+                unique.push(*vertex);
+                (unique.len() - 1) as u16
+            })
+        })
+        .collect();
+    let indices = indices.iter().map(|&i| remap[i as usize]).collect();
+    (unique, indices)
+}
+
+/// A translucent face's two triangles and the center they're sorted by.
+#[derive(Debug, Clone, Copy)]
+struct TranslucentQuad {
+    indices: [u16; 6],
+    center: Vec3,
+}
+
+/// The translucent faces of `mesh`, six indices each.
+fn translucent_quads(mesh: &ChunkMesh) -> Vec<TranslucentQuad> {
+    mesh.indices[mesh.translucent_start..]
+        .chunks_exact(6)
+        .map(|triangles| {
+            let position = |&i: &u16| Vec3::from(mesh.vertices[i as usize].position);
+            let (min, max) = triangles
+                .iter()
+                .map(position)
+                .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), p| {
+                    (min.min(p), max.max(p))
+                });
+            TranslucentQuad {
+                indices: triangles.try_into().unwrap(),
+                center: (min + max) / 2.0,
+            }
+        })
+        .collect()
+}
+
+/// Orders quads farthest from `eye` first, so blending each over the ones
+/// already drawn layers them correctly.
+fn sort_back_to_front(quads: &mut [TranslucentQuad], eye: Vec3) {
+    quads.sort_by(|a, b| {
+        let distance = |quad: &TranslucentQuad| quad.center.distance_squared(eye);
+        distance(b).total_cmp(&distance(a))
+    });
+}
+
 pub struct Geometry {
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
+    pub vertex_count: u32,
     pub index_count: u32,
+    /// First index of the decorations; `translucent_start` when there are none.
+    pub decoration_start: u32,
+    /// First index of the translucent faces; `index_count` when there are none.
+    pub translucent_start: u32,
+    /// Translucent faces in the order the index buffer holds them.
+    translucent: Vec<TranslucentQuad>,
+    /// Camera position the translucent faces were last sorted for.
+    sorted_for: Option<Vec3>,
 }
 
 impl Geometry {
-    pub fn new(device: &wgpu::Device, vertices: &[Vertex], indices: &[u16]) -> Result<Self> {
+    /// Uploads `mesh`. A mesh without faces, such as an all-air chunk, gets
+    /// zero-sized buffers that are never bound.
+    pub fn new(device: &wgpu::Device, mesh: &ChunkMesh) -> Result<Self> {
+        let (vertices, indices) = (&mesh.vertices, &mesh.indices);
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices),
+            contents: bytemuck::cast_slice(vertices.as_slice()),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(indices),
-            usage: wgpu::BufferUsages::INDEX,
+            contents: bytemuck::cast_slice(indices.as_slice()),
+            // Rewritten when the translucent faces are re-sorted
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
         });
 
         Ok(Self {
             vertex_buffer,
             index_buffer,
+            vertex_count: vertices.len() as u32,
             index_count: indices.len() as u32,
+            decoration_start: mesh.decoration_start as u32,
+            translucent_start: mesh.translucent_start as u32,
+            translucent: translucent_quads(mesh),
+            sorted_for: None,
         })
     }
+
+    /// Reorders the translucent faces back to front as seen from `eye`, if
+    /// it moved since the last sort. Faces are sorted by their centers, so
+    /// faces that cut through each other can still blend in the wrong order.
+    pub fn sort_translucent(&mut self, queue: &wgpu::Queue, eye: Vec3) {
+        if self.translucent.is_empty() || self.sorted_for == Some(eye) {
+            return;
+        }
+        sort_back_to_front(&mut self.translucent, eye);
+        let indices: Vec<u16> = self
+            .translucent
+            .iter()
+            .flat_map(|quad| quad.indices)
+            .collect();
+        // Six 2-byte indices per face keep the offset 4-byte aligned
+        let offset = self.translucent_start as u64 * 2;
+        queue.write_buffer(&self.index_buffer, offset, bytemuck::cast_slice(&indices));
+        self.sorted_for = Some(eye);
+    }
+
+    /// Whether there are no faces to draw.
+    pub fn is_empty(&self) -> bool {
+        self.index_count == 0
+    }
+
+    /// Index ranges of the opaque faces, decorations and translucent faces.
+    pub fn opaque_indices(&self) -> Range<u32> {
+        0..self.decoration_start
+    }
+
+    pub fn decoration_indices(&self) -> Range<u32> {
+        self.decoration_start..self.translucent_start
+    }
+
+    pub fn translucent_indices(&self) -> Range<u32> {
+        self.translucent_start..self.index_count
+    }
+
+    /// Binds the buffers and draws the triangles in `indices`, if any.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, indices: Range<u32>) {
+        if indices.is_empty() {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(indices, 0, 0..1);
+    }
+
+    /// Size of the vertex and index buffers in bytes.
+    pub fn size_bytes(&self) -> u64 {
+        self.vertex_buffer.size() + self.index_buffer.size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{GpuOptions, Renderer};
+    use crate::world_gen::{
+        build_chunk_mesh, generate_chunk, Block, BlockType, Chunk, WorldGenConfig,
+    };
+    use glam::IVec2;
+
+    #[test]
+    fn dedup_keeps_indices_and_drops_vertices() {
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(0, 0, 0, Some(Block::new(BlockType::Stone)));
+        let mesh = build_chunk_mesh(&chunk, &WorldGenConfig::default());
+        let (cube, cube_indices) = (mesh.vertices, mesh.indices);
+
+        // Two copies of the cube share every vertex
+        let mut vertices = cube.clone();
+        vertices.extend(&cube);
+        let mut indices = cube_indices.clone();
+        indices.extend(cube_indices.iter().map(|&i| i + cube.len() as u16));
+
+        let (deduped, deduped_indices) = dedup_vertices(&vertices, &indices);
+        assert_eq!(deduped_indices.len(), indices.len());
+        assert_eq!(deduped.len(), cube.len());
+        for (&before, &after) in indices.iter().zip(&deduped_indices) {
+            let [a, b] = [vertices[before as usize], deduped[after as usize]];
+            assert_eq!(bytemuck::bytes_of(&a), bytemuck::bytes_of(&b));
+        }
+    }
+
+    #[test]
+    fn dedup_shrinks_generated_chunk_meshes_slightly() {
+        let config = WorldGenConfig::default();
+        let chunk = generate_chunk(IVec2::ZERO, &config);
+        let mesh = build_chunk_mesh(&chunk, &config);
+        let deduped = build_chunk_mesh(
+            &chunk,
+            &WorldGenConfig {
+                dedup_vertices: true,
+                ..config
+            },
+        );
+        // Neighbouring faces differ in texture coordinates, so only a few
+        // corners merge (1720 to 1700 vertices today)
+        assert!(deduped.vertices.len() < mesh.vertices.len());
+        assert!(deduped.vertices.len() * 50 > mesh.vertices.len() * 49);
+        assert_eq!(deduped.indices.len(), mesh.indices.len());
+        let corner =
+            |mesh: &ChunkMesh, i: u16| bytemuck::bytes_of(&mesh.vertices[i as usize]).to_vec();
+        for (&before, &after) in mesh.indices.iter().zip(&deduped.indices) {
+            assert_eq!(corner(&mesh, before), corner(&deduped, after));
+        }
+    }
+
+    #[test]
+    fn translucent_faces_sort_back_to_front() {
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(0, 0, 0, Some(Block::new(BlockType::Glass)));
+        chunk.set(3, 0, 0, Some(Block::new(BlockType::Glass)));
+        let mesh = build_chunk_mesh(&chunk, &WorldGenConfig::default());
+        let mut quads = translucent_quads(&mesh);
+        assert_eq!(quads.len(), 12);
+
+        // From either side, the far cube's faces come first
+        sort_back_to_front(&mut quads, Vec3::new(-5.0, 0.5, 0.5));
+        assert!(quads[..6].iter().all(|quad| quad.center.x >= 3.0));
+        sort_back_to_front(&mut quads, Vec3::new(9.0, 0.5, 0.5));
+        assert!(quads[..6].iter().all(|quad| quad.center.x <= 1.0));
+        let distances: Vec<f32> = quads
+            .iter()
+            .map(|quad| quad.center.distance(Vec3::new(9.0, 0.5, 0.5)))
+            .collect();
+        assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn empty_mesh_uploads_and_draws_nothing() {
+        let Ok(renderer) = pollster::block_on(Renderer::new(4, 4, &GpuOptions::default())) else {
+            eprintln!("no GPU adapter, skipping empty geometry test");
+            return;
+        };
+        let device = &renderer.device;
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let geometry = Geometry::new(device, &ChunkMesh::default()).unwrap();
+        assert!(geometry.is_empty());
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &renderer.texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            geometry.draw(&mut render_pass, geometry.opaque_indices());
+            geometry.draw(&mut render_pass, geometry.translucent_indices());
+        }
+        renderer.queue.submit(Some(encoder.finish()));
+        let error = pollster::block_on(device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+    }
 }
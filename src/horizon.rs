@@ -0,0 +1,78 @@
+use crate::geometry::Vertex;
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+
+/// A flat plane at sea level that follows the camera, drawn under the terrain
+/// so the world seems to reach the horizon past the loaded chunks.
+#[derive(Debug, Clone, Copy)]
+pub struct Horizon {
+    /// Distance from the camera to the plane's edges, in blocks. 0 disables it.
+    pub distance: f32,
+    /// Color of the plane, roughly the average of the grass texture.
+    pub color: [u8; 3],
+}
+
+impl Default for Horizon {
+    fn default() -> Self {
+        Self {
+            distance: 0.0,
+            color: [0x77, 0xc8, 0x4a],
+        }
+    }
+}
+
+/// GPU buffers for the horizon plane. The quad is rewritten every frame to
+/// stay centered under the camera.
+pub struct HorizonGeometry {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+}
+
+impl HorizonGeometry {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Horizon Vertex Buffer"),
+            size: (std::mem::size_of::<Vertex>() * 4) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Horizon Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    /// Moves the quad under `eye` at height `sea_level`.
+    pub fn update(&self, queue: &wgpu::Queue, horizon: &Horizon, eye: Vec3, sea_level: f32) {
+        let color = Vec3::from(horizon.color.map(srgb_to_linear));
+        let d = horizon.distance;
+        let corners = [(-d, -d), (-d, d), (d, d), (d, -d)];
+        let vertices = corners.map(|(x, z)| {
+            let position = Vec3::new(eye.x + x, sea_level, eye.z + z);
+            let mut vertex = Vertex::new(position, color, [0.0; 2]);
+            vertex.normal = Vec3::Y.to_array();
+            vertex.smooth_normal = vertex.normal;
+            vertex
+        });
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+    }
+}
+
+/// Converts an sRGB channel to the linear value the shader works with.
+fn srgb_to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,215 @@
+use crate::present::{draw_text, pixel, Viewport};
+use crate::world_gen::BlockType;
+use anyhow::{bail, Result};
+use std::io::Write;
+use std::str::FromStr;
+
+const TEXT_COLOR: [u8; 3] = [255, 255, 255];
+const PANEL_COLOR: [u8; 3] = [40, 40, 40];
+
+/// Shape of the crosshair drawn over the center of the view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CrosshairStyle {
+    /// A "+" in the center cell.
+    #[default]
+    Plus,
+    /// A box-drawing cross, "┼" with arms of "─" and "│".
+    Lines,
+    /// A single dot.
+    Dot,
+    /// A ring of box-drawing lines with rounded corners around a dot.
+    Circle,
+}
+
+impl FromStr for CrosshairStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "plus" => CrosshairStyle::Plus,
+            "lines" => CrosshairStyle::Lines,
+            "dot" => CrosshairStyle::Dot,
+            "circle" => CrosshairStyle::Circle,
+            other => {
+                bail!("unknown crosshair style '{other}' (expected plus, lines, dot or circle)")
+            }
+        })
+    }
+}
+
+/// How the crosshair is drawn, see `--crosshair`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Crosshair {
+    pub style: CrosshairStyle,
+    /// Arm length of `Lines` and radius of `Circle`, in rows. Columns are
+    /// about half as wide as rows are tall, so they reach twice as many
+    /// columns across.
+    pub size: u32,
+    pub color: [u8; 3],
+}
+
+impl Default for Crosshair {
+    fn default() -> Self {
+        Self {
+            style: CrosshairStyle::default(),
+            size: 1,
+            color: TEXT_COLOR,
+        }
+    }
+}
+
+impl Crosshair {
+    /// Glyphs making up the crosshair, by (column, row) offset from the
+    /// center cell.
+    fn cells(&self) -> Vec<(i32, i32, char)> {
+        let rows = self.size as i32;
+        let cols = rows * 2;
+        match self.style {
+            CrosshairStyle::Plus => vec![(0, 0, '+')],
+            CrosshairStyle::Dot => vec![(0, 0, '•')],
+            CrosshairStyle::Lines => {
+                let mut cells = vec![(0, 0, '┼')];
+                for col in 1..=cols {
+                    cells.extend([(-col, 0, '─'), (col, 0, '─')]);
+                }
+                for row in 1..=rows {
+                    cells.extend([(0, -row, '│'), (0, row, '│')]);
+                }
+                cells
+            }
+            // At terminal resolution a rounded box is as close to a circle
+            // as the glyphs get
+            CrosshairStyle::Circle if rows == 0 => vec![(0, 0, '·')],
+            CrosshairStyle::Circle => {
+                let mut cells = vec![
+                    (0, 0, '·'),
+                    (-cols, -rows, '╭'),
+                    (cols, -rows, '╮'),
+                    (-cols, rows, '╰'),
+                    (cols, rows, '╯'),
+                ];
+                for col in 1 - cols..cols {
+                    cells.extend([(col, -rows, '─'), (col, rows, '─')]);
+                }
+                for row in 1 - rows..rows {
+                    cells.extend([(-cols, row, '│'), (cols, row, '│')]);
+                }
+                cells
+            }
+        }
+    }
+
+    /// Draws the crosshair around the center cell, each glyph keeping the
+    /// color of the cell it covers as background. Glyphs falling outside
+    /// the visible `viewport` are left out.
+    pub fn draw(
+        &self,
+        out: &mut impl Write,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        cell: (u32, u32),
+        viewport: Viewport,
+    ) -> std::io::Result<()> {
+        let (cell_width, cell_height) = cell;
+        let center_col = (width / 2 / cell_width) as i32;
+        let center_row = (height / 2 / cell_height) as i32;
+        for (col, row, glyph) in self.cells() {
+            let (Ok(col), Ok(row)) = (
+                u32::try_from(center_col + col),
+                u32::try_from(center_row + row),
+            ) else {
+                continue;
+            };
+            if col >= viewport.cols || row >= viewport.rows {
+                continue;
+            }
+            if let Some(bg) = pixel(pixels, width, col * cell_width, row * cell_height) {
+                let text = glyph.to_string();
+                let (row, col) = (viewport.row + row, viewport.col + col);
+                draw_text(out, row, col, &text, self.color, bg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Draws the block selection bar centered on the viewport's bottom row.
+/// Slots that don't fit its width are left out.
+pub fn draw_hotbar(
+    out: &mut impl Write,
+    viewport: Viewport,
+    selected: BlockType,
+) -> std::io::Result<()> {
+    if viewport.rows == 0 {
+        return Ok(());
+    }
+    let row = viewport.row + viewport.rows - 1;
+    let width = viewport.cols;
+    let slots: Vec<String> = BlockType::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, block_type)| format!(" {} {} ", i + 1, block_type.name()))
+        .collect();
+    let total: usize = slots.iter().map(|slot| slot.chars().count()).sum();
+    let mut col = width.saturating_sub(total as u32) / 2;
+
+    for (slot, &block_type) in slots.iter().zip(BlockType::ALL.iter()) {
+        let len = slot.chars().count() as u32;
+        if col + len > width {
+            break;
+        }
+        // The selected slot is drawn with inverted colors
+        let (fg, bg) = if block_type == selected {
+            (PANEL_COLOR, TEXT_COLOR)
+        } else {
+            (TEXT_COLOR, PANEL_COLOR)
+        };
+        draw_text(out, row, viewport.col + col, slot, fg, bg)?;
+        col += len;
+    }
+    Ok(())
+}
+
+/// Draws lines of text as a panel in the viewport's top-left corner, starting
+/// `row` rows down. Lines are padded to the same width and cut to the viewport.
+pub fn draw_panel(
+    out: &mut impl Write,
+    row: u32,
+    lines: &[String],
+    viewport: Viewport,
+) -> std::io::Result<()> {
+    let panel_width = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (line_row, line) in (row..viewport.rows).zip(lines) {
+        let text: String = format!(" {line:panel_width$} ")
+            .chars()
+            .take(viewport.cols as usize)
+            .collect();
+        let line_row = viewport.row + line_row;
+        draw_text(out, line_row, viewport.col, &text, TEXT_COLOR, PANEL_COLOR)?;
+    }
+    Ok(())
+}
+
+/// Draws a one-line message in the viewport's top-left corner, cut to its width.
+pub fn draw_notice(out: &mut impl Write, text: &str, viewport: Viewport) -> std::io::Result<()> {
+    if viewport.rows == 0 {
+        return Ok(());
+    }
+    let text: String = format!(" {text} ")
+        .chars()
+        .take(viewport.cols as usize)
+        .collect();
+    draw_text(
+        out,
+        viewport.row,
+        viewport.col,
+        &text,
+        TEXT_COLOR,
+        PANEL_COLOR,
+    )
+}
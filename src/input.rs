@@ -0,0 +1,337 @@
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEventKind};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Camera movement a key is bound to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Step along the view (or the heading while walking) and sideways.
+    Move {
+        forward: f32,
+        right: f32,
+    },
+    /// Move up or down.
+    Rise(f32),
+    /// Turn the camera in place.
+    Look {
+        right: f32,
+        up: f32,
+    },
+    /// Turn the camera around the point under the crosshair, keeping it
+    /// centered.
+    Orbit {
+        right: f32,
+        up: f32,
+    },
+    /// Lean the view right (positive) or left around its forward axis.
+    Roll(f32),
+    Jump,
+}
+
+impl Action {
+    /// What the action does, for listing the controls.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Action::Move { forward, .. } if forward > 0.0 => "Move forward",
+            Action::Move { forward, .. } if forward < 0.0 => "Move back",
+            Action::Move { right, .. } if right < 0.0 => "Move left",
+            Action::Move { .. } => "Move right",
+            Action::Rise(distance) if distance > 0.0 => "Move up",
+            Action::Rise(_) => "Move down",
+            Action::Look { right, .. } if right < 0.0 => "Look left",
+            Action::Look { right, .. } if right > 0.0 => "Look right",
+            Action::Look { up, .. } if up > 0.0 => "Look up",
+            Action::Look { .. } => "Look down",
+            Action::Orbit { right, .. } if right < 0.0 => "Orbit left",
+            Action::Orbit { right, .. } if right > 0.0 => "Orbit right",
+            Action::Orbit { up, .. } if up > 0.0 => "Orbit up",
+            Action::Orbit { .. } => "Orbit down",
+            Action::Roll(steps) if steps < 0.0 => "Lean left",
+            Action::Roll(_) => "Lean right",
+            Action::Jump => "Jump while walking",
+        }
+    }
+
+    /// The action repeated `factor` times over, for keys held down. A jump
+    /// happens once per press however long it's held, so it's `None`.
+    pub fn scaled(self, factor: f32) -> Option<Action> {
+        Some(match self {
+            Action::Move { forward, right } => Action::Move {
+                forward: forward * factor,
+                right: right * factor,
+            },
+            Action::Rise(distance) => Action::Rise(distance * factor),
+            Action::Look { right, up } => Action::Look {
+                right: right * factor,
+                up: up * factor,
+            },
+            Action::Orbit { right, up } => Action::Orbit {
+                right: right * factor,
+                up: up * factor,
+            },
+            Action::Roll(steps) => Action::Roll(steps * factor),
+            Action::Jump => return None,
+        })
+    }
+}
+
+/// Movement keys held down, on terminals that report key releases. Until a
+/// key's first release arrives there's no telling whether the terminal
+/// reports it (some only do for some keys), so each of its presses and
+/// repeats takes a single step as usual.
+#[derive(Debug, Default)]
+pub struct HeldKeys {
+    /// Keys a release has been seen for.
+    releases_reported: HashSet<KeyCode>,
+    /// Keys down, and whether they've moved the camera yet.
+    keys: Vec<(KeyCode, bool)>,
+}
+
+impl HeldKeys {
+    /// Tracks a key event. Returns whether the key should take a single
+    /// step now: always without release reports, and for a tap released
+    /// before any frame moved it.
+    pub fn update(&mut self, code: KeyCode, kind: KeyEventKind) -> bool {
+        match kind {
+            KeyEventKind::Release => {
+                self.releases_reported.insert(code);
+                let tapped = self.keys.contains(&(code, false));
+                self.keys.retain(|&(key, _)| key != code);
+                tapped
+            }
+            _ if !self.releases_reported.contains(&code) => true,
+            KeyEventKind::Press => {
+                if !self.keys.iter().any(|&(key, _)| key == code) {
+                    self.keys.push((code, false));
+                }
+                false
+            }
+            // Held keys already move every frame
+            KeyEventKind::Repeat => false,
+        }
+    }
+
+    /// Lets go of every key, for when their releases won't arrive.
+    pub fn release_all(&mut self) {
+        self.keys.clear();
+    }
+
+    /// The keys down, to move by for a frame.
+    pub fn advance(&mut self) -> Vec<KeyCode> {
+        self.keys
+            .iter_mut()
+            .map(|(key, moved)| {
+                *moved = true;
+                *key
+            })
+            .collect()
+    }
+}
+
+/// Look steps per terminal cell the mouse moves, so sweeping across a
+/// 100-column view turns the camera most of the way around.
+const MOUSE_LOOK_STEPS: f32 = 0.25;
+
+/// Keys some profile binds to a movement, in the order controls are listed.
+const MOVEMENT_KEYS: [KeyCode; 17] = [
+    KeyCode::Char('w'),
+    KeyCode::Char('s'),
+    KeyCode::Char('a'),
+    KeyCode::Char('d'),
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Char('q'),
+    KeyCode::Char('e'),
+    KeyCode::Char('h'),
+    KeyCode::Char('l'),
+    KeyCode::Char('k'),
+    KeyCode::Char('j'),
+    KeyCode::Char('['),
+    KeyCode::Char(']'),
+    KeyCode::Char(' '),
+];
+
+/// Which keys move the camera. Keys for editing blocks and toggling views are
+/// the same in every profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputProfile {
+    /// WASD or the arrows walk and fly, H/J/K/L or the mouse look around.
+    #[default]
+    Game,
+    /// H/J/K/L orbit the targeted point and the arrows pan the view, for
+    /// looking over terrain from every side.
+    Inspection,
+}
+
+impl InputProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            InputProfile::Game => "game",
+            InputProfile::Inspection => "inspection",
+        }
+    }
+
+    /// The profile to switch to with Tab.
+    pub fn next(self) -> InputProfile {
+        match self {
+            InputProfile::Game => InputProfile::Inspection,
+            InputProfile::Inspection => InputProfile::Game,
+        }
+    }
+
+    /// Every movement key of the profile with its action.
+    pub fn bindings(self) -> Vec<(KeyCode, Action)> {
+        MOVEMENT_KEYS
+            .iter()
+            .filter_map(|&code| Some((code, self.action(code)?)))
+            .collect()
+    }
+
+    /// The turn for the mouse moving `cols` cells right and `rows` down, if
+    /// the profile looks around with the mouse. Cells are coarse, so each
+    /// is a fraction of a look step.
+    pub fn mouse_action(self, cols: i32, rows: i32) -> Option<Action> {
+        match self {
+            InputProfile::Game => Some(Action::Look {
+                right: cols as f32 * MOUSE_LOOK_STEPS,
+                up: -rows as f32 * MOUSE_LOOK_STEPS,
+            }),
+            InputProfile::Inspection => None,
+        }
+    }
+
+    /// The movement bound to `code`, if any.
+    pub fn action(self, code: KeyCode) -> Option<Action> {
+        use InputProfile::{Game, Inspection};
+
+        let step = |forward, right| Action::Move { forward, right };
+        let look = |right, up| Action::Look { right, up };
+        let orbit = |right, up| Action::Orbit { right, up };
+        let action = match (self, code) {
+            (_, KeyCode::Char('w')) => step(0.5, 0.0),
+            (_, KeyCode::Char('s')) => step(-0.5, 0.0),
+            (_, KeyCode::Char('a')) => step(0.0, -0.5),
+            (_, KeyCode::Char('d')) => step(0.0, 0.5),
+            (_, KeyCode::Char('q')) => Action::Rise(-0.5),
+            (_, KeyCode::Char('e')) => Action::Rise(0.5),
+            (_, KeyCode::Char(' ')) => Action::Jump,
+            (_, KeyCode::Char('[')) => Action::Roll(-1.0),
+            (_, KeyCode::Char(']')) => Action::Roll(1.0),
+
+            (Game, KeyCode::Up) => step(0.5, 0.0),
+            (Game, KeyCode::Down) => step(-0.5, 0.0),
+            (Game, KeyCode::Left) => step(0.0, -0.5),
+            (Game, KeyCode::Right) => step(0.0, 0.5),
+            (Game, KeyCode::Char('h')) => look(-1.0, 0.0),
+            (Game, KeyCode::Char('l')) => look(1.0, 0.0),
+            (Game, KeyCode::Char('j')) => look(0.0, 1.0),
+            (Game, KeyCode::Char('k')) => look(0.0, -1.0),
+
+            (Inspection, KeyCode::Up) => Action::Rise(0.5),
+            (Inspection, KeyCode::Down) => Action::Rise(-0.5),
+            (Inspection, KeyCode::Left) => step(0.0, -0.5),
+            (Inspection, KeyCode::Right) => step(0.0, 0.5),
+            (Inspection, KeyCode::Char('h')) => orbit(-1.0, 0.0),
+            (Inspection, KeyCode::Char('l')) => orbit(1.0, 0.0),
+            (Inspection, KeyCode::Char('j')) => orbit(0.0, 1.0),
+            (Inspection, KeyCode::Char('k')) => orbit(0.0, -1.0),
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+impl FromStr for InputProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "game" => InputProfile::Game,
+            "inspection" | "inspect" => InputProfile::Inspection,
+            other => bail!("unknown input profile '{other}' (expected game or inspection)"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_share_wasd_and_differ_on_arrows_and_hjkl() {
+        for profile in [InputProfile::Game, InputProfile::Inspection] {
+            assert_eq!(
+                profile.action(KeyCode::Char('w')),
+                Some(Action::Move {
+                    forward: 0.5,
+                    right: 0.0
+                })
+            );
+            // Toggles and editing keys are left to the caller
+            assert_eq!(profile.action(KeyCode::Char('b')), None);
+        }
+        assert!(matches!(
+            InputProfile::Game.action(KeyCode::Char('h')),
+            Some(Action::Look { .. })
+        ));
+        assert!(matches!(
+            InputProfile::Inspection.action(KeyCode::Char('h')),
+            Some(Action::Orbit { .. })
+        ));
+        assert_eq!(
+            InputProfile::Inspection.action(KeyCode::Up),
+            Some(Action::Rise(0.5))
+        );
+        assert_eq!(
+            InputProfile::Game.mouse_action(4, -2),
+            Some(Action::Look {
+                right: 4.0 * MOUSE_LOOK_STEPS,
+                up: 2.0 * MOUSE_LOOK_STEPS
+            })
+        );
+        assert_eq!(InputProfile::Inspection.mouse_action(4, -2), None);
+        assert_eq!(InputProfile::Game.next().next(), InputProfile::Game);
+    }
+
+    #[test]
+    fn held_keys_step_per_event_until_releases_are_reported() {
+        use KeyEventKind::{Press, Release, Repeat};
+        let w = KeyCode::Char('w');
+        let mut held = HeldKeys::default();
+
+        // Terminals without release events only send presses
+        assert!(held.update(w, Press));
+        assert!(held.update(w, Press));
+        assert!(held.advance().is_empty());
+
+        // Once a release shows up, held keys move every frame instead
+        assert!(!held.update(w, Release));
+        assert!(!held.update(w, Press));
+        assert!(!held.update(w, Repeat));
+        assert_eq!(held.advance(), [w]);
+        assert!(!held.update(w, Release));
+        assert!(held.advance().is_empty());
+
+        // A tap too quick for any frame still takes its step
+        assert!(!held.update(w, Press));
+        assert!(held.update(w, Release));
+
+        // A key whose release never arrives keeps stepping per event rather
+        // than being held forever
+        let e = KeyCode::Char('e');
+        assert!(held.update(e, Press));
+        assert!(held.update(e, Repeat));
+        assert!(held.advance().is_empty());
+
+        // Losing focus lets go of keys whose releases went elsewhere
+        assert!(!held.update(w, Press));
+        held.release_all();
+        assert!(held.advance().is_empty());
+
+        assert_eq!(Action::Jump.scaled(2.0), None);
+        assert_eq!(Action::Rise(0.5).scaled(0.1), Some(Action::Rise(0.05)));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::world_gen::BlockType;
+use anyhow::{anyhow, Result};
+use glam::IVec3;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Progress added by each break-key event on a block of hardness 1. Holding
+/// the key relies on the terminal's key repeat, so such a block takes roughly
+/// ten repeats to break.
+const BREAK_STEP: f32 = 0.1;
+
+/// Progress is discarded when the break key hasn't been seen for this long.
+const BREAK_TIMEOUT: Duration = Duration::from_millis(1000);
+
+struct BreakState {
+    target: IVec3,
+    progress: f32,
+    last_hit: Instant,
+}
+
+/// How long each block type takes to break. Break progress per key event is
+/// divided by the hardness, so 2.0 takes twice as long as 1.0 and 0 breaks
+/// instantly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hardness {
+    values: [f32; BlockType::ALL.len()],
+}
+
+impl Default for Hardness {
+    fn default() -> Self {
+        Self {
+            values: BlockType::ALL.map(BlockType::hardness),
+        }
+    }
+}
+
+impl Hardness {
+    /// Types missing from the table, such as bedrock, can't be overridden
+    /// and keep their built-in hardness.
+    pub fn get(&self, block_type: BlockType) -> f32 {
+        self.values
+            .get(block_type as usize)
+            .copied()
+            .unwrap_or(block_type.hardness())
+    }
+
+    pub fn set(&mut self, block_type: BlockType, hardness: f32) {
+        self.values[block_type as usize] = hardness;
+    }
+}
+
+/// Parses overrides of the default table, e.g. `stone=3,dirt=0.2`.
+impl FromStr for Hardness {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut table = Hardness::default();
+        for entry in s.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let (name, value) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("invalid hardness '{entry}' (expected block=value)"))?;
+            let hardness: f32 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|hardness: &f32| *hardness >= 0.0)
+                .ok_or_else(|| anyhow!("invalid hardness value '{value}' for {name}"))?;
+            table.set(name.parse()?, hardness);
+        }
+        Ok(table)
+    }
+}
+
+/// Tracks break progress on the targeted block across frames.
+pub struct BlockBreaker {
+    state: Option<BreakState>,
+    hardness: Hardness,
+}
+
+impl BlockBreaker {
+    pub fn new(hardness: Hardness) -> Self {
+        Self {
+            state: None,
+            hardness,
+        }
+    }
+
+    /// Registers a break-key event on `target`, a block of `block_type`.
+    /// Returns true once the accumulated progress is complete and the block
+    /// should be removed.
+    pub fn hit(&mut self, target: IVec3, block_type: BlockType, now: Instant) -> bool {
+        let step = BREAK_STEP / self.hardness.get(block_type);
+        let progress = match &self.state {
+            Some(state) if state.target == target => state.progress + step,
+            _ => step,
+        };
+
+        if progress >= 1.0 {
+            self.state = None;
+            return true;
+        }
+
+        self.state = Some(BreakState {
+            target,
+            progress,
+            last_hit: now,
+        });
+        false
+    }
+
+    /// Drops progress when the crosshair moved to another block or the break
+    /// key was released.
+    pub fn update(&mut self, target: Option<IVec3>, now: Instant) {
+        if let Some(state) = &self.state {
+            if Some(state.target) != target || now - state.last_hit > BREAK_TIMEOUT {
+                self.state = None;
+            }
+        }
+    }
+
+    /// Discards any progress, e.g. when the world changed underneath it.
+    pub fn cancel(&mut self) {
+        self.state = None;
+    }
+
+    /// The block being broken and its progress in `[0, 1)`.
+    pub fn progress(&self) -> Option<(IVec3, f32)> {
+        self.state
+            .as_ref()
+            .map(|state| (state.target, state.progress))
+    }
+}
@@ -0,0 +1,163 @@
+use crate::world_gen::{Block, CHUNK_SIZE_X, CHUNK_SIZE_Z, WORLD_HEIGHT};
+use glam::{IVec2, IVec3};
+use std::collections::VecDeque;
+
+/// Sky light of open sky. Light loses a level for every block it spreads
+/// away from a column open to the sky, so it reaches this many blocks under
+/// an overhang. Kept below the chunk width, so a block only changes the
+/// light of its own chunk and the ones beside it.
+pub const MAX_SKY_LIGHT: u8 = 6;
+
+/// How far past a chunk's edges, in blocks, an edit can change the sky light
+/// its faces look into.
+pub const SKY_LIGHT_REACH: i32 = MAX_SKY_LIGHT as i32;
+
+/// Whether a block stops sky light: anything solid but glass, so tree
+/// canopies still cast shadows.
+fn blocks_light(block: Option<Block>) -> bool {
+    block.is_some_and(|block| block.block_type.is_solid() && !block.block_type.is_transparent())
+}
+
+/// Sky light levels of the cells in and around one chunk.
+pub struct SkyLight {
+    /// Block-space position of the first cell covered.
+    start: IVec3,
+    /// Number of cells covered along each axis.
+    size: IVec3,
+    levels: Vec<u8>,
+}
+
+impl SkyLight {
+    /// Spreads sky light through the chunk at `chunk_pos` and the blocks
+    /// within `SKY_LIGHT_REACH` of it, looked up in block space with
+    /// `block_at`. Cells open to the sky get `MAX_SKY_LIGHT` and the light
+    /// spreads from them in every direction but through light-blocking
+    /// blocks.
+    pub fn spread(chunk_pos: IVec2, block_at: impl Fn(IVec3) -> Option<Block>) -> Self {
+        let reach = IVec3::new(SKY_LIGHT_REACH, 0, SKY_LIGHT_REACH);
+        let origin = IVec3::new(
+            chunk_pos.x * CHUNK_SIZE_X as i32,
+            0,
+            chunk_pos.y * CHUNK_SIZE_Z as i32,
+        );
+        let mut light = Self {
+            start: origin - reach,
+            size: IVec3::new(
+                CHUNK_SIZE_X as i32,
+                WORLD_HEIGHT as i32,
+                CHUNK_SIZE_Z as i32,
+            ) + reach * 2,
+            levels: Vec::new(),
+        };
+        let cells = (light.size.x * light.size.y * light.size.z) as usize;
+        light.levels = vec![0; cells];
+        // Looked up once, since spreading visits most cells several times
+        let mut blocked = vec![false; cells];
+        for x in 0..light.size.x {
+            for z in 0..light.size.z {
+                for y in 0..light.size.y {
+                    let cell = light.start + IVec3::new(x, y, z);
+                    blocked[light.index(cell).unwrap()] = blocks_light(block_at(cell));
+                }
+            }
+        }
+
+        // Open sky reaches down each column to its first light-blocking block
+        let mut queue = VecDeque::new();
+        for x in 0..light.size.x {
+            for z in 0..light.size.z {
+                for y in (0..light.size.y).rev() {
+                    let cell = light.start + IVec3::new(x, y, z);
+                    let i = light.index(cell).unwrap();
+                    if blocked[i] {
+                        break;
+                    }
+                    light.levels[i] = MAX_SKY_LIGHT;
+                    queue.push_back(cell);
+                }
+            }
+        }
+
+        // Every source starts at the same level, so visiting cells in order
+        // of distance sets each one from its nearest source
+        let steps = [
+            IVec3::X,
+            IVec3::NEG_X,
+            IVec3::Y,
+            IVec3::NEG_Y,
+            IVec3::Z,
+            IVec3::NEG_Z,
+        ];
+        while let Some(cell) = queue.pop_front() {
+            let level = light.levels[light.index(cell).unwrap()];
+            if level <= 1 {
+                continue;
+            }
+            for step in steps {
+                let next = cell + step;
+                let Some(i) = light.index(next) else {
+                    continue;
+                };
+                if blocked[i] || light.levels[i] >= level - 1 {
+                    continue;
+                }
+                light.levels[i] = level - 1;
+                queue.push_back(next);
+            }
+        }
+        light
+    }
+
+    fn index(&self, cell: IVec3) -> Option<usize> {
+        let local = cell - self.start;
+        let inside = local.cmpge(IVec3::ZERO).all() && local.cmplt(self.size).all();
+        inside.then(|| ((local.x * self.size.z + local.z) * self.size.y + local.y) as usize)
+    }
+
+    /// Sky light of the cell at block-space `cell`, 0 inside a
+    /// light-blocking block. Cells above the world are open sky, and so are
+    /// cells beside it past the area spread through.
+    pub fn level(&self, cell: IVec3) -> u8 {
+        match self.index(cell) {
+            Some(i) => self.levels[i],
+            None if cell.y < 0 => 0,
+            None => MAX_SKY_LIGHT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_gen::BlockType;
+    use std::collections::HashMap;
+
+    #[test]
+    fn light_fades_under_a_roof_and_crosses_chunk_edges() {
+        // A stone roof over the x >= 0 half of the world at y = 4, with the
+        // other half open
+        let mut blocks = HashMap::new();
+        for x in 0..30 {
+            for z in -30..30 {
+                blocks.insert(IVec3::new(x, 4, z), Block::new(BlockType::Stone));
+            }
+        }
+        let block_at = |cell: IVec3| blocks.get(&cell).copied();
+
+        for chunk_pos in [IVec2::ZERO, IVec2::new(-1, 0)] {
+            let light = SkyLight::spread(chunk_pos, block_at);
+            assert_eq!(light.level(IVec3::new(-1, 0, 3)), MAX_SKY_LIGHT);
+            assert_eq!(light.level(IVec3::new(5, 5, 3)), MAX_SKY_LIGHT);
+            assert_eq!(light.level(IVec3::new(0, 2, 3)), MAX_SKY_LIGHT - 1);
+            assert_eq!(light.level(IVec3::new(3, 2, 3)), MAX_SKY_LIGHT - 4);
+            assert_eq!(light.level(IVec3::new(5, 2, 3)), 0);
+            assert_eq!(light.level(IVec3::new(3, 4, 3)), 0);
+        }
+
+        // Glass lets the sky straight through
+        blocks.insert(IVec3::new(3, 4, 3), Block::new(BlockType::Glass));
+        let light = SkyLight::spread(IVec2::ZERO, |cell| blocks.get(&cell).copied());
+        assert_eq!(light.level(IVec3::new(3, 2, 3)), MAX_SKY_LIGHT);
+        assert_eq!(light.level(IVec3::new(4, 2, 3)), MAX_SKY_LIGHT - 1);
+    }
+}
@@ -1,71 +1,317 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::{
-    cursor, execute,
+    cursor,
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
     terminal::{self},
 };
-use glam::{Mat4, Vec2, Vec3};
+use glam::{IVec2, IVec3, Mat4, Vec3};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use wgpu::util::DeviceExt;
 
 mod camera;
+mod chunk_grid;
+mod chunk_worker;
+mod config;
+mod export;
+mod frame_stats;
 mod geometry;
+mod horizon;
+mod hud;
+mod input;
+mod interaction;
+mod light;
 mod material;
+mod observer;
 mod perlin;
+mod postprocess;
+mod present;
+mod render_distance;
 mod renderer;
+mod rng;
+mod selection;
+mod sky;
+mod splash;
+mod state;
+mod stream;
+mod structures;
+mod taa;
+mod world;
 mod world_gen;
 
-use camera::Camera;
-use geometry::Geometry;
+use camera::{Camera, EYE_HEIGHT};
+use chunk_grid::ChunkGrid;
+use chunk_worker::{ChunkWorker, GeneratedChunk};
+use config::Config;
+use frame_stats::{FrameStats, FrameTiming};
+use geometry::{Geometry, Vertex};
+use horizon::HorizonGeometry;
+use input::{Action, HeldKeys, InputProfile};
+use interaction::BlockBreaker;
+use light::SKY_LIGHT_REACH;
 use material::Material;
-use renderer::Renderer;
-use world_gen::generate_chunk_geometry;
+use observer::{LogObserver, WorldObserver};
+use present::{FrameWriter, Viewport};
+use render_distance::{in_range, should_unload, DistanceTuner};
+use renderer::{PendingFrame, Renderer};
+use selection::SelectionBox;
+use sky::Sun;
+use splash::Splash;
+use state::{State, ViewToggles};
+use stream::FrameStream;
+use taa::TemporalAa;
+use world::{BlockEdit, RaycastHit, World};
+use world_gen::{
+    build_lit_chunk_mesh, create_chunk_geometry, Axis, BakedLighting, Block, BlockType,
+    FaceBrightness, CHUNK_HEIGHT, CHUNK_SIZE_X, CHUNK_SIZE_Z,
+};
+
+/// Distance, in blocks, to the point orbited when no block is targeted.
+const ORBIT_DISTANCE: f32 = 8.0;
+
+/// How long on-screen notices such as the new seed stay visible.
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+/// Degrees turned by one press of a look key at sensitivity 1.
+const LOOK_STEP: f32 = 10.0;
+
+/// Longest time step physics advances by at once, in seconds.
+const MAX_PHYSICS_STEP: f32 = 0.1;
+
+/// Chunks re-meshed per frame after the lighting mode changes, unless
+/// `--uploads-per-frame` sets the pace.
+const RELIGHT_CHUNKS_PER_FRAME: usize = 4;
+
+/// `--chunks` grids wider than this print a warning at startup.
+const LARGE_CHUNK_GRID: i32 = 16;
+
+/// Vertices in a generated chunk's mesh, averaged over the 64 chunks around
+/// the origin of the default world. Sizes the memory estimate for large
+/// `--chunks` grids.
+const TYPICAL_CHUNK_VERTICES: usize = 1720;
+
+/// A rendered frame on its way back from the GPU, with the camera it was
+/// rendered from for the CPU-side effects.
+struct InFlightFrame {
+    readback: PendingFrame,
+    view_proj: Mat4,
+    eye: Vec3,
+}
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 struct Uniforms {
     view_proj: [[f32; 4]; 4],
+    // xyz: min corner of the block being broken, w: block size (0 when none)
+    target_block: [f32; 4],
+    break_progress: f32,
+    // 1 when meshes carry baked brightness in their vertex colors
+    baked_lighting: f32,
+    // How much brighter the block under the crosshair is drawn
+    hover_brightness: f32,
+    _padding: f32,
+    // xyz: direction towards the sun, w: 1 for smooth shading, 0 for flat
+    sun: [f32; 4],
+    // xyz: camera position
+    eye: [f32; 4],
+    // x: distance where fog starts, y: where it's complete, z: 1 when enabled
+    fog: [f32; 4],
+    // xyz: min corner of the block under the crosshair, w: block size (0 when
+    // none or the highlight is off)
+    hover_block: [f32; 4],
 }
 
 impl Uniforms {
     fn new() -> Self {
         Self {
             view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            target_block: [0.0; 4],
+            break_progress: 0.0,
+            baked_lighting: 0.0,
+            hover_brightness: 0.0,
+            _padding: 0.0,
+            sun: [0.0, 1.0, 0.0, 0.0],
+            eye: [0.0; 4],
+            fog: [0.0; 4],
+            hover_block: [0.0; 4],
         }
     }
 
+    fn update_lighting(&mut self, sun: &Sun, smooth_shading: bool, baked: bool) {
+        let direction = sun.direction.normalize_or_zero();
+        let smooth = if smooth_shading { 1.0 } else { 0.0 };
+        self.sun = [direction.x, direction.y, direction.z, smooth];
+        self.baked_lighting = if baked { 1.0 } else { 0.0 };
+    }
+
     fn update_view_proj(&mut self, camera: &Camera) {
         self.view_proj = camera.get_proj_view_matrix().to_cols_array_2d();
+        self.eye = camera.position.extend(0.0).to_array();
+    }
+
+    fn update_fog(&mut self, range: Option<(f32, f32)>) {
+        self.fog = match range {
+            Some((start, end)) => [start, end, 1.0, 0.0],
+            None => [0.0; 4],
+        };
+    }
+
+    fn update_hover(&mut self, block: Option<IVec3>, block_size: f32, brightness: f32) {
+        self.hover_block = match block {
+            Some(block) => (block.as_vec3() * block_size).extend(block_size).to_array(),
+            None => [0.0; 4],
+        };
+        self.hover_brightness = brightness;
+    }
+
+    fn update_break_progress(&mut self, breaker: &BlockBreaker, block_size: f32) {
+        match breaker.progress() {
+            Some((block, progress)) => {
+                let min = block.as_vec3() * block_size;
+                self.target_block = [min.x, min.y, min.z, block_size];
+                self.break_progress = progress;
+            }
+            None => {
+                self.target_block = [0.0; 4];
+                self.break_progress = 0.0;
+            }
+        }
     }
 }
 
 struct MinecraftTTY {
     renderer: Renderer,
     camera: Camera,
-    geometries: Vec<Geometry>,
+    world: World,
+    geometries: HashMap<IVec2, Geometry>,
+    /// Present when `--horizon` is enabled.
+    horizon: Option<HorizonGeometry>,
+    /// Frame around the block under the crosshair.
+    selection: SelectionBox,
+    /// Lines along chunk boundaries, drawn while `show_chunk_grid` is set.
+    chunk_grid: ChunkGrid,
+    show_chunk_grid: bool,
+    chunk_worker: ChunkWorker,
+    /// Chunks loaded or on their way for the current seed, so streaming
+    /// doesn't ask for them twice.
+    requested: HashSet<IVec2>,
+    /// Chunks finished by the worker but not uploaded yet, with
+    /// `--uploads-per-frame`.
+    pending_uploads: VecDeque<GeneratedChunk>,
+    /// Loaded chunks still meshed with the previous lighting mode, or with
+    /// sky light that didn't cross from their neighbours, re-meshed a few
+    /// per frame.
+    relight_queue: VecDeque<IVec2>,
+    /// The chunks in `relight_queue`, so none is queued twice.
+    relight_queued: HashSet<IVec2>,
+    /// Present with `--target-frame-time`, adjusting the render distance.
+    distance_tuner: Option<DistanceTuner>,
+    target: Option<RaycastHit>,
+    /// Told about block events, if anything is listening.
+    observer: Option<Box<dyn WorldObserver>>,
+    /// Block last reported as stood on, for `observer`.
+    standing_on: Option<IVec3>,
+    breaker: BlockBreaker,
+    /// Block type used when placing blocks.
+    selected_block: BlockType,
+    /// Short message drawn over the view until the given time.
+    notice: Option<(String, Instant)>,
+    /// Chunks with blocks broken, placed or restored since the world was
+    /// generated. They're kept loaded, and their edits go into saved states.
+    edited: HashSet<IVec2>,
+    /// Edits restored by `--load-state` for chunks that haven't loaded yet.
+    pending_edits: HashMap<IVec2, Vec<BlockEdit>>,
+    /// Whether blocks were broken or placed since the last save.
+    unsaved_edits: bool,
+    /// Asking whether to save the edits before quitting; other keys are
+    /// ignored until it's answered.
+    quit_prompt: bool,
+    /// Draw dark outlines along depth discontinuities.
+    outlines: bool,
+    /// Brighten the block under the crosshair.
+    hover_highlight: bool,
+    /// Show the depth buffer in grayscale instead of the shaded scene.
+    depth_view: bool,
+    /// Draw back faces too, to tell winding bugs apart from culling.
+    show_back_faces: bool,
+    /// Light with per-vertex normals averaged across faces instead of flat
+    /// per-face normals.
+    smooth_shading: bool,
+    /// Show chunk, vertex and triangle counts of the loaded meshes.
+    show_stats: bool,
+    /// Show the world generation settings, such as the seed.
+    show_world_info: bool,
+    /// Which keys move the camera; Tab switches.
+    input_profile: InputProfile,
+    /// Cell the mouse was last reported at, to turn by how far it moves.
+    mouse_cell: Option<(u16, u16)>,
+    /// Walk on the terrain under gravity instead of flying freely.
+    walking: bool,
+    /// While flying, movement keys push the camera into a glide that fades
+    /// out instead of stopping it dead.
+    inertia: bool,
+    /// Keep exactly the loaded chunks: nothing streams in, is dropped or is
+    /// uploaded, and every loaded chunk is drawn wherever the camera goes.
+    streaming_paused: bool,
+    /// Stop movement at the edge of the loaded chunks instead of flying out
+    /// over ungenerated space.
+    clamp_to_loaded: bool,
+    /// Wrap frames in synchronized-update escapes. Toggleable to compare
+    /// terminals that show artifacts with them.
+    sync_updates: bool,
+    /// Present with `--taa`, jittering the projection and blending frames.
+    taa: Option<TemporalAa>,
+    /// With `--frame-latency 1`, the frame the GPU is working on while the
+    /// one before it is presented.
+    in_flight: Option<InFlightFrame>,
+    /// Movement keys held down, moved by every frame.
+    held_keys: HeldKeys,
+    /// Present with `--stats`, collecting the time each frame took.
+    frame_stats: Option<FrameStats>,
+    /// When physics last advanced.
+    last_update: Instant,
     material: Material,
     uniform_buffer: wgpu::Buffer,
     uniform_bind_group: wgpu::BindGroup,
     uniforms: Uniforms,
-    terminal_width: u32,
-    terminal_height: u32,
+    /// Terminal cells the view is drawn into.
+    viewport: Viewport,
+    /// Receiver of a copy of every frame, with `--stream`.
+    stream: Option<FrameStream>,
+    config: Config,
 }
 
 impl MinecraftTTY {
-    async fn new() -> Result<Self> {
-        // Use fixed terminal size (100x60)
-        let (terminal_width, terminal_height) = (100, 60);
-
-        // Renderer resolution matches terminal exactly
-        let renderer_width = terminal_width;
-        let renderer_height = terminal_height;
+    async fn new(config: &Config) -> Result<Self> {
+        // Rendered at the cells' pixel grid unless --render-size asks for
+        // another size
+        let viewport = config.viewport;
+        let (grid_width, grid_height) = config.grid_size();
+        let (renderer_width, renderer_height) =
+            config.render_size.unwrap_or((grid_width, grid_height));
 
-        let renderer = Renderer::new(renderer_width, renderer_height).await?;
+        let renderer = Renderer::new(renderer_width, renderer_height, &config.gpu).await?;
 
-        let camera = Camera::new(
-            renderer_width as f32 / renderer_height as f32,
-            Vec3::new(0.0, 10.0, 0.0), // Position camera at reference location
+        let adapter_info = renderer.adapter_info();
+        eprintln!(
+            "Using adapter: {} ({:?}, {:?})",
+            adapter_info.name, adapter_info.backend, adapter_info.device_type
         );
 
+        // The grid's shape is what ends up on screen
+        let mut camera = Camera::new(grid_width as f32 / grid_height as f32, config.position);
+        camera.fov_degrees = config.fov_degrees;
+        camera.fov_axis = config.fov_axis;
+        camera.rotate_y(config.yaw);
+        camera.rotate_x(config.pitch);
+        camera.rotate_z(config.roll);
+
         let uniforms = Uniforms::new();
         let uniform_buffer =
             renderer
@@ -76,76 +322,1079 @@ impl MinecraftTTY {
                     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 });
 
-        let material = Material::new(&renderer.device, &renderer.queue, &uniform_buffer)?;
+        let material = Material::new(
+            &renderer.device,
+            &renderer.queue,
+            &uniform_buffer,
+            config.detail_map,
+            config.overlay_depth_bias,
+        )?;
         let uniform_bind_group = material.create_bind_group(&renderer.device, &uniform_buffer);
 
-        // Generate chunks like the reference implementation
-        let mut geometries = Vec::new();
-        let chunk_positions = [
-            Vec2::new(0.0, 0.0),
-            Vec2::new(-1.0, 0.0),
-            Vec2::new(0.0, -1.0),
-            Vec2::new(-1.0, -1.0),
-        ];
-        for chunk_pos in chunk_positions {
-            let geometry =
-                generate_chunk_geometry(&renderer.device, &renderer.queue, chunk_pos)?;
-            geometries.push(geometry);
+        let world = World::new(config.world.clone());
+        let mut pending_edits: HashMap<IVec2, Vec<BlockEdit>> = HashMap::new();
+        for &edit in &config.edits {
+            pending_edits
+                .entry(World::chunk_pos_of(edit.position))
+                .or_default()
+                .push(edit);
+        }
+        let mut chunk_worker = ChunkWorker::spawn(config.chunk_threads, config.generation_budget);
+        let chunks = config.chunks as i32;
+        if chunks > LARGE_CHUNK_GRID {
+            // Six 16-bit indices for every four vertices
+            let chunk_bytes = TYPICAL_CHUNK_VERTICES
+                * (std::mem::size_of::<Vertex>() + 3 * std::mem::size_of::<u16>() / 2);
+            let count = (chunks * chunks) as usize;
+            eprintln!(
+                "Generating {count} chunks; expect a slow start and around {} MB of meshes",
+                count * chunk_bytes / 1_000_000
+            );
         }
+        // An N x N grid around the origin; even sizes split evenly across it
+        let first = -(chunks / 2);
+        let mut requested = HashSet::new();
+        for x in first..first + chunks {
+            for z in first..first + chunks {
+                chunk_worker.request(IVec2::new(x, z), &world.config);
+                requested.insert(IVec2::new(x, z));
+            }
+        }
+
+        let horizon =
+            (config.horizon.distance > 0.0).then(|| HorizonGeometry::new(&renderer.device));
+        let selection = SelectionBox::new(&renderer.device);
 
-        Ok(Self {
+        let mut app = Self {
             renderer,
             camera,
-            geometries,
+            world,
+            geometries: HashMap::new(),
+            horizon,
+            selection,
+            chunk_grid: ChunkGrid::new(),
+            show_chunk_grid: config.chunk_grid,
+            chunk_worker,
+            requested,
+            pending_uploads: VecDeque::new(),
+            relight_queue: VecDeque::new(),
+            relight_queued: HashSet::new(),
+            distance_tuner: config.auto_distance.map(DistanceTuner::new),
+            target: None,
+            observer: config
+                .log_block_events
+                .then(|| Box::new(LogObserver) as Box<dyn WorldObserver>),
+            standing_on: None,
+            breaker: BlockBreaker::new(config.hardness.clone()),
+            selected_block: BlockType::Grass,
+            notice: None,
+            edited: HashSet::new(),
+            pending_edits,
+            unsaved_edits: false,
+            quit_prompt: false,
+            outlines: config.view.outlines,
+            hover_highlight: config.view.hover_highlight,
+            depth_view: false,
+            show_back_faces: false,
+            smooth_shading: config.view.smooth_shading,
+            show_stats: false,
+            show_world_info: false,
+            walking: config.view.walking,
+            inertia: config.inertia,
+            streaming_paused: false,
+            clamp_to_loaded: config.clamp_to_loaded,
+            sync_updates: true,
+            input_profile: config.input_profile,
+            mouse_cell: None,
+            taa: (config.taa_blend > 0.0).then(|| TemporalAa::new(config.taa_blend)),
+            in_flight: None,
+            frame_stats: config.stats.then(FrameStats::new),
+            held_keys: HeldKeys::default(),
+            last_update: Instant::now(),
             material,
             uniform_buffer,
             uniform_bind_group,
             uniforms,
-            terminal_width,
-            terminal_height,
-        })
-    }
-
-    fn handle_input(&mut self) -> Result<bool> {
-        use crossterm::event::{self, Event, KeyCode, KeyEvent};
-
-        // Use non-blocking poll with very short timeout
-        match event::poll(std::time::Duration::from_millis(0)) {
-            Ok(true) => {
-                match event::read() {
-                    Ok(Event::Key(KeyEvent { code, .. })) => match code {
-                        KeyCode::Char('x') | KeyCode::Esc => return Ok(false),
-                        KeyCode::Char('w') | KeyCode::Up => self.camera.move_forward(0.5),
-                        KeyCode::Char('s') | KeyCode::Down => self.camera.move_forward(-0.5),
-                        KeyCode::Char('a') | KeyCode::Left => self.camera.move_right(-0.5),
-                        KeyCode::Char('d') | KeyCode::Right => self.camera.move_right(0.5),
-                        KeyCode::Char('q') => self.camera.move_up(-0.5),
-                        KeyCode::Char('e') => self.camera.move_up(0.5),
-                        KeyCode::Char('h') => self.camera.rotate_y(-10.0),
-                        KeyCode::Char('l') => self.camera.rotate_y(10.0),
-                        KeyCode::Char('j') => self.camera.rotate_x(10.0),
-                        KeyCode::Char('k') => self.camera.rotate_x(-10.0),
-                        _ => {}
-                    },
-                    Ok(_) => {}  // Other events
-                    Err(_) => {} // Ignore input errors
+            viewport,
+            stream: config.stream.clone().map(FrameStream::spawn),
+            config: config.clone(),
+        };
+
+        // The first frame should show the whole starting area
+        let started = Instant::now();
+        let finished = app.chunk_worker.wait_all();
+        log::debug!(
+            "generated {} chunks in {:.2?}",
+            finished.len(),
+            started.elapsed()
+        );
+        app.receive_chunks(finished)?;
+
+        Ok(app)
+    }
+
+    /// Waits up to `timeout` for input, then applies every queued event so
+    /// fast key repeats aren't left waiting for later frames. Returns false
+    /// when the user asked to quit.
+    fn handle_input(&mut self, timeout: Duration) -> Result<bool> {
+        use crossterm::event::{self, Event, KeyEvent, KeyEventKind, MouseEvent, MouseEventKind};
+
+        let mut timeout = timeout;
+        // Polling or read errors are ignored, as a dropped event is harmless
+        while event::poll(timeout).unwrap_or(false) {
+            timeout = Duration::ZERO;
+            let (code, kind) = match event::read() {
+                Ok(Event::Key(KeyEvent { code, kind, .. })) => (code, kind),
+                // Releases made in another window never arrive
+                Ok(Event::FocusLost) => {
+                    self.held_keys.release_all();
+                    self.mouse_cell = None;
+                    continue;
+                }
+                Ok(Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Moved | MouseEventKind::Drag(_),
+                    column,
+                    row,
+                    ..
+                })) => {
+                    self.mouse_moved(column, row);
+                    continue;
                 }
+                _ => continue,
+            };
+            let held = self
+                .input_profile
+                .action(code)
+                .is_some_and(|action| action.scaled(1.0).is_some());
+            let step = if held && self.config.key_repeat_rate > 0.0 {
+                self.held_keys.update(code, kind)
+            } else {
+                kind != KeyEventKind::Release
+            };
+            if step && !self.handle_key(code)? {
+                return Ok(false);
             }
-            Ok(false) => {} // No input available
-            Err(_) => {}    // Ignore polling errors
         }
         Ok(true)
     }
 
-    fn render(&mut self) -> Result<()> {
+    /// Applies a single key press. Returns false for the quit keys.
+    fn handle_key(&mut self, code: crossterm::event::KeyCode) -> Result<bool> {
+        use crossterm::event::KeyCode;
+
+        if self.quit_prompt {
+            match code {
+                // A failed save keeps playing, with the error on screen
+                KeyCode::Char('y') => {
+                    self.quit_prompt = false;
+                    return Ok(!self.save_state());
+                }
+                KeyCode::Char('n') => return Ok(false),
+                KeyCode::Esc => self.quit_prompt = false,
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        if let Some(action) = self.input_profile.action(code) {
+            self.apply_action(action);
+            return Ok(true);
+        }
+
+        match code {
+            KeyCode::Char('x') | KeyCode::Esc => {
+                if !self.unsaved_edits || !self.config.confirm_quit {
+                    return Ok(false);
+                }
+                self.quit_prompt = true;
+            }
+            KeyCode::Tab => self.cycle_input_profile(),
+            KeyCode::Char('S') => {
+                self.save_state();
+            }
+            KeyCode::Char('G') => self.toggle_chunk_grid(),
+            KeyCode::Char('R') => self.camera.rotate_z(-self.camera.roll()),
+            KeyCode::Char('I') => self.toggle_inertia(),
+            KeyCode::Char('P') => self.streaming_paused = !self.streaming_paused,
+            KeyCode::Char('H') => self.hover_highlight = !self.hover_highlight,
+            KeyCode::Char('f') => self.toggle_walking(),
+            KeyCode::Char('r') => self.toggle_clamp_to_loaded(),
+            KeyCode::Char('b') => self.break_target()?,
+            KeyCode::Char('p') => self.place_block()?,
+            KeyCode::Char('n') => self.regenerate_world()?,
+            KeyCode::Char('o') => self.outlines = !self.outlines,
+            KeyCode::Char('z') => self.depth_view = !self.depth_view,
+            KeyCode::Char('c') => self.show_back_faces = !self.show_back_faces,
+            KeyCode::Char('v') => self.smooth_shading = !self.smooth_shading,
+            KeyCode::Char('i') => self.show_stats = !self.show_stats,
+            KeyCode::Char('t') => self.show_world_info = !self.show_world_info,
+            KeyCode::Char('g') => self.cycle_glyph(),
+            KeyCode::Char('m') => self.cycle_lighting(),
+            KeyCode::Char('u') => self.toggle_sync_updates(),
+            KeyCode::Char(c @ '1'..='9') => {
+                let slot = c as usize - '1' as usize;
+                if let Some(&block_type) = BlockType::ALL.get(slot) {
+                    self.selected_block = block_type;
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Raycasts from the camera to find the block under the crosshair.
+    fn update_target(&mut self) {
+        let block_size = self.world.config.block_size;
+        let previous = self.target.map(|hit| hit.block);
+        self.target = self.world.raycast(
+            self.camera.position / block_size,
+            self.camera.forward,
+            self.config.reach,
+        );
+        if let Some(observer) = &mut self.observer {
+            let block = self.target.map(|hit| hit.block);
+            if block != previous {
+                let target = block.and_then(|block| {
+                    let found = self.world.get_block(block)?;
+                    Some((found.block_type, block))
+                });
+                observer.on_target_block(target);
+            }
+        }
+        self.breaker
+            .update(self.target.map(|hit| hit.block), Instant::now());
+    }
+
+    /// Advances break progress on the targeted block, removing it once complete.
+    fn break_target(&mut self) -> Result<()> {
+        self.update_target();
+        let Some(hit) = self.target else {
+            return Ok(());
+        };
+
+        let Some(block) = self.world.get_block(hit.block) else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        if self.breaker.hit(hit.block, block.block_type, now) {
+            if let Some(chunk_pos) = self.world.set_block(hit.block, None) {
+                self.remesh_edit(hit.block)?;
+                self.edited.insert(chunk_pos);
+                self.unsaved_edits = true;
+            }
+            self.update_target();
+        }
+        Ok(())
+    }
+
+    /// Places the selected block against the targeted face.
+    fn place_block(&mut self) -> Result<()> {
+        self.update_target();
+        let Some(hit) = self.target else {
+            return Ok(());
+        };
+        if hit.normal == IVec3::ZERO {
+            return Ok(());
+        }
+
+        let position = hit.block + hit.normal;
+        let camera_cell = (self.camera.position / self.world.config.block_size)
+            .floor()
+            .as_ivec3();
+        if position == camera_cell || self.world.get_block(position).is_some() {
+            return Ok(());
+        }
+        if position.y >= self.config.build_height as i32 {
+            let text = format!("Build height limit is {}", self.config.build_height);
+            self.notice = Some((text, Instant::now() + NOTICE_DURATION));
+            return Ok(());
+        }
+
+        let block = Block {
+            block_type: self.selected_block,
+            axis: Axis::from_normal(hit.normal),
+        };
+        if let Some(chunk_pos) = self.world.set_block(position, Some(block)) {
+            self.remesh_edit(position)?;
+            self.edited.insert(chunk_pos);
+            self.unsaved_edits = true;
+        }
+        Ok(())
+    }
+
+    /// Moves the camera forward and sideways. Walking, and flying with
+    /// `--level-flight`, keep to the ground plane; otherwise movement follows
+    /// the view.
+    fn step(&mut self, forward: f32, right: f32) {
+        let from = self.camera.position;
+        if self.walking || self.config.level_flight {
+            self.camera.walk_forward(forward);
+        } else {
+            self.camera.move_forward(forward);
+        }
+        self.camera.move_right(right);
+
+        if self.push_from(from) {
+            return;
+        }
+        if self.movement_blocked() {
+            self.camera.position = from;
+        } else if self.walking {
+            let walked = self.camera.position.distance(from);
+            self.camera.bob(walked, &self.config.effects);
+        }
+    }
+
+    /// Moves the camera up or down: straight up while walking or with
+    /// `--level-flight`, along the tilted view's up otherwise.
+    fn rise(&mut self, distance: f32) {
+        let from = self.camera.position;
+        if self.walking || self.config.level_flight {
+            self.camera.move_vertical(distance);
+        } else {
+            self.camera.move_up(distance);
+        }
+
+        if !self.push_from(from) && self.movement_blocked() {
+            self.camera.position = from;
+        }
+    }
+
+    /// With inertia while flying, takes back the move since `from` and
+    /// pushes the camera that way instead, for `update_physics` to glide.
+    /// Returns whether it did.
+    fn push_from(&mut self, from: Vec3) -> bool {
+        if self.walking || !self.inertia {
+            return false;
+        }
+        let moved = self.camera.position - from;
+        self.camera.position = from;
+        self.camera.push(moved, self.config.fly_damping);
+        true
+    }
+
+    /// Whether the camera just moved somewhere it can't be: below the world's
+    /// floor, into a block while walking, or out of the loaded chunks while
+    /// clamped.
+    fn movement_blocked(&self) -> bool {
+        self.camera.position.y < 0.0
+            || (self.walking && self.body_blocked(self.camera.position))
+            || (self.clamp_to_loaded && self.world.chunk(self.camera_chunk()).is_none())
+    }
+
+    fn toggle_sync_updates(&mut self) {
+        self.sync_updates = !self.sync_updates;
+        let state = if self.sync_updates { "on" } else { "off" };
+        let text = format!("Synchronized updates {state}");
+        self.notice = Some((text, Instant::now() + NOTICE_DURATION));
+    }
+
+    fn toggle_clamp_to_loaded(&mut self) {
+        self.clamp_to_loaded = !self.clamp_to_loaded;
+        let mode = if self.clamp_to_loaded {
+            "Clamped to loaded chunks"
+        } else {
+            "Free flight"
+        };
+        self.notice = Some((mode.to_string(), Instant::now() + NOTICE_DURATION));
+    }
+
+    fn toggle_chunk_grid(&mut self) {
+        self.show_chunk_grid = !self.show_chunk_grid;
+        let state = if self.show_chunk_grid { "on" } else { "off" };
+        let text = format!("Chunk grid {state}");
+        self.notice = Some((text, Instant::now() + NOTICE_DURATION));
+    }
+
+    fn toggle_inertia(&mut self) {
+        self.inertia = !self.inertia;
+        self.camera.fly_velocity = Vec3::ZERO;
+        let state = if self.inertia { "on" } else { "off" };
+        let text = format!("Flying inertia {state}");
+        self.notice = Some((text, Instant::now() + NOTICE_DURATION));
+    }
+
+    fn toggle_walking(&mut self) {
+        self.walking = !self.walking;
+        self.camera.vertical_velocity = 0.0;
+        self.camera.fly_velocity = Vec3::ZERO;
+        self.standing_on = None;
+        let mode = if self.walking { "Walking" } else { "Flying" };
+        self.notice = Some((mode.to_string(), Instant::now() + NOTICE_DURATION));
+    }
+
+    /// Applies gravity since the last update while walking, or the glide
+    /// while flying with inertia, and advances the camera effects.
+    fn update_physics(&mut self) {
+        let now = Instant::now();
+        // Capped so a stall doesn't turn into one huge step
+        let dt = (now - self.last_update).as_secs_f32().min(MAX_PHYSICS_STEP);
+        self.last_update = now;
+        self.apply_held_keys(dt);
+        if self.walking {
+            let ground = self.ground_below(self.camera.position);
+            let falling = -self.camera.vertical_velocity;
+            let was_on_ground = self.camera.on_ground;
+            self.camera.apply_gravity(dt, &self.config.physics, ground);
+            if self.camera.on_ground && !was_on_ground {
+                self.camera
+                    .shake(falling, &self.config.effects, &self.config.physics);
+            }
+            if self.observer.is_some() {
+                self.notify_standing_on();
+            }
+        } else if self.inertia {
+            let from = self.camera.position;
+            let moved = self.camera.glide(dt, self.config.fly_damping);
+            self.camera.position += moved;
+            // Running into something ends the glide
+            if self.movement_blocked() {
+                self.camera.position = from;
+                self.camera.fly_velocity = Vec3::ZERO;
+            }
+        }
+        self.camera.update_effects(dt, &self.config.effects);
+    }
+
+    /// Moves for every movement key held down, at `--key-repeat` steps per
+    /// second.
+    fn apply_held_keys(&mut self, dt: f32) {
+        if self.quit_prompt {
+            return;
+        }
+        let steps = dt * self.config.key_repeat_rate;
+        for code in self.held_keys.advance() {
+            let action = self.input_profile.action(code);
+            if let Some(action) = action.and_then(|action| action.scaled(steps)) {
+                self.apply_action(action);
+            }
+        }
+    }
+
+    /// Tells the observer when the block under a grounded walker changes.
+    fn notify_standing_on(&mut self) {
+        let s = self.world.config.block_size;
+        let feet = ((self.camera.position - Vec3::Y * EYE_HEIGHT) / s)
+            .floor()
+            .as_ivec3();
+        let below = feet - IVec3::Y;
+        let standing_on = self.camera.on_ground.then_some(below);
+        if standing_on == self.standing_on {
+            return;
+        }
+        self.standing_on = standing_on;
+        let block = standing_on.and_then(|position| self.world.get_block(position));
+        if let (Some(block), Some(observer)) = (block, &mut self.observer) {
+            observer.on_enter_block(block.block_type, below);
+        }
+    }
+
+    /// Top of the highest solid block at or below the feet of a camera at
+    /// `eye`.
+    fn ground_below(&self, eye: Vec3) -> Option<f32> {
+        self.world.ground_below(eye - Vec3::Y * EYE_HEIGHT)
+    }
+
+    /// Whether a walking body with its eye at `eye` overlaps a solid block.
+    fn body_blocked(&self, eye: Vec3) -> bool {
+        self.world.column_blocked(eye - Vec3::Y * EYE_HEIGHT, eye.y)
+    }
+
+    /// Carries out a movement bound to a key or the mouse.
+    fn apply_action(&mut self, action: Action) {
+        match action {
+            Action::Move { forward, right } => self.step(forward, right),
+            Action::Rise(distance) => self.rise(distance),
+            Action::Look { right, up } => self.look(right, up),
+            // Gravity would pull an orbiting camera off its circle anyway
+            Action::Orbit { right, up } if self.walking => self.look(right, up),
+            Action::Orbit { right, up } => self.orbit(right, up),
+            Action::Roll(steps) => self.camera.rotate_z(steps * LOOK_STEP),
+            Action::Jump if self.walking => self.camera.jump(&self.config.physics),
+            Action::Jump => {}
+        }
+    }
+
+    /// Turns the camera around the point under the crosshair, or one
+    /// `ORBIT_DISTANCE` ahead when no block is targeted, keeping it centered.
+    fn orbit(&mut self, right: f32, up: f32) {
+        self.update_target();
+        let block_size = self.world.config.block_size;
+        let distance = match self.target {
+            Some(hit) => {
+                let center = (hit.block.as_vec3() + 0.5) * block_size;
+                (center - self.camera.position).dot(self.camera.forward)
+            }
+            None => ORBIT_DISTANCE * block_size,
+        };
+        let pivot = self.camera.position + self.camera.forward * distance;
+
+        let from = self.camera.position;
+        self.look(right, up);
+        self.camera.position = pivot - self.camera.forward * distance;
+        if self.movement_blocked() {
+            self.camera.position = from;
+        }
+    }
+
+    /// Writes the camera pose, world settings, block edits and view toggles
+    /// to a new JSON file in the working directory, for `--load-state`.
+    /// Returns whether it was written.
+    fn save_state(&mut self) -> bool {
+        // Restored edits whose chunks never loaded are kept as they were
+        let mut edits: Vec<BlockEdit> = self.pending_edits.values().flatten().copied().collect();
+        for &chunk_pos in &self.edited {
+            edits.extend(self.world.edits(chunk_pos));
+        }
+        let state = State {
+            position: self.camera.position,
+            yaw: self.camera.yaw(),
+            pitch: self.camera.pitch(),
+            roll: self.camera.roll(),
+            fov_degrees: self.camera.fov_degrees,
+            fov_axis: self.camera.fov_axis,
+            render_distance: self.config.render_distance,
+            glyph: self.config.glyph,
+            view: ViewToggles {
+                walking: self.walking,
+                smooth_shading: self.smooth_shading,
+                outlines: self.outlines,
+                hover_highlight: self.hover_highlight,
+            },
+            world: self.world.config.clone(),
+            edits,
+        };
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = PathBuf::from(format!("minecraftty-state-{timestamp}.json"));
+        // A failed save shouldn't end the session
+        let saved = state.save(&path);
+        let text = match &saved {
+            Ok(()) => format!("Saved {}", path.display()),
+            Err(e) => format!("{e:#}"),
+        };
+        self.notice = Some((text, Instant::now() + NOTICE_DURATION));
+        self.unsaved_edits &= saved.is_err();
+        saved.is_ok()
+    }
+
+    fn cycle_input_profile(&mut self) {
+        self.input_profile = self.input_profile.next();
+        let text = format!("Input: {}", self.input_profile.name());
+        self.notice = Some((text, Instant::now() + NOTICE_DURATION));
+    }
+
+    /// Turns the camera by how far the mouse moved since its last report.
+    /// The terminal only reports cells, so look steps are coarse, and the
+    /// turning stops where the pointer reaches the edge of the window.
+    fn mouse_moved(&mut self, column: u16, row: u16) {
+        if let Some((last_column, last_row)) = self.mouse_cell.replace((column, row)) {
+            let cols = column as i32 - last_column as i32;
+            let rows = row as i32 - last_row as i32;
+            if let Some(action) = self.input_profile.mouse_action(cols, rows) {
+                self.apply_action(action);
+            }
+        }
+    }
+
+    /// Turns the camera by a number of look steps, scaled by the configured
+    /// sensitivity. Positive `up` looks up unless the Y axis is inverted.
+    fn look(&mut self, right: f32, up: f32) {
+        let up = if self.config.invert_y { -up } else { up };
+        let yaw = right * LOOK_STEP * self.config.sensitivity_x;
+        let pitch = up * LOOK_STEP * self.config.sensitivity_y;
+        self.camera.rotate_y(yaw);
+        self.camera.rotate_x(pitch);
+    }
+
+    /// Summarizes the cost of the loaded chunk meshes for the stats overlay.
+    fn mesh_stats(&self) -> Vec<String> {
+        let geometries = self.geometries.values();
+        let vertices: u64 = geometries.clone().map(|g| g.vertex_count as u64).sum();
+        let indices: u64 = geometries.clone().map(|g| g.index_count as u64).sum();
+        let bytes: u64 = geometries.map(Geometry::size_bytes).sum();
+        let mut lines = vec![
+            format!("Chunks     {}", self.geometries.len()),
+            format!("Vertices   {vertices}"),
+            format!("Triangles  {}", indices / 3),
+            format!("GPU memory {:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+        ];
+        if let Some(distance) = self.render_distance() {
+            lines.push(format!("Distance   {distance} chunks"));
+        }
+        let (width, height) = self.config.grid_size();
+        let (render_width, render_height) = (self.renderer.width, self.renderer.height);
+        if (width, height) == (render_width, render_height) {
+            lines.push(format!("Resolution {width}x{height}"));
+        } else {
+            lines.push(format!(
+                "Resolution {render_width}x{render_height} to {width}x{height}"
+            ));
+        }
+        lines
+    }
+
+    /// Lines describing the active world generation settings.
+    fn world_info(&self) -> Vec<String> {
+        let config = &self.world.config;
+        let soil = match config.soil_depth {
+            Some(depth) => format!("{depth} blocks"),
+            None => "noise mix".to_string(),
+        };
+        let block = self.camera_block();
+        let biome = match (self.world.biome(block.x, block.z), config.biome_size) {
+            (Some(biome), Some(size)) => format!("{} (size {size})", biome.name),
+            _ => "off".to_string(),
+        };
+        let flowers = config.flower_share * 100.0;
+        let plants = match config.decoration_density {
+            Some(density) => format!("{density} per column ({flowers:.0}% flowers)"),
+            None => "off".to_string(),
+        };
+        let trees = match config.tree_density {
+            Some(density) => format!("{density} per column"),
+            None => "off".to_string(),
+        };
+        vec![
+            format!("Seed       {}", config.seed),
+            format!("Block size {}", config.block_size),
+            format!("Ore size   {}", config.ore_size),
+            format!("Ore rarity {}", config.ore_rarity),
+            format!("Soil       {soil}"),
+            format!("Grass      {}", if config.grass_top { "on" } else { "off" }),
+            format!("Biome      {biome}"),
+            format!("Trees      {trees}"),
+            format!("Plants     {plants}"),
+        ]
+    }
+
+    /// Switches to the next cell glyph and names it on screen. The glyph only
+    /// changes how each cell's pixels are drawn, so the render size stays the
+    /// same.
+    fn cycle_glyph(&mut self) {
+        let glyph = self.config.glyph.next();
+        self.config.glyph = glyph;
+        self.notice = Some((
+            format!("Glyph: {} {}", glyph.name(), glyph.symbol()),
+            Instant::now() + NOTICE_DURATION,
+        ));
+    }
+
+    /// Switches to the next lighting mode (dynamic sun, baked sun, fixed face
+    /// brightness), queues the loaded chunks to be re-meshed with it and
+    /// names it on screen.
+    fn cycle_lighting(&mut self) {
+        // Keeps any --face-brightness for the fixed-face mode
+        let faces = match self.config.world.baked_lighting {
+            Some(BakedLighting::Faces(faces)) => faces,
+            _ => FaceBrightness::default(),
+        };
+        let sun = self.config.sun.direction.normalize_or_zero();
+        let (lighting, name) = match self.world.config.baked_lighting {
+            None => (Some(BakedLighting::Sun(sun)), "baked sun"),
+            Some(BakedLighting::Sun(_)) => (Some(BakedLighting::Faces(faces)), "fixed faces"),
+            Some(BakedLighting::Faces(_)) => (None, "dynamic sun"),
+        };
+        self.world.config.baked_lighting = lighting;
+
+        // Re-meshing every chunk at once stalls a frame, so the nearest
+        // chunks change first and the rest follow over the next frames
+        let camera_chunk = self.camera_chunk();
+        let mut positions: Vec<IVec2> = self.world.chunks().map(|chunk| chunk.position).collect();
+        positions.sort_by_key(|&chunk_pos| (chunk_pos - camera_chunk).length_squared());
+        self.relight_queued = positions.iter().copied().collect();
+        self.relight_queue = positions.into();
+        self.notice = Some((
+            format!("Lighting: {name}"),
+            Instant::now() + NOTICE_DURATION,
+        ));
+    }
+
+    /// Re-meshes this frame's share of the chunks queued by `cycle_lighting`
+    /// and `receive_chunks`.
+    fn relight_chunks(&mut self) -> Result<()> {
+        let count = self
+            .config
+            .uploads_per_frame
+            .unwrap_or(RELIGHT_CHUNKS_PER_FRAME)
+            .min(self.relight_queue.len());
+        let positions: Vec<IVec2> = self.relight_queue.drain(..count).collect();
+        for chunk_pos in positions {
+            self.relight_queued.remove(&chunk_pos);
+            self.remesh_chunk(chunk_pos)?;
+        }
+        Ok(())
+    }
+
+    /// Queues a loaded chunk for `relight_chunks`, unless it's already queued.
+    fn queue_relight(&mut self, chunk_pos: IVec2) {
+        if self.world.chunk(chunk_pos).is_some() && self.relight_queued.insert(chunk_pos) {
+            self.relight_queue.push_back(chunk_pos);
+        }
+    }
+
+    /// Moves to the next seed and regenerates every loaded chunk. The old
+    /// chunks stay visible until their replacements arrive.
+    fn regenerate_world(&mut self) -> Result<()> {
+        let seed = self.world.config.seed.wrapping_add(1);
+        self.world.set_seed(seed);
+        self.breaker.cancel();
+        // Edits don't carry over to another seed's terrain
+        self.edited.clear();
+        self.pending_edits.clear();
+        self.unsaved_edits = false;
+
+        // Chunks still in flight for the old seed will be dropped, so
+        // streaming asks for them again
+        let positions: Vec<IVec2> = self.world.chunks().map(|chunk| chunk.position).collect();
+        self.requested = positions.iter().copied().collect();
+        for chunk_pos in positions {
+            self.chunk_worker.request(chunk_pos, &self.world.config);
+        }
+
+        self.notice = Some((format!("Seed {seed}"), Instant::now() + NOTICE_DURATION));
+        Ok(())
+    }
+
+    /// Block-space position of the camera.
+    fn camera_block(&self) -> IVec3 {
+        (self.camera.position / self.world.config.block_size)
+            .floor()
+            .as_ivec3()
+    }
+
+    /// Chunk the camera is in.
+    fn camera_chunk(&self) -> IVec2 {
+        World::chunk_pos_of(self.camera_block())
+    }
+
+    /// Chunks around the camera to stream and draw, tuned to the frame time
+    /// with `--target-frame-time`. `None` draws every loaded chunk.
+    fn render_distance(&self) -> Option<u32> {
+        match &self.distance_tuner {
+            Some(tuner) => Some(tuner.distance(self.camera.fov_degrees)),
+            None => self.config.render_distance,
+        }
+    }
+
+    /// How far away terrain can be seen: up to the far plane, or the edge of
+    /// the render distance when that's closer.
+    fn visible_distance(&self) -> f32 {
+        let far = self.camera.far;
+        let Some(distance) = self.render_distance() else {
+            return far;
+        };
+        // On average the camera is half a chunk from the edges of its own
+        let chunk_size = CHUNK_SIZE_X.min(CHUNK_SIZE_Z) as f32 * self.world.config.block_size;
+        far.min((distance as f32 + 0.5) * chunk_size)
+    }
+
+    /// Whether `chunk_pos` is within the render distance of the camera. Always
+    /// true without `--render-distance` or while streaming is paused.
+    fn in_render_distance(&self, chunk_pos: IVec2, camera_chunk: IVec2) -> bool {
+        self.streaming_paused
+            || self
+                .render_distance()
+                .is_none_or(|distance| in_range(chunk_pos - camera_chunk, distance))
+    }
+
+    /// With `--render-distance`, requests the chunks around the camera that
+    /// haven't been asked for yet, nearest first.
+    fn stream_chunks(&mut self) {
+        let Some(distance) = self.render_distance() else {
+            return;
+        };
+        let center = self.camera_chunk();
+        let distance = distance as i32;
+        let mut missing: Vec<IVec2> = (-distance..=distance)
+            .flat_map(|x| (-distance..=distance).map(move |z| center + IVec2::new(x, z)))
+            .filter(|chunk_pos| !self.requested.contains(chunk_pos))
+            .collect();
+        missing.sort_by_key(|&chunk_pos| (chunk_pos - center).length_squared());
+        for chunk_pos in missing {
+            self.chunk_worker.request(chunk_pos, &self.world.config);
+            self.requested.insert(chunk_pos);
+        }
+    }
+
+    /// With `--render-distance`, drops the chunks that have gone
+    /// `--unload-margin` chunks past it. Edited chunks are kept, as they'd
+    /// come back unedited.
+    fn unload_chunks(&mut self) {
+        let Some(distance) = self.render_distance() else {
+            return;
+        };
+        let center = self.camera_chunk();
+        let margin = self.config.unload_margin;
+        let far: Vec<IVec2> = self
+            .requested
+            .iter()
+            .copied()
+            .filter(|&chunk_pos| {
+                should_unload(chunk_pos - center, distance, margin)
+                    && !self.edited.contains(&chunk_pos)
+            })
+            .collect();
+        for chunk_pos in &far {
+            self.requested.remove(chunk_pos);
+            self.geometries.remove(chunk_pos);
+            self.world.remove_chunk(*chunk_pos);
+        }
+        if !far.is_empty() {
+            log::debug!("unloaded {} chunks", far.len());
+        }
+    }
+
+    /// Chunks to upload this frame: everything the worker has finished, or
+    /// the oldest few with `--uploads-per-frame`, so a burst of finished
+    /// chunks is spread over several frames instead of stalling one.
+    fn next_uploads(&mut self) -> Vec<GeneratedChunk> {
+        let finished = self.chunk_worker.drain();
+        let Some(budget) = self.config.uploads_per_frame else {
+            return finished;
+        };
+        self.pending_uploads.extend(finished);
+        let count = budget.min(self.pending_uploads.len());
+        self.pending_uploads.drain(..count).collect()
+    }
+
+    /// Uploads chunks finished by the worker and adds them to the world.
+    fn receive_chunks(&mut self, finished: Vec<GeneratedChunk>) -> Result<()> {
+        let started = Instant::now();
+        let mut uploaded = 0;
+        // Chunks the worker left unmeshed, meshed before the lighting mode
+        // changed or before their edits were restored
+        let mut unmeshed = Vec::new();
+        for generated in finished {
+            // Generated for a seed the world has since moved away from, or
+            // unloaded while it was being generated
+            if generated.config.seed != self.world.config.seed
+                || !self.requested.contains(&generated.chunk.position)
+            {
+                continue;
+            }
+            let chunk_pos = generated.chunk.position;
+            let stale = generated.config.baked_lighting != self.world.config.baked_lighting;
+            self.world.insert_chunk(generated.chunk);
+            // Edits restored by --load-state go in before it's meshed
+            let restored = self.pending_edits.remove(&chunk_pos);
+            if let Some(edits) = &restored {
+                for edit in edits {
+                    self.world.set_block(edit.position, edit.block);
+                }
+                self.edited.insert(chunk_pos);
+            }
+            match generated.mesh {
+                Some(mesh) if !stale && restored.is_none() => {
+                    let geometry = Geometry::new(&self.renderer.device, &mesh)?;
+                    self.geometries.insert(chunk_pos, geometry);
+                }
+                _ => unmeshed.push(chunk_pos),
+            }
+            uploaded += 1;
+        }
+
+        // Meshed once the whole batch is in the world, so sky light crosses
+        // between chunks that arrived together
+        for &chunk_pos in &unmeshed {
+            self.remesh_chunk(chunk_pos)?;
+        }
+        // Their sky light also spreads into the loaded chunks sharing an
+        // edge with them. Diagonal neighbours only share a corner, which is
+        // left as it is
+        if self.world.config.sky_shadow.is_some() {
+            let meshed: HashSet<IVec2> = unmeshed.iter().copied().collect();
+            for chunk_pos in unmeshed {
+                for step in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                    if !meshed.contains(&(chunk_pos + step)) {
+                        self.queue_relight(chunk_pos + step);
+                    }
+                }
+            }
+        }
+        if uploaded > 0 {
+            log::debug!(
+                "uploaded {uploaded} chunk meshes in {:.2?}",
+                started.elapsed()
+            );
+        }
+        Ok(())
+    }
+
+    fn remesh_chunk(&mut self, chunk_pos: IVec2) -> Result<()> {
+        if let Some(chunk) = self.world.chunk(chunk_pos) {
+            log::trace!("re-meshing chunk {chunk_pos}");
+            let light = self
+                .world
+                .config
+                .sky_shadow
+                .map(|_| self.world.sky_light(chunk_pos));
+            let geometry = create_chunk_geometry(
+                &self.renderer.device,
+                chunk,
+                light.as_ref(),
+                &self.world.config,
+            )?;
+            self.geometries.insert(chunk_pos, geometry);
+        }
+        Ok(())
+    }
+
+    /// Re-meshes the chunk holding an edited block and, with `--sky-shadow`,
+    /// the chunks beside it that the edit changes the sky light of.
+    fn remesh_edit(&mut self, block: IVec3) -> Result<()> {
+        let reach = if self.world.config.sky_shadow.is_some() {
+            SKY_LIGHT_REACH
+        } else {
+            0
+        };
+        let first = World::chunk_pos_of(block - IVec3::new(reach, 0, reach));
+        let last = World::chunk_pos_of(block + IVec3::new(reach, 0, reach));
+        for x in first.x..=last.x {
+            for z in first.y..=last.y {
+                self.remesh_chunk(IVec2::new(x, z))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders and presents a frame. Returns how long writing it to the
+    /// terminal took, or `None` when nothing was presented: the first frame
+    /// with `--frame-latency 1` is only started.
+    fn render(&mut self) -> Result<Option<Duration>> {
+        if !self.streaming_paused {
+            self.stream_chunks();
+            self.unload_chunks();
+            let finished = self.next_uploads();
+            self.receive_chunks(finished)?;
+        }
+        self.relight_chunks()?;
+        self.update_physics();
+
+        if let Some(taa) = &mut self.taa {
+            self.camera.jitter = taa.next_jitter(self.renderer.width, self.renderer.height);
+        }
+
+        let started = Instant::now();
+        let frame = self.render_and_swap();
+        let submitted = Instant::now();
+        let Some(frame) = frame else {
+            return Ok(None);
+        };
+
+        // Copy to CPU and display in terminal. The readback waits for the GPU,
+        // so its time includes the actual rendering
+        let mut pixels = self.read_frame(frame);
+        if let Some(taa) = &mut self.taa {
+            pixels = taa.resolve(pixels, &self.camera, self.renderer.height);
+        }
+        let read_back = Instant::now();
+        if let Some(tuner) = &mut self.distance_tuner {
+            tuner.record(read_back - started);
+        }
+        let bytes = self.present_to_terminal(pixels)?;
+        let present_time = read_back.elapsed();
+        if let Some(stats) = &mut self.frame_stats {
+            stats.record(FrameTiming {
+                render: submitted - started,
+                readback: read_back - submitted,
+                present: present_time,
+            });
+        }
+
+        log::debug!(
+            "frame: submit {:.2?}, readback {:.2?}, present {:.2?} ({bytes} bytes)",
+            submitted - started,
+            read_back - submitted,
+            present_time
+        );
+        Ok(Some(present_time))
+    }
+
+    /// Renders a frame and starts reading it back. Returns the frame to
+    /// present now: this one, or with a frame of latency the one before it,
+    /// which the GPU finished while this one was recorded. The very first
+    /// frame then has nothing to show yet.
+    fn render_and_swap(&mut self) -> Option<InFlightFrame> {
+        self.render_frame();
+        let frame = self.start_readback();
+        if self.config.frame_latency == 0 {
+            return Some(frame);
+        }
+        self.in_flight.replace(frame)
+    }
+
+    /// Renders the scene into the off-screen texture without touching the terminal.
+    fn render_frame(&mut self) {
+        self.update_target();
+
         // Update uniforms
         self.uniforms.update_view_proj(&self.camera);
+        let block_size = self.world.config.block_size;
+        let hovered = self.target.filter(|_| self.hover_highlight);
+        self.uniforms.update_hover(
+            hovered.map(|hit| hit.block),
+            block_size,
+            self.config.hover_brightness,
+        );
+        self.uniforms
+            .update_break_progress(&self.breaker, block_size);
+        self.uniforms.update_lighting(
+            &self.config.sun,
+            self.smooth_shading,
+            self.world.config.baked_lighting.is_some(),
+        );
+        self.uniforms
+            .update_fog(self.config.fog.range(self.visible_distance()));
         self.renderer.queue.write_buffer(
             &self.uniform_buffer,
             0,
             bytemuck::cast_slice(&[self.uniforms]),
         );
 
+        if let Some(horizon) = &self.horizon {
+            let sea_level = (CHUNK_HEIGHT as f32 - 0.05) * self.world.config.block_size;
+            horizon.update(
+                &self.renderer.queue,
+                &self.config.horizon,
+                self.camera.position,
+                sea_level,
+            );
+        }
+
+        self.selection.update(
+            &self.renderer.queue,
+            self.target.map(|hit| hit.block),
+            self.world.config.block_size,
+        );
+
+        if self.show_chunk_grid {
+            self.chunk_grid.update(
+                &self.renderer.device,
+                self.camera_chunk(),
+                self.world.config.block_size,
+            );
+        }
+
+        // Translucent faces are drawn in the order their index buffers hold
+        // them, so they're re-sorted whenever the camera moves. Chunks past
+        // the render distance aren't drawn, so they wait until they are
+        let camera_chunk = self.camera_chunk();
+        let drawn: Vec<IVec2> = self
+            .geometries
+            .keys()
+            .copied()
+            .filter(|&chunk_pos| self.in_render_distance(chunk_pos, camera_chunk))
+            .collect();
+        for chunk_pos in drawn {
+            if let Some(geometry) = self.geometries.get_mut(&chunk_pos) {
+                geometry.sort_translucent(&self.renderer.queue, self.camera.position);
+            }
+        }
+
         // Render to texture
         let mut encoder =
             self.renderer
@@ -154,22 +1403,49 @@ impl MinecraftTTY {
                     label: Some("Render Encoder"),
                 });
 
+        // The depth copy is cleared to the far plane in its own pass; clearing
+        // two color attachments in one pass mixes up the clear colors on GL
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Depth Copy Clear"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.renderer.depth_color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.renderer.texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.4,
-                            g: 0.7,
-                            b: 1.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.renderer.texture_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color {
+                                r: 0.4,
+                                g: 0.7,
+                                b: 1.0,
+                                a: 1.0,
+                            }),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    }),
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.renderer.depth_color_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    }),
+                ],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                     view: &self.renderer.depth_texture_view,
                     depth_ops: Some(wgpu::Operations {
@@ -182,131 +1458,467 @@ impl MinecraftTTY {
                 timestamp_writes: None,
             });
 
-            render_pass.set_pipeline(&self.material.render_pipeline);
+            let pipeline = if self.show_back_faces {
+                &self.material.double_sided_pipeline
+            } else {
+                &self.material.render_pipeline
+            };
             render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
 
-            for geometry in &self.geometries {
-                render_pass.set_vertex_buffer(0, geometry.vertex_buffer.slice(..));
+            // The horizon goes first and just below sea level, so terrain
+            // always covers it and it hides the cliffs at the world's edge
+            if let Some(horizon) = &self.horizon {
+                render_pass.set_pipeline(&self.material.horizon_pipeline);
+                render_pass.set_vertex_buffer(0, horizon.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(horizon.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..horizon.index_count, 0, 0..1);
+            }
+
+            // Pipeline and bind group are shared by every chunk, so only the
+            // buffers change between draws. Chunks with nothing to draw, or
+            // past the render distance, are skipped rather than bound.
+            let camera_chunk = self.camera_chunk();
+            let mut visible: Vec<_> = self
+                .geometries
+                .iter()
+                .filter(|(&chunk_pos, geometry)| {
+                    !geometry.is_empty() && self.in_render_distance(chunk_pos, camera_chunk)
+                })
+                .collect();
+
+            render_pass.set_pipeline(pipeline);
+            for (_, geometry) in &visible {
+                geometry.draw(&mut render_pass, geometry.opaque_indices());
+            }
+            render_pass.set_pipeline(&self.material.decoration_pipeline);
+            for (_, geometry) in &visible {
+                geometry.draw(&mut render_pass, geometry.decoration_indices());
+            }
+
+            // Translucent faces blend over the finished opaque scene, farthest
+            // chunks first so nearer glass lands on top. Chunks are ordered by
+            // their centers, so glass near a seam can still land in the wrong
+            // order against glass just across it
+            let chunk_size = Vec3::new(CHUNK_SIZE_X as f32, 0.0, CHUNK_SIZE_Z as f32)
+                * self.world.config.block_size;
+            let distance = |chunk_pos: IVec2| {
+                let center =
+                    (Vec3::new(chunk_pos.x as f32, 0.0, chunk_pos.y as f32) + 0.5) * chunk_size;
+                let eye = self.camera.position * Vec3::new(1.0, 0.0, 1.0);
+                center.distance_squared(eye)
+            };
+            visible.sort_by(|(&a, _), (&b, _)| distance(b).total_cmp(&distance(a)));
+            render_pass.set_pipeline(&self.material.translucent_pipeline);
+            for (_, geometry) in &visible {
+                geometry.draw(&mut render_pass, geometry.translucent_indices());
+            }
+
+            if self.selection.visible {
+                let selection = &self.selection;
+                render_pass.set_pipeline(&self.material.overlay_pipeline);
+                render_pass.set_vertex_buffer(0, selection.vertex_buffer.slice(..));
                 render_pass
-                    .set_index_buffer(geometry.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.draw_indexed(0..geometry.index_count, 0, 0..1);
+                    .set_index_buffer(selection.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..selection.index_count, 0, 0..1);
+            }
+
+            if self.show_chunk_grid {
+                render_pass.set_pipeline(&self.material.line_pipeline);
+                self.chunk_grid.draw(&mut render_pass);
             }
         }
 
         self.renderer
             .queue
             .submit(std::iter::once(encoder.finish()));
+    }
 
-        // Copy to CPU and display in terminal
-        pollster::block_on(self.present_to_terminal())?;
+    /// Renders a single frame and writes it to `path` as an image. For
+    /// scripts, the path goes to stdout once it's written and the render
+    /// stats to stderr.
+    fn save_screenshot(&mut self, path: &Path) -> Result<()> {
+        let started = Instant::now();
+        self.render_frame();
+        let pixels = self.read_frame(self.start_readback());
+        let rendered = started.elapsed();
 
+        let (width, height) = (self.renderer.width, self.renderer.height);
+        image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8)
+            .with_context(|| format!("failed to write screenshot to {}", path.display()))?;
+        eprintln!("Rendered {width}x{height} in {rendered:.2?}");
+        println!("{}", path.display());
         Ok(())
     }
 
-    async fn present_to_terminal(&self) -> Result<()> {
-        let pixels = self.renderer.read_pixels().await?;
+    /// Meshes every loaded chunk and writes them to `path` as a Wavefront OBJ.
+    fn export_obj(&self, path: &Path) -> Result<()> {
+        let meshes: Vec<_> = self
+            .world
+            .chunks()
+            .map(|chunk| {
+                let light = self
+                    .world
+                    .config
+                    .sky_shadow
+                    .map(|_| self.world.sky_light(chunk.position));
+                build_lit_chunk_mesh(chunk, light.as_ref(), &self.world.config)
+            })
+            .collect();
+        let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        export::write_obj(&mut out, &meshes)?;
+        out.flush()?;
+        eprintln!("Exported {} chunks to {}", meshes.len(), path.display());
+        Ok(())
+    }
 
-        // Use a buffered writer for better performance
-        let mut stdout = std::io::BufWriter::new(std::io::stdout());
+    /// Starts reading back the frame just rendered, with its depth only when
+    /// a CPU pass will use it.
+    fn start_readback(&self) -> InFlightFrame {
+        let with_depth = self.config.sun.size_degrees > 0.0 || self.depth_view || self.outlines;
+        InFlightFrame {
+            readback: self.renderer.start_readback(with_depth),
+            view_proj: self.camera.get_proj_view_matrix(),
+            eye: self.camera.position,
+        }
+    }
+
+    /// Reads back the rendered frame with post-processing applied.
+    fn read_frame(&self, frame: InFlightFrame) -> Vec<u8> {
+        let (mut pixels, depth) = self.renderer.finish_readback(frame.readback);
+        // A toggle switched on while the frame was in flight waits for the next
+        let Some(depth) = depth else {
+            return pixels;
+        };
+        let (near, far) = (self.camera.near, self.camera.far);
+
+        sky::draw_sun(
+            &mut pixels,
+            &depth,
+            self.renderer.width,
+            self.renderer.height,
+            frame.view_proj.inverse(),
+            frame.eye,
+            &self.config.sun,
+        );
+        if self.depth_view {
+            postprocess::depth_to_grayscale(&mut pixels, &depth, near, far);
+        }
+        if self.outlines {
+            postprocess::outline_edges(
+                &mut pixels,
+                &depth,
+                self.renderer.width,
+                self.renderer.height,
+                near,
+                far,
+            );
+        }
+        pixels
+    }
+
+    /// Draws a frame and the HUD to the terminal. Returns the number of
+    /// bytes written.
+    fn present_to_terminal(&self, mut pixels: Vec<u8>) -> Result<usize> {
+        let (width, height) = self.config.grid_size();
+        if (width, height) != (self.renderer.width, self.renderer.height) {
+            pixels = postprocess::resample(
+                &pixels,
+                self.renderer.width,
+                self.renderer.height,
+                width,
+                height,
+            );
+        }
+        if self.config.smoothing > 0 {
+            pixels = postprocess::tent_filter(&pixels, width, height, self.config.smoothing);
+        }
+        if self.config.white_balance != [1.0; 3] {
+            postprocess::white_balance(&mut pixels, self.config.white_balance);
+        }
+        if self.config.color_step > 1 {
+            postprocess::quantize(&mut pixels, self.config.color_step);
+        }
+
+        // The whole frame is kept, to mirror to `--stream`, however it's
+        // written out
+        let mut frame = FrameWriter::new(stdout().lock(), self.config.output_buffering);
 
         // Use synchronized update to prevent flickering
-        write!(stdout, "\x1b[?2026h")?; // Begin synchronized update
+        if self.sync_updates {
+            write!(frame, "\x1b[?2026h")?; // Begin synchronized update
+        }
+
+        // Hidden at startup too, but some terminals show the cursor again
+        // (on resize, focus changes), making it flicker while the frame draws
+        write!(frame, "\x1b[?25l")?;
 
         // Move cursor to top-left (don't clear screen every frame)
-        write!(stdout, "\x1b[H")?;
-
-        // Track previous colors to avoid unnecessary ANSI code output
-        let mut prev_color1: Option<[u8; 3]> = None;
-        let mut prev_color2: Option<[u8; 3]> = None;
-
-        // Sample pixels for terminal rendering
-        // Each terminal character covers 1 pixel horizontally, 2 pixels vertically
-        for terminal_row in 0..self.terminal_height {
-            // Move cursor to the beginning of this terminal row
-            write!(stdout, "\x1b[{};1H", terminal_row + 1)?;
-
-            for terminal_col in 0..self.terminal_width {
-                // Sample 2 vertical pixels for each terminal character
-                let renderer_x = terminal_col;
-                let renderer_y = terminal_row * 2;
-
-                // Get top pixel
-                let top_idx = ((renderer_y * self.renderer.width + renderer_x) * 4) as usize;
-                let c1 = if top_idx + 2 < pixels.len() {
-                    [
-                        pixels[top_idx],
-                        pixels[top_idx + 1],
-                        pixels[top_idx + 2],
-                    ]
-                } else {
-                    [0, 0, 0]
-                };
+        write!(frame, "\x1b[H")?;
 
-                // Get bottom pixel
-                let bottom_idx = (((renderer_y + 1) * self.renderer.width + renderer_x) * 4) as usize;
-                let c2 = if bottom_idx + 2 < pixels.len() {
-                    [
-                        pixels[bottom_idx],
-                        pixels[bottom_idx + 1],
-                        pixels[bottom_idx + 2],
-                    ]
-                } else {
-                    c1 // Use top color if bottom doesn't exist
-                };
+        // The real terminal may be smaller than the viewport; only draw what
+        // fits so nothing wraps or scrolls
+        let viewport = match terminal::size() {
+            Ok((cols, rows)) => self.viewport.clip(cols as u32, rows as u32),
+            Err(_) => self.viewport,
+        };
+        let (cell_width, cell_height) = self.config.cell_pixels;
+        let viewport = Viewport {
+            cols: viewport.cols.min(width.div_ceil(cell_width)),
+            rows: viewport.rows.min(height.div_ceil(cell_height)),
+            ..viewport
+        };
 
-                if prev_color1.is_none()
-                    || prev_color1.unwrap() != c1
-                    || prev_color2.is_none()
-                    || prev_color2.unwrap() != c2
-                {
-                    // c1 = foreground, c2 = background
-                    write!(stdout, "\x1b[38;2;{};{};{}m", c1[0], c1[1], c1[2])?;
-                    write!(stdout, "\x1b[48;2;{};{};{}m", c2[0], c2[1], c2[2])?;
-
-                    prev_color1 = Some(c1);
-                    prev_color2 = Some(c2);
-                }
+        present::write_frame(
+            &mut frame,
+            &pixels,
+            width,
+            height,
+            viewport,
+            self.config.glyph,
+            self.config.cell_pixels,
+        )?;
 
-                write!(stdout, "▀")?;
+        self.config.crosshair.draw(
+            &mut frame,
+            &pixels,
+            width,
+            height,
+            self.config.cell_pixels,
+            viewport,
+        )?;
+        // Panels stack below the notice line
+        let mut panel_row = 1;
+        if self.input_profile == InputProfile::Inspection {
+            let lines = ["Inspecting: H/J/K/L orbit, arrows pan (Tab for game keys)".to_string()];
+            hud::draw_panel(&mut frame, panel_row, &lines, viewport)?;
+            panel_row += 2;
+        }
+        if self.streaming_paused {
+            let lines = ["STREAMING PAUSED (Shift+P resumes)".to_string()];
+            hud::draw_panel(&mut frame, panel_row, &lines, viewport)?;
+            panel_row += 2;
+        }
+        if self.show_stats {
+            let lines = self.mesh_stats();
+            hud::draw_panel(&mut frame, panel_row, &lines, viewport)?;
+            panel_row += lines.len() as u32 + 1;
+        }
+        if self.show_world_info {
+            hud::draw_panel(&mut frame, panel_row, &self.world_info(), viewport)?;
+        }
+        hud::draw_hotbar(&mut frame, viewport, self.selected_block)?;
+        if self.quit_prompt {
+            hud::draw_notice(&mut frame, "Save before exit? (y/n/esc)", viewport)?;
+        } else if let Some((text, until)) = &self.notice {
+            if Instant::now() < *until {
+                hud::draw_notice(&mut frame, text, viewport)?;
             }
         }
 
         // End synchronized update
-        write!(stdout, "\x1b[?2026l")?; // End synchronized update
+        if self.sync_updates {
+            write!(frame, "\x1b[?2026l")?; // End synchronized update
+        }
 
-        stdout.flush()?;
-        Ok(())
+        let frame = frame.finish()?;
+        if let Some(stream) = &self.stream {
+            stream.send(&frame);
+        }
+        Ok(frame.len())
     }
 
     fn run(&mut self) -> Result<()> {
         terminal::enable_raw_mode()?;
         execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        // Ask for key release events to track held keys, and focus changes
+        // to let go of them when the releases go elsewhere. Terminals without
+        // the kitty keyboard protocol ignore the request, and Windows reports
+        // releases anyway but refuses it, so failing is fine
+        let track_releases = self.config.key_repeat_rate > 0.0;
+        let pushed_flags = track_releases && {
+            let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+            execute!(stdout(), PushKeyboardEnhancementFlags(flags)).is_ok()
+        };
+        if track_releases {
+            execute!(stdout(), EnableFocusChange).ok();
+        }
+        // Pointer motion for mouse look, reported for every cell crossed
+        execute!(stdout(), EnableMouseCapture).ok();
 
-        let result = loop {
-            // Handle input first for better responsiveness
-            if !self.handle_input()? {
-                break Ok(());
-            }
+        let frame_time = Duration::from_secs(1) / self.config.fps;
+        let mut skip_frames = 0;
+        let mut rendered = 0;
+        let result = 'frames: loop {
+            let deadline = Instant::now() + frame_time;
 
-            if let Err(e) = self.render() {
-                break Err(e);
+            if skip_frames > 0 {
+                skip_frames -= 1;
+            } else {
+                // Only presented frames count, so `--frames` shows exactly
+                // that many whatever the frame latency
+                let present_time = match self.render() {
+                    Ok(Some(present_time)) => present_time,
+                    Ok(None) => continue,
+                    Err(e) => break Err(e),
+                };
+                rendered += 1;
+                if self.config.frames.is_some_and(|frames| rendered >= frames) {
+                    break Ok(());
+                }
+
+                // When the terminal can't take a frame per budget, give it
+                // time to catch up instead of queueing more output
+                if self.config.frame_skip && present_time > frame_time {
+                    skip_frames = (present_time.as_nanos() / frame_time.as_nanos()) as u32;
+                    log::info!(
+                        "terminal took {:.1?} to present a frame, skipping {skip_frames} frame(s)",
+                        present_time
+                    );
+                }
             }
 
-            std::thread::sleep(std::time::Duration::from_millis(33)); // ~30 FPS
+            // Block on input for the rest of the frame budget so key presses
+            // are applied as soon as they arrive and the CPU idles otherwise
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                // Errors still go through the cleanup below so the cursor
+                // and the main screen come back
+                match self.handle_input(remaining) {
+                    Ok(true) => {}
+                    Ok(false) => break 'frames Ok(()),
+                    Err(e) => break 'frames Err(e),
+                }
+            }
         };
 
+        if pushed_flags {
+            execute!(stdout(), PopKeyboardEnhancementFlags).ok();
+        }
+        if track_releases {
+            execute!(stdout(), DisableFocusChange).ok();
+        }
+        execute!(stdout(), DisableMouseCapture).ok();
         execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen)?;
         terminal::disable_raw_mode()?;
 
+        if let (Ok(()), Some(stats)) = (&result, &self.frame_stats) {
+            eprint!("{stats}");
+        }
         result
     }
 }
 
 fn main() -> Result<()> {
-    env_logger::init();
+    let Some(config) = Config::from_args()? else {
+        return Ok(());
+    };
+
+    let mut logger = env_logger::Builder::from_default_env();
+    if config.gpu.debug {
+        logger
+            .filter_module("wgpu_core", log::LevelFilter::Info)
+            .filter_module("wgpu_hal", log::LevelFilter::Info);
+    }
+    logger.init();
+
+    // Runs that never reach the terminal have no use for the title screen
+    let interactive = config.screenshot.is_none() && config.export_obj.is_none();
+    let splash = if config.splash && interactive {
+        let status = "Generating the world...";
+        Some(Splash::show(
+            config.world.seed,
+            config.input_profile,
+            config.viewport,
+            status,
+        )?)
+    } else {
+        None
+    };
 
     pollster::block_on(async {
-        let mut app = MinecraftTTY::new().await?;
-        app.run()
+        let mut app = MinecraftTTY::new(&config).await?;
+        if let Some(path) = &config.export_obj {
+            app.export_obj(path)?;
+        }
+        match &config.screenshot {
+            Some(path) => app.save_screenshot(path),
+            None if config.export_obj.is_some() => Ok(()),
+            None => {
+                if let Some(splash) = splash {
+                    splash.draw("Press any key to start")?;
+                    splash.wait_for_key()?;
+                }
+                app.run()
+            }
+        }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+    use world_gen::seed_from_str;
+
+    /// Sky clear color (0.4, 0.7, 1.0) after sRGB encoding.
+    const SKY: [u8; 3] = [170, 218, 255];
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+        let idx = ((y * width + x) * 4) as usize;
+        [pixels[idx], pixels[idx + 1], pixels[idx + 2]]
+    }
+
+    /// Renders one frame of a fixed seed and camera pose through the headless
+    /// path. Runs on whatever adapter is available, including software ones.
+    #[test]
+    fn tour_frame_shows_terrain_below_sky() {
+        let mut config = Config::default();
+        config.world.seed = seed_from_str("tour");
+        // Above the 2x2 chunk area, looking down at it from its edge
+        config.position = Vec3::new(-12.0, 20.0, 0.0);
+        config.pitch = -40.0;
+
+        let mut app = match pollster::block_on(MinecraftTTY::new(&config)) {
+            Ok(app) => app,
+            Err(err) => {
+                eprintln!("skipping tour test, no adapter available: {err}");
+                return;
+            }
+        };
+
+        app.render_frame();
+        let pixels = app.renderer.read_pixels();
+        let (width, height) = (app.renderer.width, app.renderer.height);
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+        assert_eq!(pixel(&pixels, width, width / 2, 0), SKY);
+        assert_ne!(pixel(&pixels, width, width / 2, height / 2), SKY);
+        assert_ne!(pixel(&pixels, width, width / 2, height - 1), SKY);
+    }
+
+    /// With a frame of latency the first frame is only started, so every
+    /// later render presents one frame and `--frames` counts them all.
+    #[test]
+    fn frame_latency_presents_from_the_second_frame() {
+        for (latency, presented) in [(0, 3), (1, 2)] {
+            let config = Config {
+                frame_latency: latency,
+                ..Config::default()
+            };
+            let mut app = match pollster::block_on(MinecraftTTY::new(&config)) {
+                Ok(app) => app,
+                Err(err) => {
+                    eprintln!("skipping frame latency test, no adapter available: {err}");
+                    return;
+                }
+            };
+            let frames = (0..3).filter(|_| app.render_and_swap().is_some()).count();
+            assert_eq!(frames, presented, "latency {latency}");
+        }
+    }
+}
@@ -1,11 +1,42 @@
 use crate::geometry::Vertex;
+use crate::renderer::DEPTH_COLOR_FORMAT;
 use anyhow::Result;
 use image::GenericImageView;
 
+/// Default depth bias of the overlay pipeline, in steps of the depth format's
+/// precision. Negative values pull overlays lying flat on block faces towards
+/// the camera so they don't z-fight with them; `--overlay-depth-bias`
+/// overrides it.
+pub const DEFAULT_OVERLAY_DEPTH_BIAS: i32 = -4;
+
+/// Extra overlay bias per unit of depth slope, for faces seen at a glancing
+/// angle, where depth changes fastest across a pixel.
+pub const OVERLAY_DEPTH_BIAS_SLOPE: f32 = -1.0;
+
 pub struct Material {
     pub render_pipeline: wgpu::RenderPipeline,
+    /// Same as `render_pipeline` without back-face culling, for debugging
+    /// faces that go missing because of their winding.
+    pub double_sided_pipeline: wgpu::RenderPipeline,
+    /// Draws decorations such as flowers without culling, leaving out the
+    /// texels around them.
+    pub decoration_pipeline: wgpu::RenderPipeline,
+    /// Alpha-blends transparent blocks such as glass over what's already
+    /// drawn, without writing depth.
+    pub translucent_pipeline: wgpu::RenderPipeline,
+    /// Draws the untextured horizon plane in its vertex color.
+    pub horizon_pipeline: wgpu::RenderPipeline,
+    /// Draws untextured overlays such as the selection frame in their vertex
+    /// color, depth-biased to stay on top of the faces they lie on.
+    pub overlay_pipeline: wgpu::RenderPipeline,
+    /// Draws line lists in their vertex color, for debug overlays such as the
+    /// chunk grid.
+    pub line_pipeline: wgpu::RenderPipeline,
     bind_group_layout: wgpu::BindGroupLayout,
     pub texture_view: wgpu::TextureView,
+    /// Brightness detail multiplied into the block atlas, such as baked
+    /// ambient occlusion; plain white when `--detail-map` is off.
+    pub detail_view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
 }
 
@@ -14,47 +45,32 @@ impl Material {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         _uniform_buffer: &wgpu::Buffer,
+        detail_map: bool,
+        overlay_depth_bias: i32,
     ) -> Result<Self> {
-        // Load texture
-        let texture_bytes = include_bytes!("../textures/blocks.png");
-        let texture_image = image::load_from_memory(texture_bytes)?;
-        let texture_rgba = texture_image.to_rgba8();
-        let texture_dimensions = texture_image.dimensions();
-
-        let texture_size = wgpu::Extent3d {
-            width: texture_dimensions.0,
-            height: texture_dimensions.1,
-            depth_or_array_layers: 1,
+        let texture_view = load_texture(
+            device,
+            queue,
+            "Block Texture",
+            include_bytes!("../textures/blocks.png"),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+        )?;
+        // Per-texel brightness laid out like the block atlas. When disabled a
+        // single white texel stands in, so the shader and bind group don't change
+        let detail_view = if detail_map {
+            load_texture(
+                device,
+                queue,
+                "Block Detail Texture",
+                include_bytes!("../textures/blocks_detail.png"),
+                wgpu::TextureFormat::Rgba8Unorm,
+            )?
+        } else {
+            let white = [255; 4];
+            let format = wgpu::TextureFormat::Rgba8Unorm;
+            create_texture(device, queue, "Block Detail Texture", 1, 1, &white, format)
         };
 
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Block Texture"),
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &texture_rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * texture_dimensions.0),
-                rows_per_image: Some(texture_dimensions.1),
-            },
-            texture_size,
-        );
-
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::Repeat,
             address_mode_v: wgpu::AddressMode::Repeat,
@@ -74,7 +90,7 @@ impl Material {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -98,6 +114,16 @@ impl Material {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
             ],
             label: Some("texture_bind_group_layout"),
         });
@@ -109,51 +135,64 @@ impl Material {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
+        let pipeline = |fragment_entry_point, options| {
+            create_pipeline(
+                device,
+                &render_pipeline_layout,
+                &shader,
+                fragment_entry_point,
+                options,
+            )
+        };
+        let back_face_culling = PipelineOptions {
+            cull_mode: Some(wgpu::Face::Back),
+            ..PipelineOptions::default()
+        };
+        let render_pipeline = pipeline("fs_main", back_face_culling);
+        let double_sided_pipeline = pipeline("fs_main", PipelineOptions::default());
+        let decoration_pipeline = pipeline("fs_cutout", PipelineOptions::default());
+        let translucent_pipeline = pipeline(
+            "fs_main",
+            PipelineOptions {
+                translucent: true,
+                ..back_face_culling
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
+        );
+        let horizon_pipeline = pipeline("fs_horizon", PipelineOptions::default());
+        // Blended like glass so overlays leave depth alone, though they're
+        // opaque
+        let overlay_pipeline = pipeline(
+            "fs_overlay",
+            PipelineOptions {
+                translucent: true,
+                bias: wgpu::DepthBiasState {
+                    constant: overlay_depth_bias,
+                    slope_scale: OVERLAY_DEPTH_BIAS_SLOPE,
+                    clamp: 0.0,
+                },
+                ..PipelineOptions::default()
             },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+        );
+        let line_pipeline = pipeline(
+            "fs_overlay",
+            PipelineOptions {
+                translucent: true,
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..PipelineOptions::default()
             },
-            multiview: None,
-        });
+        );
 
         Ok(Self {
             render_pipeline,
+            double_sided_pipeline,
+            decoration_pipeline,
+            translucent_pipeline,
+            horizon_pipeline,
+            overlay_pipeline,
+            line_pipeline,
             bind_group_layout,
             texture_view,
+            detail_view,
             sampler,
         })
     }
@@ -178,8 +217,158 @@ impl Material {
                     binding: 2,
                     resource: wgpu::BindingResource::Sampler(&self.sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&self.detail_view),
+                },
             ],
             label: Some("texture_bind_group"),
         })
     }
 }
+
+/// Decodes an image and uploads it as a texture of the given format.
+fn load_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+    bytes: &[u8],
+    format: wgpu::TextureFormat,
+) -> Result<wgpu::TextureView> {
+    let image = image::load_from_memory(bytes)?;
+    let (width, height) = image.dimensions();
+    Ok(create_texture(
+        device,
+        queue,
+        label,
+        width,
+        height,
+        &image.to_rgba8(),
+        format,
+    ))
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    format: wgpu::TextureFormat,
+) -> wgpu::TextureView {
+    let texture_size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: texture_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        rgba,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width),
+            rows_per_image: Some(height),
+        },
+        texture_size,
+    );
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// How a pipeline differs from a plain opaque one drawing triangles.
+#[derive(Debug, Clone, Copy, Default)]
+struct PipelineOptions {
+    cull_mode: Option<wgpu::Face>,
+    /// Alpha-blend over the scene without writing either depth buffer.
+    translucent: bool,
+    bias: wgpu::DepthBiasState,
+    topology: wgpu::PrimitiveTopology,
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    options: PipelineOptions,
+) -> wgpu::RenderPipeline {
+    let PipelineOptions {
+        cull_mode,
+        translucent,
+        bias,
+        topology,
+    } = options;
+    // Translucent surfaces blend over the scene and leave the depth buffers
+    // alone, so what's behind them still counts as the nearest surface
+    let (blend, depth_copy_writes) = if translucent {
+        (wgpu::BlendState::ALPHA_BLENDING, wgpu::ColorWrites::empty())
+    } else {
+        (wgpu::BlendState::REPLACE, wgpu::ColorWrites::ALL)
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(wgpu::ColorTargetState {
+                    format: DEPTH_COLOR_FORMAT,
+                    blend: None,
+                    write_mask: depth_copy_writes,
+                }),
+            ],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: !translucent,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias,
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,32 @@
+use crate::world_gen::BlockType;
+use glam::IVec3;
+
+/// Receives block-level events as the camera moves through the world, for
+/// reacting to them with sounds, logging or game logic. Every method does
+/// nothing by default, so an observer only implements the events it needs.
+pub trait WorldObserver {
+    /// The walking camera stepped onto the block at `position`.
+    fn on_enter_block(&mut self, _block_type: BlockType, _position: IVec3) {}
+
+    /// The crosshair moved onto the block at `position`, or off every block
+    /// when `None`.
+    fn on_target_block(&mut self, _target: Option<(BlockType, IVec3)>) {}
+}
+
+/// Logs every event at info level, with `--log-block-events`.
+pub struct LogObserver;
+
+impl WorldObserver for LogObserver {
+    fn on_enter_block(&mut self, block_type: BlockType, position: IVec3) {
+        log::info!("stepped onto {} at {position}", block_type.name());
+    }
+
+    fn on_target_block(&mut self, target: Option<(BlockType, IVec3)>) {
+        match target {
+            Some((block_type, position)) => {
+                log::info!("targeting {} at {position}", block_type.name())
+            }
+            None => log::info!("targeting nothing"),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+// Packs the rendered frame's RGB bytes without alpha into a narrower
+// texture: texel x of each row holds bytes 4x..4x+3 of the row's R, G, B
+// stream, so reading it back moves three bytes per pixel instead of four.
+
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+
+// Covers the target with one triangle
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// The frame is sRGB, so loads come back linear; encoding them again makes
+// the bytes match an RGBA readback exactly
+fn encode_srgb(linear: f32) -> f32 {
+    if (linear <= 0.0031308) {
+        return linear * 12.92;
+    }
+    return 1.055 * pow(linear, 1.0 / 2.4) - 0.055;
+}
+
+@fragment
+fn fs_pack(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let width = i32(textureDimensions(frame).x);
+    let y = i32(position.y);
+    let first = i32(position.x) * 4;
+    var bytes: array<f32, 4>;
+    for (var i = 0; i < 4; i++) {
+        let byte = first + i;
+        // Bytes past the row's last pixel are padding
+        let pixel = textureLoad(frame, vec2<i32>(min(byte / 3, width - 1), y), 0);
+        bytes[i] = encode_srgb(pixel[byte % 3]);
+    }
+    return vec4<f32>(bytes[0], bytes[1], bytes[2], bytes[3]);
+}
@@ -1,6 +1,8 @@
 // Perlin noise implementation
 // Based on https://github.com/mgord9518/perlin-zig/blob/main/lib/perlin.zig
 
+use crate::rng::Rng;
+
 const PERMUTATION: [u8; 256] = [
     151, 160, 137, 91,  90,  15,  131, 13,  201, 95,  96,  53,  194, 233, 7,   225,
     140, 36,  103, 30,  69,  142, 8,   99,  37,  240, 21,  10,  23,  190, 6,   148,
@@ -20,7 +22,32 @@ const PERMUTATION: [u8; 256] = [
     222, 114, 67,  29,  24,  72,  243, 141, 128, 195, 78,  66,  215, 61,  156, 180,
 ];
 
-pub fn noise3d(x: f64, y: f64, z: f64) -> f64 {
+/// Perlin noise with a permutation table derived from a world seed.
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    permutation: [u8; 256],
+}
+
+impl Perlin {
+    /// Seed 0 keeps the reference permutation table; any other seed shuffles it.
+    pub fn new(seed: u64) -> Self {
+        let mut permutation = PERMUTATION;
+        if seed != 0 {
+            let mut rng = Rng::new(seed);
+            for i in (1..permutation.len()).rev() {
+                let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+                permutation.swap(i, j);
+            }
+        }
+        Self { permutation }
+    }
+
+    pub fn noise3d(&self, x: f64, y: f64, z: f64) -> f64 {
+        noise3d_with(&self.permutation, x, y, z)
+    }
+}
+
+fn noise3d_with(permutation: &[u8; 256], x: f64, y: f64, z: f64) -> f64 {
     let x_floor = x.floor();
     let y_floor = y.floor();
     let z_floor = z.floor();
@@ -38,25 +65,25 @@ pub fn noise3d(x: f64, y: f64, z: f64) -> f64 {
     let w = fade(z_frac);
     
     // Hash coordinates of the 8 cube corners
-    let a = PERMUTATION[x_int as usize];
-    let aa = PERMUTATION[(a.wrapping_add(y_int)) as usize];
-    let ab = PERMUTATION[(a.wrapping_add(y_int).wrapping_add(1)) as usize];
-    let b = PERMUTATION[(x_int.wrapping_add(1)) as usize];
-    let ba = PERMUTATION[(b.wrapping_add(y_int)) as usize];
-    let bb = PERMUTATION[(b.wrapping_add(y_int).wrapping_add(1)) as usize];
+    let a = permutation[x_int as usize];
+    let aa = permutation[(a.wrapping_add(y_int)) as usize];
+    let ab = permutation[(a.wrapping_add(y_int).wrapping_add(1)) as usize];
+    let b = permutation[(x_int.wrapping_add(1)) as usize];
+    let ba = permutation[(b.wrapping_add(y_int)) as usize];
+    let bb = permutation[(b.wrapping_add(y_int).wrapping_add(1)) as usize];
     
     // Add blended results from all 8 corners of the cube
     lerp(w,
         lerp(v,
-            lerp(u, grad3d(PERMUTATION[(aa.wrapping_add(z_int)) as usize], x_frac, y_frac, z_frac),
-                    grad3d(PERMUTATION[(ba.wrapping_add(z_int)) as usize], x_frac - 1.0, y_frac, z_frac)),
-            lerp(u, grad3d(PERMUTATION[(ab.wrapping_add(z_int)) as usize], x_frac, y_frac - 1.0, z_frac),
-                    grad3d(PERMUTATION[(bb.wrapping_add(z_int)) as usize], x_frac - 1.0, y_frac - 1.0, z_frac))),
+            lerp(u, grad3d(permutation[(aa.wrapping_add(z_int)) as usize], x_frac, y_frac, z_frac),
+                    grad3d(permutation[(ba.wrapping_add(z_int)) as usize], x_frac - 1.0, y_frac, z_frac)),
+            lerp(u, grad3d(permutation[(ab.wrapping_add(z_int)) as usize], x_frac, y_frac - 1.0, z_frac),
+                    grad3d(permutation[(bb.wrapping_add(z_int)) as usize], x_frac - 1.0, y_frac - 1.0, z_frac))),
         lerp(v,
-            lerp(u, grad3d(PERMUTATION[(aa.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac, y_frac, z_frac - 1.0),
-                    grad3d(PERMUTATION[(ba.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac - 1.0, y_frac, z_frac - 1.0)),
-            lerp(u, grad3d(PERMUTATION[(ab.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac, y_frac - 1.0, z_frac - 1.0),
-                    grad3d(PERMUTATION[(bb.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac - 1.0, y_frac - 1.0, z_frac - 1.0))))
+            lerp(u, grad3d(permutation[(aa.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac, y_frac, z_frac - 1.0),
+                    grad3d(permutation[(ba.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac - 1.0, y_frac, z_frac - 1.0)),
+            lerp(u, grad3d(permutation[(ab.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac, y_frac - 1.0, z_frac - 1.0),
+                    grad3d(permutation[(bb.wrapping_add(z_int).wrapping_add(1)) as usize], x_frac - 1.0, y_frac - 1.0, z_frac - 1.0))))
 }
 
 fn fade(t: f64) -> f64 {
@@ -0,0 +1,181 @@
+/// Relative jump in view distance between neighbouring pixels that counts as
+/// an edge.
+const EDGE_THRESHOLD: f32 = 0.15;
+
+/// Brightness kept on outlined pixels.
+const EDGE_SHADE: f32 = 0.15;
+
+/// Converts a depth-buffer value back to view-space distance for a
+/// `perspective_rh` projection with the given clip planes.
+pub fn linearize_depth(depth: f32, near: f32, far: f32) -> f32 {
+    near * far / (far - depth * (far - near))
+}
+
+/// Darkens pixels sitting on a depth discontinuity, drawing outlines around
+/// block silhouettes. `pixels` is RGBA8 and `depth` holds one value per pixel.
+pub fn outline_edges(
+    pixels: &mut [u8],
+    depth: &[f32],
+    width: u32,
+    height: u32,
+    near: f32,
+    far: f32,
+) {
+    let (width, height) = (width as usize, height as usize);
+    if depth.len() < width * height || pixels.len() < width * height * 4 {
+        return;
+    }
+
+    let distance: Vec<f32> = depth
+        .iter()
+        .map(|&d| linearize_depth(d, near, far))
+        .collect();
+    // Only the nearer side of a discontinuity is darkened, so outlines hug
+    // the foreground block instead of bleeding onto what's behind it
+    let outlines = |here: f32, other: f32| other - here > EDGE_THRESHOLD * here;
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let here = distance[i];
+            let on_edge = (x > 0 && outlines(here, distance[i - 1]))
+                || (x + 1 < width && outlines(here, distance[i + 1]))
+                || (y > 0 && outlines(here, distance[i - width]))
+                || (y + 1 < height && outlines(here, distance[i + width]));
+            if on_edge {
+                darken(&mut pixels[i * 4..i * 4 + 3]);
+            }
+        }
+    }
+}
+
+/// Replaces the image with its depth buffer in grayscale, near surfaces
+/// white and far ones dark. Distances are normalized to the visible range so
+/// the whole gradient is used; empty sky is drawn black.
+pub fn depth_to_grayscale(pixels: &mut [u8], depth: &[f32], near: f32, far: f32) {
+    let distance: Vec<Option<f32>> = depth
+        .iter()
+        .map(|&d| (d < 1.0).then(|| linearize_depth(d, near, far)))
+        .collect();
+    let (min, max) = distance
+        .iter()
+        .flatten()
+        .fold((f32::INFINITY, 0.0f32), |(min, max), &d| (min.min(d), max.max(d)));
+    let range = (max - min).max(f32::EPSILON);
+
+    for (rgba, distance) in pixels.chunks_exact_mut(4).zip(distance) {
+        let value = match distance {
+            Some(d) => (255.0 * (1.0 - 0.8 * (d - min) / range)) as u8,
+            None => 0,
+        };
+        rgba[..3].fill(value);
+    }
+}
+
+/// Blurs the image with a tent filter of the given radius, so each pixel
+/// blends with its neighbours weighted by distance. Edges are clamped.
+pub fn tent_filter(pixels: &[u8], width: u32, height: u32, radius: u32) -> Vec<u8> {
+    let (width, height, radius) = (width as i64, height as i64, radius as i64);
+    let mut out = pixels.to_vec();
+    if radius == 0 || pixels.len() < (width * height * 4) as usize {
+        return out;
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u64; 3];
+            let mut total = 0u64;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let weight = ((radius + 1 - dx.abs()) * (radius + 1 - dy.abs())) as u64;
+                    let sx = (x + dx).clamp(0, width - 1);
+                    let sy = (y + dy).clamp(0, height - 1);
+                    let i = ((sy * width + sx) * 4) as usize;
+                    for c in 0..3 {
+                        sum[c] += pixels[i + c] as u64 * weight;
+                    }
+                    total += weight;
+                }
+            }
+            let i = ((y * width + x) * 4) as usize;
+            for c in 0..3 {
+                out[i + c] = ((sum[c] + total / 2) / total) as u8;
+            }
+        }
+    }
+    out
+}
+
+/// Resizes a `width` x `height` image to `out_width` x `out_height`. Each
+/// output pixel averages the source pixels it covers, or repeats the nearest
+/// one when scaling up.
+pub fn resample(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    out_width: u32,
+    out_height: u32,
+) -> Vec<u8> {
+    let mut out = vec![255; (out_width * out_height * 4) as usize];
+    if pixels.len() < (width * height * 4) as usize {
+        return out;
+    }
+    // Source pixels from `start` up to but not including `end`, at least one
+    let span = |i: u32, from: u32, to: u32| {
+        let start = (i as u64 * from as u64 / to as u64) as u32;
+        let end = ((i as u64 + 1) * from as u64).div_ceil(to as u64) as u32;
+        start..end.clamp(start + 1, from)
+    };
+
+    for y in 0..out_height {
+        let rows = span(y, height, out_height);
+        for x in 0..out_width {
+            let cols = span(x, width, out_width);
+            let mut sum = [0u32; 3];
+            let mut total = 0;
+            for sy in rows.clone() {
+                for sx in cols.clone() {
+                    let i = ((sy * width + sx) * 4) as usize;
+                    for c in 0..3 {
+                        sum[c] += pixels[i + c] as u32;
+                    }
+                    total += 1;
+                }
+            }
+            let i = ((y * out_width + x) * 4) as usize;
+            for c in 0..3 {
+                out[i + c] = ((sum[c] + total / 2) / total) as u8;
+            }
+        }
+    }
+    out
+}
+
+/// Scales the red, green and blue channels by independent gains, e.g. to
+/// cancel out a terminal color scheme's tint. Results are clamped to 0..=255.
+pub fn white_balance(pixels: &mut [u8], gains: [f32; 3]) {
+    for rgba in pixels.chunks_exact_mut(4) {
+        for (channel, gain) in rgba.iter_mut().zip(gains) {
+            *channel = (*channel as f32 * gain).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// Rounds each color channel to the nearest multiple of `step`, so
+/// neighbouring cells that differ by a shade share a color and need no new
+/// escape sequence.
+pub fn quantize(pixels: &mut [u8], step: u8) {
+    let step = step as u16;
+    for rgba in pixels.chunks_exact_mut(4) {
+        for channel in &mut rgba[..3] {
+            let rounded = (*channel as u16 + step / 2) / step * step;
+            *channel = rounded.min(255) as u8;
+        }
+    }
+}
+
+fn darken(rgb: &mut [u8]) {
+    for channel in rgb {
+        *channel = (*channel as f32 * EDGE_SHADE) as u8;
+    }
+}
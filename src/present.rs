@@ -0,0 +1,570 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::str::FromStr;
+
+/// Character used to draw a terminal cell from the pixels it covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Glyph {
+    /// "▀": the foreground paints the top half, the background the bottom one.
+    #[default]
+    #[serde(rename = "upper")]
+    UpperHalf,
+    /// "▄": the foreground paints the bottom half, the background the top one.
+    #[serde(rename = "lower")]
+    LowerHalf,
+    /// A blank cell whose background is the average of its pixels. Halves the
+    /// vertical resolution but avoids gaps on fonts with poor block glyphs.
+    Full,
+    /// One of the 16 quadrant glyphs ("▘", "▞", "▙", ...), splitting the cell
+    /// into its brighter and darker quarters. Doubles the horizontal detail
+    /// of the half blocks on fonts that draw quadrants, given cells of at
+    /// least two pixel columns (`--cell-pixels 2x2`).
+    Quadrant,
+}
+
+/// Quadrant glyphs indexed by the quarters the foreground paints: 1 top
+/// left, 2 top right, 4 bottom left, 8 bottom right.
+const QUADRANTS: [&str; 16] = [
+    " ", "▘", "▝", "▀", "▖", "▌", "▞", "▛", "▗", "▚", "▐", "▜", "▄", "▙", "▟", "█",
+];
+
+impl Glyph {
+    pub fn name(self) -> &'static str {
+        match self {
+            Glyph::UpperHalf => "upper",
+            Glyph::LowerHalf => "lower",
+            Glyph::Full => "full",
+            Glyph::Quadrant => "quadrant",
+        }
+    }
+
+    /// The glyph to switch to when cycling through them.
+    pub fn next(self) -> Glyph {
+        match self {
+            Glyph::UpperHalf => Glyph::LowerHalf,
+            Glyph::LowerHalf => Glyph::Full,
+            Glyph::Full => Glyph::Quadrant,
+            Glyph::Quadrant => Glyph::UpperHalf,
+        }
+    }
+
+    /// A representative symbol, for naming the glyph on screen.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Glyph::UpperHalf => "▀",
+            Glyph::LowerHalf => "▄",
+            Glyph::Full => " ",
+            Glyph::Quadrant => "▚",
+        }
+    }
+
+    /// Whether the glyph draws anything with the foreground color.
+    pub fn uses_foreground(self) -> bool {
+        self != Glyph::Full
+    }
+
+    /// Parts the glyph splits a cell into, as columns and rows. Each part's
+    /// color is the average of the pixels it covers.
+    pub fn parts(self) -> (u32, u32) {
+        match self {
+            Glyph::UpperHalf | Glyph::LowerHalf => (1, 2),
+            Glyph::Full => (1, 1),
+            Glyph::Quadrant => (2, 2),
+        }
+    }
+
+    /// Returns the symbol and (foreground, background) colors for a cell
+    /// whose parts, row by row, have the given colors.
+    pub fn cell(self, parts: &[[u8; 3]]) -> (&'static str, [u8; 3], [u8; 3]) {
+        match self {
+            Glyph::UpperHalf => ("▀", parts[0], parts[1]),
+            Glyph::LowerHalf => ("▄", parts[1], parts[0]),
+            Glyph::Full => (" ", parts[0], parts[0]),
+            Glyph::Quadrant => {
+                // Quarters brighter than the cell's average take the
+                // foreground and the rest the background, each drawn in
+                // the average color of its quarters
+                let luma = |c: &[u8; 3]| c[0] as u32 * 2 + c[1] as u32 * 5 + c[2] as u32;
+                let total: u32 = parts.iter().map(luma).sum();
+                let mask = (0..4)
+                    .filter(|&i| luma(&parts[i]) * 4 > total)
+                    .fold(0, |mask, i| mask | 1 << i);
+                let dark = average(parts, |i| mask & 1 << i == 0).unwrap_or_default();
+                let bright = average(parts, |i| mask & 1 << i != 0).unwrap_or(dark);
+                (QUADRANTS[mask], bright, dark)
+            }
+        }
+    }
+}
+
+/// Average color of the `colors` whose index passes `include`, if any do.
+fn average(colors: &[[u8; 3]], include: impl Fn(usize) -> bool) -> Option<[u8; 3]> {
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+    let included = colors.iter().enumerate().filter(|&(i, _)| include(i));
+    for (_, color) in included {
+        for (total, &channel) in sum.iter_mut().zip(color) {
+            *total += channel as u32;
+        }
+        count += 1;
+    }
+    (count > 0).then(|| sum.map(|total| (total / count) as u8))
+}
+
+impl FromStr for Glyph {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "upper" | "▀" => Glyph::UpperHalf,
+            "lower" | "▄" => Glyph::LowerHalf,
+            "full" | "block" => Glyph::Full,
+            "quadrant" | "quad" => Glyph::Quadrant,
+            other => bail!("unknown glyph '{other}' (expected upper, lower, full or quadrant)"),
+        })
+    }
+}
+
+/// Rectangle of terminal cells the view is drawn into, so it can sit inside
+/// a larger TUI. Rows and columns are 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub row: u32,
+    pub col: u32,
+    pub cols: u32,
+    pub rows: u32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            row: 0,
+            col: 0,
+            cols: 100,
+            rows: 30,
+        }
+    }
+}
+
+impl Viewport {
+    /// The part of the viewport that fits on a `cols` x `rows` terminal.
+    pub fn clip(self, cols: u32, rows: u32) -> Self {
+        Self {
+            cols: self.cols.min(cols.saturating_sub(self.col)),
+            rows: self.rows.min(rows.saturating_sub(self.row)),
+            ..self
+        }
+    }
+
+    /// Shrinks to at most `max_cols` x `max_rows`, centered in the original
+    /// rectangle.
+    pub fn cap(self, max_cols: u32, max_rows: u32) -> Self {
+        let cols = self.cols.min(max_cols);
+        let rows = self.rows.min(max_rows);
+        Self {
+            row: self.row + (self.rows - rows) / 2,
+            col: self.col + (self.cols - cols) / 2,
+            cols,
+            rows,
+        }
+    }
+}
+
+impl FromStr for Viewport {
+    type Err = anyhow::Error;
+
+    /// Parses `ROW,COL,COLS,ROWS`.
+    fn from_str(s: &str) -> Result<Self> {
+        let values: Vec<u32> = s
+            .split(',')
+            .map(|v| v.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| anyhow::anyhow!("invalid viewport '{s}' (expected row,col,cols,rows)"))?;
+        match values[..] {
+            [row, col, cols, rows] if cols > 0 && rows > 0 => Ok(Self {
+                row,
+                col,
+                cols,
+                rows,
+            }),
+            _ => bail!("invalid viewport '{s}' (expected row,col,cols,rows with a non-zero size)"),
+        }
+    }
+}
+
+/// How a frame's bytes are handed to the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputBuffering {
+    /// Build the whole frame in memory and write it with a single call. The
+    /// default everywhere, since it presented large frames faster.
+    #[default]
+    Frame,
+    /// Write the frame out in `OUTPUT_CHUNK` pieces while it's still being
+    /// built, so the terminal can start parsing it sooner.
+    Incremental,
+}
+
+impl FromStr for OutputBuffering {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "frame" | "single" => OutputBuffering::Frame,
+            "incremental" => OutputBuffering::Incremental,
+            other => bail!("unknown output buffering '{other}' (expected frame or incremental)"),
+        })
+    }
+}
+
+/// Bytes `OutputBuffering::Incremental` collects before passing them on.
+pub const OUTPUT_CHUNK: usize = 64 * 1024;
+
+/// Collects a frame's terminal output and passes it on to `out` as the
+/// buffering asks. The whole frame is kept either way, for `--stream`.
+pub struct FrameWriter<W: Write> {
+    out: W,
+    frame: Vec<u8>,
+    buffering: OutputBuffering,
+    /// Bytes of `frame` already written to `out`.
+    sent: usize,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(out: W, buffering: OutputBuffering) -> Self {
+        Self {
+            out,
+            frame: Vec::new(),
+            buffering,
+            sent: 0,
+        }
+    }
+
+    /// Writes and flushes whatever is left, returning the whole frame.
+    pub fn finish(mut self) -> std::io::Result<Vec<u8>> {
+        self.out.write_all(&self.frame[self.sent..])?;
+        self.out.flush()?;
+        Ok(self.frame)
+    }
+}
+
+impl<W: Write> Write for FrameWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.frame.extend_from_slice(buf);
+        if self.buffering == OutputBuffering::Incremental
+            && self.frame.len() - self.sent >= OUTPUT_CHUNK
+        {
+            self.out.write_all(&self.frame[self.sent..])?;
+            self.sent = self.frame.len();
+        }
+        Ok(buf.len())
+    }
+
+    /// Frames are only flushed whole, by `finish`.
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the RGB color of a pixel from an RGBA buffer, if it's in range.
+pub fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> Option<[u8; 3]> {
+    if x >= width {
+        return None;
+    }
+    let idx = (y as usize * width as usize + x as usize) * 4;
+    pixels.get(idx..idx + 3).map(|p| [p[0], p[1], p[2]])
+}
+
+/// Writes a `width` x `height` RGBA frame as terminal cells into `viewport`,
+/// each cell covering `cell` (columns, rows) pixels. A viewport smaller than
+/// the frame shows its top-left part, and pixels missing from a short buffer
+/// are drawn black.
+pub fn write_frame(
+    out: &mut impl Write,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    viewport: Viewport,
+    glyph: Glyph,
+    cell: (u32, u32),
+) -> std::io::Result<()> {
+    let (cell_width, cell_height) = cell;
+    let cols = viewport.cols.min(width.div_ceil(cell_width));
+    let rows = viewport.rows.min(height.div_ceil(cell_height));
+    let (part_cols, part_rows) = glyph.parts();
+
+    // Track previous colors to avoid unnecessary ANSI code output
+    let mut prev_colors: Option<([u8; 3], [u8; 3])> = None;
+    let mut parts = [[0; 3]; 4];
+
+    for row in 0..rows {
+        // Move cursor to the beginning of this row of the viewport
+        write!(out, "\x1b[{};{}H", viewport.row + row + 1, viewport.col + 1)?;
+
+        for col in 0..cols {
+            // Each part averages its share of the cell's pixels. Parts
+            // smaller than a pixel, or past the frame's last row or column,
+            // repeat the nearest pixel
+            for part_row in 0..part_rows {
+                let ys = span(row * cell_height, cell_height, part_row, part_rows);
+                for part_col in 0..part_cols {
+                    let xs = span(col * cell_width, cell_width, part_col, part_cols);
+                    let mut sum = [0u32; 3];
+                    for y in ys.clone() {
+                        for x in xs.clone() {
+                            let color = pixel(pixels, width, x.min(width - 1), y.min(height - 1))
+                                .unwrap_or([0, 0, 0]);
+                            for (total, channel) in sum.iter_mut().zip(color) {
+                                *total += channel as u32;
+                            }
+                        }
+                    }
+                    let count = ys.len() as u32 * xs.len() as u32;
+                    parts[(part_row * part_cols + part_col) as usize] =
+                        sum.map(|total| (total / count) as u8);
+                }
+            }
+
+            // The glyph decides which parts the foreground and background paint
+            let (symbol, fg, bg) = glyph.cell(&parts);
+            if prev_colors != Some((fg, bg)) {
+                if glyph.uses_foreground() {
+                    write!(out, "\x1b[38;2;{};{};{}m", fg[0], fg[1], fg[2])?;
+                }
+                write!(out, "\x1b[48;2;{};{};{}m", bg[0], bg[1], bg[2])?;
+
+                prev_colors = Some((fg, bg));
+            }
+
+            write!(out, "{symbol}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Pixels part `index` of `parts` covers along one side of a cell that
+/// starts at `start` and spans `size` pixels; at least one.
+fn span(start: u32, size: u32, index: u32, parts: u32) -> std::ops::Range<u32> {
+    let from = start + index * size / parts;
+    let to = start + (index + 1) * size / parts;
+    from..to.max(from + 1)
+}
+
+/// Writes `text` at a 0-based cell position with the given colors.
+pub fn draw_text(
+    out: &mut impl Write,
+    row: u32,
+    col: u32,
+    text: &str,
+    fg: [u8; 3],
+    bg: [u8; 3],
+) -> std::io::Result<()> {
+    write!(
+        out,
+        "\x1b[{};{}H\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m{}",
+        row + 1,
+        col + 1,
+        fg[0],
+        fg[1],
+        fg[2],
+        bg[0],
+        bg[1],
+        bg[2],
+        text
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(output: &[u8]) -> usize {
+        String::from_utf8_lossy(output).matches('▀').count()
+    }
+
+    fn sized(cols: u32, rows: u32) -> Viewport {
+        Viewport {
+            row: 0,
+            col: 0,
+            cols,
+            rows,
+        }
+    }
+
+    #[test]
+    fn incremental_output_writes_while_the_frame_is_built() {
+        let chunk = vec![b'x'; OUTPUT_CHUNK];
+        for buffering in [OutputBuffering::Frame, OutputBuffering::Incremental] {
+            let mut out = Vec::new();
+            let mut writer = FrameWriter::new(&mut out, buffering);
+            writer.write_all(&chunk).unwrap();
+            writer.write_all(b"tail").unwrap();
+            let sent = writer.out.len();
+            let frame = writer.finish().unwrap();
+
+            let incremental = buffering == OutputBuffering::Incremental;
+            assert_eq!(sent, if incremental { OUTPUT_CHUNK } else { 0 });
+            // Both end up writing and keeping the same bytes
+            assert_eq!(frame.len(), OUTPUT_CHUNK + 4);
+            assert_eq!(out, frame);
+        }
+    }
+
+    #[test]
+    fn write_frame_clamps_to_tiny_terminal() {
+        let pixels = vec![255; 8 * 6 * 4];
+
+        let mut out = Vec::new();
+        write_frame(
+            &mut out,
+            &pixels,
+            8,
+            6,
+            sized(1, 1),
+            Glyph::UpperHalf,
+            (1, 2),
+        )
+        .unwrap();
+        assert_eq!(cells(&out), 1);
+
+        let mut out = Vec::new();
+        write_frame(
+            &mut out,
+            &pixels,
+            8,
+            6,
+            sized(0, 0),
+            Glyph::UpperHalf,
+            (1, 2),
+        )
+        .unwrap();
+        assert_eq!(cells(&out), 0);
+    }
+
+    #[test]
+    fn write_frame_clamps_to_frame_size() {
+        // A terminal larger than the frame only gets the frame's cells, and
+        // an odd height still covers its last pixel row
+        let pixels = vec![255; 3 * 5 * 4];
+        let mut out = Vec::new();
+        write_frame(
+            &mut out,
+            &pixels,
+            3,
+            5,
+            sized(200, 100),
+            Glyph::UpperHalf,
+            (1, 2),
+        )
+        .unwrap();
+        assert_eq!(cells(&out), 3 * 3);
+    }
+
+    #[test]
+    fn write_frame_tolerates_short_pixel_buffer() {
+        let pixels = vec![255; 10];
+        let mut out = Vec::new();
+        write_frame(
+            &mut out,
+            &pixels,
+            4,
+            4,
+            sized(4, 2),
+            Glyph::LowerHalf,
+            (1, 2),
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8_lossy(&out).matches('▄').count(), 8);
+
+        let mut out = Vec::new();
+        write_frame(&mut out, &[], 0, 0, sized(1, 1), Glyph::UpperHalf, (1, 2)).unwrap();
+        assert_eq!(cells(&out), 0);
+    }
+
+    #[test]
+    fn write_frame_offsets_rows_by_viewport_origin() {
+        let pixels = vec![255; 4 * 4 * 4];
+        let viewport = Viewport {
+            row: 5,
+            col: 10,
+            cols: 4,
+            rows: 2,
+        };
+        let mut out = Vec::new();
+        write_frame(&mut out, &pixels, 4, 4, viewport, Glyph::UpperHalf, (1, 2)).unwrap();
+        let out = String::from_utf8_lossy(&out);
+        assert!(out.contains("\x1b[6;11H"));
+        assert!(out.contains("\x1b[7;11H"));
+        assert_eq!(cells(out.as_bytes()), 8);
+    }
+
+    #[test]
+    fn quadrants_split_bright_and_dark_pixels() {
+        const WHITE: [u8; 3] = [255, 255, 255];
+        const BLACK: [u8; 3] = [0, 0, 0];
+        assert_eq!(
+            Glyph::Quadrant.cell(&[WHITE, BLACK, BLACK, WHITE]),
+            ("▚", WHITE, BLACK)
+        );
+        assert_eq!(Glyph::Quadrant.cell(&[BLACK; 4]), (" ", BLACK, BLACK));
+
+        // A 2x4 cell averages two pixel rows into each quarter: the left
+        // column is bright, the right one dark
+        let mut pixels = Vec::new();
+        for _ in 0..4 {
+            pixels.extend([255, 255, 255, 255, 0, 0, 0, 255]);
+        }
+        let mut out = Vec::new();
+        write_frame(
+            &mut out,
+            &pixels,
+            2,
+            4,
+            sized(1, 1),
+            Glyph::Quadrant,
+            (2, 4),
+        )
+        .unwrap();
+        let out = String::from_utf8_lossy(&out);
+        assert!(out.ends_with("\x1b[38;2;255;255;255m\x1b[48;2;0;0;0m▌"));
+    }
+
+    #[test]
+    fn viewport_clips_to_terminal() {
+        let viewport: Viewport = "5,10,80,24".parse().unwrap();
+        assert_eq!(
+            viewport.clip(50, 20),
+            Viewport {
+                cols: 40,
+                rows: 15,
+                ..viewport
+            }
+        );
+        assert_eq!(
+            viewport.clip(5, 5),
+            Viewport {
+                cols: 0,
+                rows: 0,
+                ..viewport
+            }
+        );
+        assert!("1,2,0,3".parse::<Viewport>().is_err());
+    }
+
+    #[test]
+    fn capped_viewport_is_centered() {
+        let terminal = sized(500, 140);
+        assert_eq!(
+            terminal.cap(200, 60),
+            Viewport {
+                row: 40,
+                col: 150,
+                cols: 200,
+                rows: 60,
+            }
+        );
+        // A cap larger than the viewport leaves it alone
+        assert_eq!(terminal.cap(1000, u32::MAX), terminal);
+    }
+}
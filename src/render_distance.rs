@@ -0,0 +1,181 @@
+use glam::IVec2;
+use std::time::Duration;
+
+/// Field of view the tuned distance is measured at; narrower views reach
+/// proportionally farther and wider ones less far.
+const REFERENCE_FOV_DEGREES: f32 = 70.0;
+
+/// Weight of the newest frame in the smoothed frame time.
+const SMOOTHING: f32 = 0.1;
+
+/// Frames to wait after a change before changing again, so the chunks it
+/// streams in and out show up in the frame time first.
+const COOLDOWN_FRAMES: u32 = 30;
+
+/// The distance grows only while frames take less than this share of the
+/// target, leaving a band where it holds steady instead of oscillating.
+const GROW_HEADROOM: f32 = 0.7;
+
+/// Whether a chunk `offset` chunks from the camera's is within `distance`,
+/// so it gets loaded and drawn.
+pub fn in_range(offset: IVec2, distance: u32) -> bool {
+    offset.abs().max_element() <= distance as i32
+}
+
+/// Whether a loaded chunk `offset` chunks from the camera's should be
+/// dropped. That waits until it's `margin` chunks past the render distance,
+/// so hovering at the edge doesn't load and drop the same chunks over and
+/// over.
+pub fn should_unload(offset: IVec2, distance: u32, margin: u32) -> bool {
+    !in_range(offset, distance + margin)
+}
+
+/// Settings for `--target-frame-time`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoDistance {
+    /// Time rendering a frame should take, not counting terminal output.
+    pub target: Duration,
+    /// Bounds of the render distance, in chunks.
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Adjusts the render distance to hold a frame-time target: a chunk farther
+/// while frames are cheap, a chunk closer while they're over budget.
+pub struct DistanceTuner {
+    settings: AutoDistance,
+    /// Distance at the reference FOV.
+    base: u32,
+    /// Smoothed frame time in seconds.
+    frame_time: Option<f32>,
+    cooldown: u32,
+}
+
+impl DistanceTuner {
+    /// Starts at the largest distance and backs off if frames are too slow.
+    pub fn new(settings: AutoDistance) -> Self {
+        Self {
+            settings,
+            base: settings.max,
+            frame_time: None,
+            cooldown: COOLDOWN_FRAMES,
+        }
+    }
+
+    /// Records how long the last frame took to render.
+    pub fn record(&mut self, frame_time: Duration) {
+        let frame_time = frame_time.as_secs_f32();
+        let smoothed = match self.frame_time {
+            Some(smoothed) => smoothed + (frame_time - smoothed) * SMOOTHING,
+            None => frame_time,
+        };
+        self.frame_time = Some(smoothed);
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return;
+        }
+        let target = self.settings.target.as_secs_f32();
+        let base = if smoothed > target {
+            self.base.saturating_sub(1).max(self.settings.min)
+        } else if smoothed < target * GROW_HEADROOM {
+            (self.base + 1).min(self.settings.max)
+        } else {
+            self.base
+        };
+        if base != self.base {
+            log::debug!(
+                "render distance {} -> {base} at {:.1} ms per frame",
+                self.base,
+                smoothed * 1000.0
+            );
+            self.base = base;
+            self.cooldown = COOLDOWN_FRAMES;
+        }
+    }
+
+    /// Render distance for a camera with the given field of view.
+    pub fn distance(&self, fov_degrees: f32) -> u32 {
+        let scale = (REFERENCE_FOV_DEGREES / fov_degrees).sqrt();
+        ((self.base as f32 * scale).round() as u32).clamp(self.settings.min, self.settings.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn tuner() -> DistanceTuner {
+        DistanceTuner::new(AutoDistance {
+            target: Duration::from_millis(20),
+            min: 2,
+            max: 8,
+        })
+    }
+
+    fn run(tuner: &mut DistanceTuner, millis: u64, frames: u32) {
+        for _ in 0..frames {
+            tuner.record(Duration::from_millis(millis));
+        }
+    }
+
+    #[test]
+    fn distance_follows_frame_time_within_bounds() {
+        let mut tuner = tuner();
+        assert_eq!(tuner.distance(REFERENCE_FOV_DEGREES), 8);
+
+        // Slow frames back off one chunk per cooldown, down to the minimum
+        run(&mut tuner, 40, COOLDOWN_FRAMES * 3);
+        assert_eq!(tuner.distance(REFERENCE_FOV_DEGREES), 6);
+        run(&mut tuner, 40, COOLDOWN_FRAMES * 20);
+        assert_eq!(tuner.distance(REFERENCE_FOV_DEGREES), 2);
+
+        // Frames just under the target hold the distance
+        run(&mut tuner, 18, COOLDOWN_FRAMES * 5);
+        assert_eq!(tuner.distance(REFERENCE_FOV_DEGREES), 2);
+
+        // Cheap frames grow it back, up to the maximum
+        run(&mut tuner, 5, COOLDOWN_FRAMES * 20);
+        assert_eq!(tuner.distance(REFERENCE_FOV_DEGREES), 8);
+    }
+
+    /// Loads and unloads around a camera in chunk `x` along the X axis, the
+    /// way streaming does, and returns how many chunks were loaded.
+    fn stream(loaded: &mut HashSet<i32>, x: i32, distance: u32, margin: u32) -> usize {
+        loaded.retain(|&chunk| !should_unload(IVec2::new(chunk - x, 0), distance, margin));
+        let wanted = (x - distance as i32..=x + distance as i32)
+            .filter(|&chunk| in_range(IVec2::new(chunk - x, 0), distance));
+        wanted.filter(|&chunk| loaded.insert(chunk)).count()
+    }
+
+    #[test]
+    fn margin_stops_reloading_at_the_edge() {
+        for (margin, expected) in [(0, 2 * 10), (1, 1)] {
+            let mut loaded = HashSet::new();
+            stream(&mut loaded, 0, 4, margin);
+            // Crossing a chunk border back and forth
+            let reloads: usize = (0..10)
+                .map(|_| stream(&mut loaded, 1, 4, margin) + stream(&mut loaded, 0, 4, margin))
+                .sum();
+            assert_eq!(reloads, expected, "margin {margin}");
+        }
+
+        // Chunks just past the distance stay until they're past the margin
+        assert!(in_range(IVec2::new(4, -4), 4));
+        assert!(!in_range(IVec2::new(5, 0), 4));
+        assert!(!should_unload(IVec2::new(-6, 2), 4, 2));
+        assert!(should_unload(IVec2::new(-7, 2), 4, 2));
+    }
+
+    #[test]
+    fn narrow_fov_reaches_farther() {
+        let mut tuner = tuner();
+        run(&mut tuner, 40, COOLDOWN_FRAMES * 5);
+        let reference = tuner.distance(REFERENCE_FOV_DEGREES);
+        assert!(tuner.distance(30.0) > reference);
+        assert!(tuner.distance(120.0) < reference);
+        // Still within the bounds
+        assert_eq!(tuner.distance(1.0), 8);
+    }
+}
@@ -1,4 +1,118 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use std::str::FromStr;
+
+/// Controls how the wgpu instance and adapter are created.
+#[derive(Debug, Clone)]
+pub struct GpuOptions {
+    pub backends: wgpu::Backends,
+    /// Enables wgpu validation layers and backend debug output.
+    pub debug: bool,
+    /// Index into the list returned by `list_adapters`. `None` lets wgpu pick.
+    pub adapter_index: Option<usize>,
+    pub power_preference: wgpu::PowerPreference,
+    pub readback: ReadbackFormat,
+}
+
+impl Default for GpuOptions {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            debug: false,
+            adapter_index: None,
+            power_preference: wgpu::PowerPreference::default(),
+            readback: ReadbackFormat::default(),
+        }
+    }
+}
+
+/// How rendered frames are copied back from the GPU. wgpu has no 3-byte
+/// color formats, so dropping alpha takes a packing pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadbackFormat {
+    /// Copy the RGBA render target as is.
+    #[default]
+    Rgba,
+    /// Pack the color bytes without alpha on the GPU first, copying three
+    /// bytes per pixel instead of four at the cost of an extra pass.
+    Rgb,
+}
+
+impl FromStr for ReadbackFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "rgba" => ReadbackFormat::Rgba,
+            "rgb" => ReadbackFormat::Rgb,
+            other => bail!("unknown readback format '{other}' (expected rgba or rgb)"),
+        })
+    }
+}
+
+fn create_instance(options: &GpuOptions) -> wgpu::Instance {
+    let flags = if options.debug {
+        wgpu::InstanceFlags::debugging()
+    } else {
+        wgpu::InstanceFlags::from_build_config()
+    };
+
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: options.backends,
+        flags,
+        ..Default::default()
+    })
+}
+
+/// Returns every adapter available for the configured backends, in the
+/// order used by `GpuOptions::adapter_index`.
+pub fn list_adapters(options: &GpuOptions) -> Vec<wgpu::AdapterInfo> {
+    create_instance(options)
+        .enumerate_adapters(options.backends)
+        .iter()
+        .map(|adapter| adapter.get_info())
+        .collect()
+}
+
+async fn select_adapter(instance: &wgpu::Instance, options: &GpuOptions) -> Result<wgpu::Adapter> {
+    if let Some(index) = options.adapter_index {
+        let mut adapters = instance.enumerate_adapters(options.backends);
+        if index < adapters.len() {
+            return Ok(adapters.swap_remove(index));
+        }
+        log::warn!(
+            "GPU index {} is out of range ({} adapters found), using the default adapter",
+            index,
+            adapters.len()
+        );
+    }
+
+    let request = |force_fallback_adapter| {
+        instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: options.power_preference,
+            compatible_surface: None, // No surface for headless rendering
+            force_fallback_adapter,
+        })
+    };
+
+    if let Some(adapter) = request(false).await {
+        return Ok(adapter);
+    }
+
+    // No hardware adapter (headless servers, minimal containers): try a software one
+    log::warn!("No GPU adapter found, trying a software fallback adapter");
+    request(true).await.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Failed to find a GPU or software fallback adapter.\n\
+             Install Vulkan/Metal/DirectX drivers, or a software renderer such as \
+             Mesa's llvmpipe/lavapipe for headless machines, then try \
+             `--backend gl` or `--backend vulkan`. Run with `--list-gpus` to see \
+             which adapters are visible."
+        )
+    })
+}
+
+/// Format of the color attachment that receives a copy of each pixel's depth.
+pub const DEPTH_COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
 
 pub struct Renderer {
     pub device: wgpu::Device,
@@ -6,27 +120,30 @@ pub struct Renderer {
     pub texture: wgpu::Texture,
     pub texture_view: wgpu::TextureView,
     pub depth_texture_view: wgpu::TextureView,
+    /// Color target the main pass mirrors depth into, so it can be read back
+    /// on backends without depth-to-buffer copies (GL).
+    depth_color_texture: wgpu::Texture,
+    pub depth_color_view: wgpu::TextureView,
     pub width: u32,
     pub height: u32,
+    /// Packs frames into RGB bytes before readback, with `ReadbackFormat::Rgb`.
+    packer: Option<RgbPacker>,
     _instance: wgpu::Instance,
-    _adapter: wgpu::Adapter,
+    adapter: wgpu::Adapter,
 }
 
 impl Renderer {
-    pub async fn new(width: u32, height: u32) -> Result<Self> {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
+    pub async fn new(width: u32, height: u32, options: &GpuOptions) -> Result<Self> {
+        let instance = create_instance(options);
+        let adapter = select_adapter(&instance, options).await?;
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None, // No surface for headless rendering
-                force_fallback_adapter: false,
-            })
-            .await
-            .ok_or_else(|| anyhow::anyhow!("Failed to find an appropriate adapter"))?;
+        let info = adapter.get_info();
+        log::info!(
+            "Selected adapter: {} ({:?}, {:?})",
+            info.name,
+            info.backend,
+            info.device_type
+        );
 
         let (device, queue) = adapter
             .request_device(
@@ -51,7 +168,10 @@ impl Renderer {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            // Bound as a texture when packing RGB readbacks
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
         });
 
@@ -75,28 +195,97 @@ impl Renderer {
 
         let depth_texture_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
+        let depth_color_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Color Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_COLOR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let depth_color_view =
+            depth_color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let packer = match options.readback {
+            ReadbackFormat::Rgba => None,
+            ReadbackFormat::Rgb => Some(RgbPacker::new(&device, &texture_view, width, height)),
+        };
+
         Ok(Self {
             device,
             queue,
             texture,
             texture_view,
             depth_texture_view,
+            depth_color_texture,
+            depth_color_view,
             width,
             height,
+            packer,
             _instance: instance,
-            _adapter: adapter,
+            adapter,
         })
     }
 
-    pub async fn read_pixels(&self) -> Result<Vec<u8>> {
-        let bytes_per_pixel = 4; // RGBA8
-        let unpadded_bytes_per_row = self.width * bytes_per_pixel;
-        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
-        let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
 
-        let buffer_size = (padded_bytes_per_row * self.height) as wgpu::BufferAddress;
+    /// Starts copying the rendered frame's color, and its depth if
+    /// `with_depth`, back to the CPU without waiting for them, so the caller
+    /// can get on with the next frame.
+    pub fn start_readback(&self, with_depth: bool) -> PendingFrame {
+        let color = match &self.packer {
+            Some(packer) => {
+                packer.pack(&self.device, &self.queue);
+                self.start_read(&packer.texture, 4)
+            }
+            None => self.start_read(&self.texture, 4),
+        };
+        PendingFrame {
+            color,
+            depth: with_depth.then(|| self.start_read(&self.depth_color_texture, 4)),
+        }
+    }
+
+    /// Waits for a frame started with `start_readback`, returning its pixels
+    /// as RGBA8 and its depth, if it was read, as one `f32` per pixel in
+    /// `[0, 1]`, where 1 is the far plane (or nothing drawn).
+    pub fn finish_readback(&self, frame: PendingFrame) -> (Vec<u8>, Option<Vec<f32>>) {
+        let color = self.finish_read(frame.color);
+        let pixels = match &self.packer {
+            Some(packer) => unpack_rgb(&color, packer.texture.width() * 4, self.width),
+            None => color,
+        };
+        let depth = frame
+            .depth
+            .map(|depth| decode_depth(&self.finish_read(depth)));
+        (pixels, depth)
+    }
+
+    /// Reads back the rendered frame as RGBA8, whatever the readback format.
+    #[cfg(test)]
+    pub fn read_pixels(&self) -> Vec<u8> {
+        self.finish_readback(self.start_readback(false)).0
+    }
+
+    /// Queues a copy of a full texture into a buffer to be mapped once the
+    /// GPU gets to it.
+    fn start_read(&self, texture: &wgpu::Texture, bytes_per_pixel: u32) -> PendingRead {
+        let (width, height) = (texture.width(), texture.height());
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let padded_bytes_per_row = padded_bytes_per_row(unpadded_bytes_per_row);
+
+        let buffer_size = (padded_bytes_per_row * height) as wgpu::BufferAddress;
         let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Pixel Buffer"),
+            label: Some("Readback Buffer"),
             size: buffer_size,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
@@ -110,7 +299,7 @@ impl Renderer {
 
         encoder.copy_texture_to_buffer(
             wgpu::ImageCopyTexture {
-                texture: &self.texture,
+                texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
@@ -120,37 +309,312 @@ impl Renderer {
                 layout: wgpu::ImageDataLayout {
                     offset: 0,
                     bytes_per_row: Some(padded_bytes_per_row),
-                    rows_per_image: Some(self.height),
+                    rows_per_image: Some(height),
                 },
             },
             wgpu::Extent3d {
-                width: self.width,
-                height: self.height,
+                width,
+                height,
                 depth_or_array_layers: 1,
             },
         );
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        let submission = self.queue.submit(std::iter::once(encoder.finish()));
+        buffer.slice(..).map_async(wgpu::MapMode::Read, |_| {});
 
-        let buffer_slice = buffer.slice(..);
+        PendingRead {
+            buffer,
+            submission,
+            unpadded_bytes_per_row,
+            padded_bytes_per_row,
+        }
+    }
 
-        buffer_slice.map_async(wgpu::MapMode::Read, |_| {});
-        self.device.poll(wgpu::Maintain::Wait);
+    /// Waits for a copy queued by `start_read` and strips its row padding.
+    fn finish_read(&self, read: PendingRead) -> Vec<u8> {
+        self.device
+            .poll(wgpu::Maintain::WaitForSubmissionIndex(read.submission));
 
+        let buffer_slice = read.buffer.slice(..);
         let data = buffer_slice.get_mapped_range();
-        let mut result = Vec::with_capacity((unpadded_bytes_per_row * self.height) as usize);
+        let result = strip_row_padding(
+            &data,
+            read.unpadded_bytes_per_row,
+            read.padded_bytes_per_row,
+        );
+        drop(data);
+        read.buffer.unmap();
 
-        for row in 0..self.height {
-            let start = (row * padded_bytes_per_row) as usize;
-            let end = start + unpadded_bytes_per_row as usize;
-            if end <= data.len() {
-                result.extend_from_slice(&data[start..end]);
-            }
+        result
+    }
+}
+
+/// A texture copy on its way back from the GPU.
+struct PendingRead {
+    buffer: wgpu::Buffer,
+    submission: wgpu::SubmissionIndex,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+}
+
+/// A frame's color and depth on their way back from the GPU, see
+/// `Renderer::start_readback`.
+pub struct PendingFrame {
+    color: PendingRead,
+    depth: Option<PendingRead>,
+}
+
+/// Depth values from the bytes of an R32Float texture.
+fn decode_depth(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Render pass that packs a frame's color bytes without alpha into a texture
+/// three quarters as wide, see pack.wgsl.
+struct RgbPacker {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl RgbPacker {
+    fn new(device: &wgpu::Device, frame: &wgpu::TextureView, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Packed RGB Texture"),
+            size: wgpu::Extent3d {
+                width: (width * 3).div_ceil(4),
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Plain unorm, so the packed bytes aren't sRGB-encoded again
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Pack Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("pack.wgsl").into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Pack Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Pack Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(frame),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pack Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Pack Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_pack",
+                targets: &[Some(wgpu::TextureFormat::Rgba8Unorm.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            texture,
+            view,
+            pipeline,
+            bind_group,
         }
+    }
 
-        drop(data);
-        buffer.unmap();
+    /// Packs the frame as it is now.
+    fn pack(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Pack Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Pack Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// Turns rows of packed RGB bytes, `row_bytes` long each, back into `width`
+/// opaque RGBA pixels per row.
+fn unpack_rgb(packed: &[u8], row_bytes: u32, width: u32) -> Vec<u8> {
+    let width = width as usize;
+    let mut pixels = Vec::with_capacity(packed.len() / row_bytes as usize * width * 4);
+    for row in packed.chunks_exact(row_bytes as usize) {
+        for rgb in row[..width * 3].chunks_exact(3) {
+            pixels.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+        }
+    }
+    pixels
+}
 
-        Ok(result)
+/// Rounds a row of `bytes` up to the stride texture-to-buffer copies need.
+fn padded_bytes_per_row(bytes: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    bytes.div_ceil(align) * align
+}
+
+/// Drops the padding at the end of each `padded` byte row, keeping the first
+/// `unpadded` bytes of each.
+fn strip_row_padding(data: &[u8], unpadded: u32, padded: u32) -> Vec<u8> {
+    data.chunks(padded as usize)
+        .filter_map(|row| row.get(..unpadded as usize))
+        .flatten()
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_padding_from_unaligned_rows() {
+        // 37 RGBA pixels are 148 bytes, padded to 256
+        let (width, height) = (37, 3);
+        let padded = padded_bytes_per_row(width * 4);
+        assert_eq!(padded, 256);
+
+        let mut data = Vec::new();
+        for row in 0..height {
+            data.extend((0..width * 4).map(|i| (row * 7 + i) as u8));
+            data.resize(((row + 1) * padded) as usize, 0xAA);
+        }
+        let pixels = strip_row_padding(&data, width * 4, padded);
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        for row in 0..height {
+            let start = (row * width * 4) as usize;
+            assert_eq!(pixels[start], (row * 7) as u8);
+            assert_eq!(pixels[start + 147], (row * 7 + 147) as u8);
+        }
+    }
+
+    /// Fills the render texture with `color`.
+    fn clear(renderer: &Renderer, color: wgpu::Color) {
+        let mut encoder = renderer
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &renderer.texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    #[test]
+    fn reads_back_unaligned_width() {
+        let Ok(renderer) = pollster::block_on(Renderer::new(37, 5, &GpuOptions::default())) else {
+            eprintln!("no GPU adapter, skipping readback test");
+            return;
+        };
+        clear(&renderer, wgpu::Color::WHITE);
+
+        let pixels = renderer.read_pixels();
+        assert_eq!(pixels.len(), 37 * 5 * 4);
+        assert!(pixels.iter().all(|&b| b == 255));
+    }
+
+    #[test]
+    fn unpacks_rgb_rows() {
+        // Two 3-pixel rows packed into 12-byte rows, the last 3 bytes padding
+        let packed = [
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 0, 0, 10, 11, 12, 13, 14, 15, 16, 17, 18, 0, 0, 0,
+        ];
+        let pixels = unpack_rgb(&packed, 12, 3);
+        assert_eq!(pixels.len(), 2 * 3 * 4);
+        assert_eq!(pixels[..8], [1, 2, 3, 255, 4, 5, 6, 255]);
+        assert_eq!(pixels[20..], [16, 17, 18, 255]);
+    }
+
+    #[test]
+    fn rgb_readback_matches_rgba() {
+        let read = |readback| {
+            let options = GpuOptions {
+                readback,
+                ..GpuOptions::default()
+            };
+            let renderer = pollster::block_on(Renderer::new(37, 5, &options)).ok()?;
+            clear(
+                &renderer,
+                wgpu::Color {
+                    r: 0.1,
+                    g: 0.5,
+                    b: 0.9,
+                    a: 0.3,
+                },
+            );
+            Some(renderer.read_pixels())
+        };
+        let (Some(rgba), Some(rgb)) = (read(ReadbackFormat::Rgba), read(ReadbackFormat::Rgb))
+        else {
+            eprintln!("no GPU adapter, skipping readback test");
+            return;
+        };
+
+        assert_eq!(rgb.len(), rgba.len());
+        // Color bytes may differ by rounding in the shader's sRGB encoding
+        for (a, b) in rgba.chunks_exact(4).zip(rgb.chunks_exact(4)) {
+            assert!(
+                a[..3].iter().zip(&b[..3]).all(|(x, y)| x.abs_diff(*y) <= 1),
+                "{a:?} vs {b:?}"
+            );
+            assert_eq!(b[3], 255);
+        }
     }
 }
@@ -0,0 +1,119 @@
+use crate::geometry::Vertex;
+use glam::{IVec3, Vec3};
+use wgpu::util::DeviceExt;
+
+/// Width of the frame drawn around each face of the targeted block, as a
+/// fraction of the block size. Around a pixel wide at arm's length in a
+/// terminal-sized frame.
+const FRAME_WIDTH: f32 = 0.08;
+
+/// Linear color of the frame.
+const FRAME_COLOR: Vec3 = Vec3::splat(0.02);
+
+/// Faces of a block as (normal, first edge, second edge), with the edges
+/// spanning the face from its minimum corner.
+const FACES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::NEG_X, Vec3::Y, Vec3::Z),
+    (Vec3::X, Vec3::Y, Vec3::Z),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z),
+    (Vec3::Y, Vec3::X, Vec3::Z),
+    (Vec3::NEG_Z, Vec3::X, Vec3::Y),
+    (Vec3::Z, Vec3::X, Vec3::Y),
+];
+
+/// Strips along the four edges of a unit face as (u from, u to, v from, v
+/// to), not overlapping at the corners.
+const STRIPS: [(f32, f32, f32, f32); 4] = [
+    (0.0, FRAME_WIDTH, 0.0, 1.0),
+    (1.0 - FRAME_WIDTH, 1.0, 0.0, 1.0),
+    (FRAME_WIDTH, 1.0 - FRAME_WIDTH, 0.0, FRAME_WIDTH),
+    (FRAME_WIDTH, 1.0 - FRAME_WIDTH, 1.0 - FRAME_WIDTH, 1.0),
+];
+
+const QUAD_COUNT: usize = FACES.len() * STRIPS.len();
+
+/// GPU buffers for the frame outlining the block under the crosshair. The
+/// frame lies flat on the block's faces and relies on the overlay pipeline's
+/// depth bias to win against them.
+pub struct SelectionBox {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub index_count: u32,
+    /// Whether a block is targeted and the frame should be drawn.
+    pub visible: bool,
+}
+
+impl SelectionBox {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Selection Vertex Buffer"),
+            size: (std::mem::size_of::<Vertex>() * QUAD_COUNT * 4) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let indices: Vec<u16> = (0..QUAD_COUNT as u16)
+            .flat_map(|quad| [0, 1, 2, 0, 2, 3].map(|i| quad * 4 + i))
+            .collect();
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Selection Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            visible: false,
+        }
+    }
+
+    /// Moves the frame to `block`, or hides it when nothing is targeted.
+    pub fn update(&mut self, queue: &wgpu::Queue, block: Option<IVec3>, block_size: f32) {
+        self.visible = block.is_some();
+        if let Some(block) = block {
+            let vertices = frame_vertices(block.as_vec3() * block_size, block_size);
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+    }
+}
+
+/// Quads framing each face of the block with its minimum corner at `min`,
+/// four vertices per quad.
+fn frame_vertices(min: Vec3, size: f32) -> Vec<Vertex> {
+    let mut vertices = Vec::with_capacity(QUAD_COUNT * 4);
+    for (normal, u, v) in FACES {
+        // Faces pointing along an axis sit on the block's far side
+        let origin = min + normal.max(Vec3::ZERO) * size;
+        for (u0, u1, v0, v1) in STRIPS {
+            for (du, dv) in [(u0, v0), (u1, v0), (u1, v1), (u0, v1)] {
+                let position = origin + (u * du + v * dv) * size;
+                let mut vertex = Vertex::new(position, FRAME_COLOR, [0.0; 2]);
+                vertex.normal = normal.to_array();
+                vertex.smooth_normal = vertex.normal;
+                vertices.push(vertex);
+            }
+        }
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_lies_on_the_block_surface() {
+        let (min, size) = (Vec3::new(2.0, 4.0, -6.0), 2.0);
+        let vertices = frame_vertices(min, size);
+        assert_eq!(vertices.len(), QUAD_COUNT * 4);
+        for vertex in &vertices {
+            let local = (Vec3::from(vertex.position) - min) / size;
+            assert!(local.cmpge(Vec3::ZERO).all() && local.cmple(Vec3::ONE).all());
+            // On the face its normal points out of
+            let normal = Vec3::from(vertex.normal);
+            let depth = local.dot(normal.abs());
+            assert_eq!(depth, if normal.max_element() > 0.0 { 1.0 } else { 0.0 });
+        }
+    }
+}
@@ -2,8 +2,30 @@
 
 struct Uniforms {
     view_proj: mat4x4<f32>,
+    // xyz: min corner of the block being broken, w: block size (0 when none)
+    target_block: vec4<f32>,
+    break_progress: f32,
+    // 1 when meshes carry baked brightness in their vertex colors
+    baked_lighting: f32,
+    // How much brighter the block under the crosshair is drawn
+    hover_brightness: f32,
+    // xyz: direction towards the sun, w: 1 for smooth shading, 0 for flat
+    sun: vec4<f32>,
+    // xyz: camera position
+    eye: vec4<f32>,
+    // x: distance where fog starts, y: where it's complete, z: 1 when enabled
+    fog: vec4<f32>,
+    // xyz: min corner of the block under the crosshair, w: block size (0 when
+    // none or the highlight is off)
+    hover_block: vec4<f32>,
 }
 
+// Light reaching faces turned away from the sun
+const AMBIENT: f32 = 0.55;
+
+// Fog fades into the sky's clear color
+const FOG_COLOR: vec3<f32> = vec3<f32>(0.4, 0.7, 1.0);
+
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
@@ -13,16 +35,24 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(2)
 var s_diffuse: sampler;
 
+// Brightness detail laid out like the atlas; white when disabled
+@group(0) @binding(3)
+var t_detail: texture_2d<f32>;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
+    @location(3) normal: vec3<f32>,
+    @location(4) smooth_normal: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
     @location(1) tex_coord: vec2<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) normal: vec3<f32>,
 }
 
 @vertex
@@ -32,13 +62,109 @@ fn vs_main(
     var out: VertexOutput;
     out.color = model.color;
     out.tex_coord = model.tex_coord;
+    out.world_position = model.position;
+    out.normal = select(model.normal, model.smooth_normal, uniforms.sun.w > 0.5);
     out.clip_position = uniforms.view_proj * vec4<f32>(model.position, 1.0);
     return out;
 }
 
 // Fragment shader
 
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    // Copy of the depth value, readable on backends that can't copy depth textures
+    @location(1) depth: f32,
+}
+
+// Brightness of a surface facing `normal`
+fn sunlight(normal: vec3<f32>) -> f32 {
+    let diffuse = max(dot(normalize(normal), uniforms.sun.xyz), 0.0);
+    return AMBIENT + (1.0 - AMBIENT) * diffuse;
+}
+
+// Blends `color` towards the sky the farther `position` is from the camera
+fn apply_fog(color: vec3<f32>, position: vec3<f32>) -> vec3<f32> {
+    if (uniforms.fog.z < 0.5) {
+        return color;
+    }
+    let distance = length(position - uniforms.eye.xyz);
+    let amount = smoothstep(uniforms.fog.x, uniforms.fog.y, distance);
+    return mix(color, FOG_COLOR, amount);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    return block_color(in);
+}
+
+// Decorations: texels below half coverage are left out entirely, so plants
+// keep their depth and need no sorting
+@fragment
+fn fs_cutout(in: VertexOutput) -> FragmentOutput {
+    let out = block_color(in);
+    if (out.color.a < 0.5) {
+        discard;
+    }
+    return out;
+}
+
+// Whether `position` lies on the block with min corner `block.xyz` and size
+// `block.w`; never when the size is 0
+fn on_block(position: vec3<f32>, block: vec4<f32>) -> bool {
+    if (block.w <= 0.0) {
+        return false;
+    }
+    let local = (position - block.xyz) / block.w;
+    let eps = 0.001;
+    return all(local >= vec3<f32>(-eps)) && all(local <= vec3<f32>(1.0 + eps));
+}
+
+// Textured, lit and fogged color of a block fragment
+fn block_color(in: VertexOutput) -> FragmentOutput {
+    var color = textureSample(t_diffuse, s_diffuse, in.tex_coord);
+    let detail = textureSample(t_detail, s_diffuse, in.tex_coord).r;
+    // Baked meshes hold flat brightness in red and smooth in green
+    var light: f32;
+    if (uniforms.baked_lighting > 0.5) {
+        light = select(in.color.r, in.color.g, uniforms.sun.w > 0.5);
+    } else {
+        light = sunlight(in.normal);
+    }
+    // Blue scales brightness per chunk, for the seam-debugging checkerboard
+    color = vec4<f32>(color.rgb * detail * light * in.color.b, color.a);
+
+    // Brighten the block under the crosshair
+    if (on_block(in.world_position, uniforms.hover_block)) {
+        color = vec4<f32>(min(color.rgb * (1.0 + uniforms.hover_brightness), vec3<f32>(1.0)), color.a);
+    }
+
+    // Darken the block being broken as progress accumulates
+    if (on_block(in.world_position, uniforms.target_block)) {
+        // Squared so the falloff reads evenly once converted to sRGB
+        let shade = 1.0 - 0.9 * uniforms.break_progress;
+        color = vec4<f32>(color.rgb * shade * shade, color.a);
+    }
+
+    var out: FragmentOutput;
+    out.color = vec4<f32>(apply_fog(color.rgb, in.world_position), color.a);
+    out.depth = in.clip_position.z;
+    return out;
+}
+
+// The horizon plane is untextured and drawn in its vertex color
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coord);
-}
\ No newline at end of file
+fn fs_horizon(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.color = vec4<f32>(apply_fog(in.color * sunlight(in.normal), in.world_position), 1.0);
+    out.depth = in.clip_position.z;
+    return out;
+}
+
+// Overlays are untextured and unlit, drawn in their vertex color
+@fragment
+fn fs_overlay(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.color = vec4<f32>(apply_fog(in.color, in.world_position), 1.0);
+    out.depth = in.clip_position.z;
+    return out;
+}
@@ -0,0 +1,151 @@
+use anyhow::{bail, Result};
+use glam::{Mat4, Vec3};
+use std::str::FromStr;
+
+/// Share of the visible distance where automatic fog begins.
+const AUTO_FOG_START: f32 = 0.6;
+
+/// Share of the visible distance where automatic fog is complete, just short
+/// of where terrain stops so its edge is never seen.
+const AUTO_FOG_END: f32 = 0.95;
+
+/// The sun drawn into the sky. Its direction is also the one scene lighting
+/// should come from.
+#[derive(Debug, Clone, Copy)]
+pub struct Sun {
+    /// Unit vector pointing from the world towards the sun.
+    pub direction: Vec3,
+    /// Apparent diameter of the disk in degrees.
+    pub size_degrees: f32,
+    pub color: [u8; 3],
+}
+
+impl Default for Sun {
+    fn default() -> Self {
+        // Low in the sky ahead of the starting camera, so it's in view at launch
+        let elevation = 20f32.to_radians();
+        let azimuth = 15f32.to_radians();
+        Self {
+            direction: Vec3::new(
+                elevation.cos() * azimuth.cos(),
+                elevation.sin(),
+                elevation.cos() * azimuth.sin(),
+            ),
+            size_degrees: 8.0,
+            color: [255, 244, 200],
+        }
+    }
+}
+
+/// Distance fog fading terrain into the sky color.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Fog {
+    /// Follows how far terrain can be seen, so the edge of the far plane or
+    /// the render distance is always hidden.
+    #[default]
+    Auto,
+    /// Fixed distances from the camera in world units.
+    Range {
+        start: f32,
+        end: f32,
+    },
+    Off,
+}
+
+impl Fog {
+    /// Start and end distances when terrain is visible up to `visible` world
+    /// units away, or `None` without fog.
+    pub fn range(self, visible: f32) -> Option<(f32, f32)> {
+        match self {
+            Fog::Auto => Some((visible * AUTO_FOG_START, visible * AUTO_FOG_END)),
+            Fog::Range { start, end } => Some((start, end)),
+            Fog::Off => None,
+        }
+    }
+}
+
+impl FromStr for Fog {
+    type Err = anyhow::Error;
+
+    /// Parses `auto`, `off` or `START,END`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => return Ok(Fog::Auto),
+            "off" => return Ok(Fog::Off),
+            _ => {}
+        }
+        let values: Vec<f32> = s
+            .split(',')
+            .map(|v| v.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| anyhow::anyhow!("invalid fog '{s}' (expected auto, off or start,end)"))?;
+        match values[..] {
+            [start, end] if 0.0 <= start && start < end => Ok(Fog::Range { start, end }),
+            _ => bail!("invalid fog '{s}' (expected 0 <= start < end)"),
+        }
+    }
+}
+
+/// Parses an `RRGGBB` hex color, with or without a leading `#`.
+pub fn parse_color(text: &str) -> Result<[u8; 3]> {
+    let hex = text.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("invalid color '{text}' (expected RRGGBB)");
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+/// Paints the sun disk and a soft glow around it onto pixels where nothing
+/// was drawn (depth left at the far plane). `inverse_view_proj` maps clip
+/// space back to world space to recover each pixel's view ray.
+pub fn draw_sun(
+    pixels: &mut [u8],
+    depth: &[f32],
+    width: u32,
+    height: u32,
+    inverse_view_proj: Mat4,
+    eye: Vec3,
+    sun: &Sun,
+) {
+    if sun.size_degrees <= 0.0 {
+        return;
+    }
+    let radius = (sun.size_degrees * 0.5).to_radians();
+    // The glow fades out over two disk radii past the edge
+    let glow = radius * 2.0;
+    let direction = sun.direction.normalize_or_zero();
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            if !depth.get(i).is_some_and(|&d| d >= 1.0) {
+                continue;
+            }
+
+            let ndc_x = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+            let ndc_y = 1.0 - (y as f32 + 0.5) / height as f32 * 2.0;
+            let far = inverse_view_proj.project_point3(Vec3::new(ndc_x, ndc_y, 1.0));
+            let ray = (far - eye).normalize_or_zero();
+            let angle = ray.dot(direction).clamp(-1.0, 1.0).acos();
+
+            let strength = if angle <= radius {
+                1.0
+            } else {
+                let t = ((angle - radius) / glow).min(1.0);
+                0.6 * (1.0 - t) * (1.0 - t)
+            };
+            if strength <= 0.0 {
+                continue;
+            }
+
+            let Some(rgb) = pixels.get_mut(i * 4..i * 4 + 3) else {
+                continue;
+            };
+            for (channel, &sun_channel) in rgb.iter_mut().zip(&sun.color) {
+                let value = *channel as f32 + (sun_channel as f32 - *channel as f32) * strength;
+                *channel = value.round() as u8;
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::input::InputProfile;
+use crate::present::{draw_text, Viewport};
+use crate::world_gen::BlockType;
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode};
+use crossterm::{cursor, execute, terminal};
+use std::io::{stdout, Write};
+
+const TITLE_COLOR: [u8; 3] = [120, 200, 80];
+const TEXT_COLOR: [u8; 3] = [255, 255, 255];
+const BACKGROUND: [u8; 3] = [20, 20, 20];
+
+/// Keys every profile handles outside the movement bindings, listed around
+/// the hotbar keys.
+const EDIT_CONTROLS: [(&str, &str); 2] =
+    [("F", "Toggle walking"), ("B / P", "Break / place a block")];
+const MENU_CONTROLS: [(&str, &str); 2] = [("Tab", "Switch key profile"), ("X / Esc", "Quit")];
+
+/// Lines of the title screen: the name, the seed, and the controls with the
+/// movement keys of `profile`.
+pub fn lines(seed: u64, profile: InputProfile) -> Vec<String> {
+    // Keys doing the same thing share a line, e.g. "W / Up"
+    let mut controls: Vec<(String, &str)> = Vec::new();
+    for (code, action) in profile.bindings() {
+        let key = key_name(code);
+        match controls
+            .iter_mut()
+            .find(|(_, what)| *what == action.describe())
+        {
+            Some((keys, _)) => *keys = format!("{keys} / {key}"),
+            None => controls.push((key, action.describe())),
+        }
+    }
+    if profile.mouse_action(0, 0).is_some() {
+        controls.push(("Mouse".to_string(), "Look around"));
+    }
+    controls.extend(EDIT_CONTROLS.map(|(keys, what)| (keys.to_string(), what)));
+    let hotbar_keys = format!("1-{}", BlockType::ALL.len());
+    controls.push((hotbar_keys, "Select the block to place"));
+    controls.extend(MENU_CONTROLS.map(|(keys, what)| (keys.to_string(), what)));
+
+    let key_width = controls
+        .iter()
+        .map(|(keys, _)| keys.len())
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![
+        "minecraftty".to_string(),
+        String::new(),
+        format!("Seed {seed}, {} keys", profile.name()),
+        String::new(),
+    ];
+    lines.extend(
+        controls
+            .into_iter()
+            .map(|(keys, what)| format!("{keys:key_width$}  {what}")),
+    );
+    lines
+}
+
+fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_uppercase().to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Title screen shown in the alternate screen while the world generates,
+/// until a key is pressed.
+pub struct Splash {
+    lines: Vec<String>,
+    viewport: Viewport,
+    dismissed: bool,
+}
+
+impl Splash {
+    /// Switches to the alternate screen and draws the title with `status`
+    /// under it.
+    pub fn show(
+        seed: u64,
+        profile: InputProfile,
+        viewport: Viewport,
+        status: &str,
+    ) -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        let splash = Self {
+            lines: lines(seed, profile),
+            viewport,
+            dismissed: false,
+        };
+        splash.draw(status)?;
+        Ok(splash)
+    }
+
+    /// Redraws the title screen with a new status line, also covering
+    /// anything written to the terminal in the meantime.
+    pub fn draw(&self, status: &str) -> Result<()> {
+        let viewport = match terminal::size() {
+            Ok((cols, rows)) => self.viewport.clip(cols as u32, rows as u32),
+            Err(_) => self.viewport,
+        };
+        let [r, g, b] = BACKGROUND;
+        let mut out = Vec::new();
+        write!(out, "\x1b[48;2;{r};{g};{b}m\x1b[2J")?;
+
+        // Centered as a block, with the status line after a gap
+        let width = self
+            .lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as u32;
+        let height = self.lines.len() as u32 + 2;
+        let col = viewport.col + viewport.cols.saturating_sub(width) / 2;
+        let top = viewport.row + viewport.rows.saturating_sub(height) / 2;
+        let shown = self.lines.iter().map(String::as_str).chain(["", status]);
+        for (row, line) in (0..viewport.rows.min(height)).zip(shown) {
+            let color = if row == 0 { TITLE_COLOR } else { TEXT_COLOR };
+            let text: String = line.chars().take(viewport.cols as usize).collect();
+            draw_text(&mut out, top + row, col, &text, color, BACKGROUND)?;
+        }
+        write!(out, "\x1b[0m")?;
+
+        let mut stdout = stdout().lock();
+        stdout.write_all(&out)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Waits for any key. The terminal stays in the alternate screen and raw
+    /// mode, for the game loop to take over.
+    pub fn wait_for_key(mut self) -> Result<()> {
+        loop {
+            if let Event::Key(_) = event::read()? {
+                self.dismissed = true;
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Drop for Splash {
+    /// Gives the terminal back when starting failed before the game loop.
+    fn drop(&mut self) {
+        if !self.dismissed {
+            let _ = execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_list_the_active_profile_keys() {
+        let game = lines(42, InputProfile::Game);
+        assert_eq!(game[0], "minecraftty");
+        assert!(game[2].contains("Seed 42"));
+        // Keys sharing an action share a line
+        assert!(game
+            .iter()
+            .any(|line| line.starts_with("W / Up ") && line.ends_with("Move forward")));
+        assert!(game.iter().any(|line| line.ends_with("Look left")));
+        assert!(game.iter().any(|line| line.starts_with("Mouse ")));
+        assert!(game
+            .iter()
+            .any(|line| line.starts_with("Space") && line.contains("Jump")));
+        let hotbar = format!("1-{} ", BlockType::ALL.len());
+        assert!(game.iter().any(|line| line.starts_with(&hotbar)));
+
+        let inspection = lines(42, InputProfile::Inspection);
+        assert!(inspection.iter().any(|line| line.ends_with("Orbit left")));
+        assert!(inspection.iter().any(|line| line.starts_with("Up / E ")));
+        assert!(!inspection.iter().any(|line| line.ends_with("Look left")));
+        assert!(!inspection.iter().any(|line| line.starts_with("Mouse ")));
+    }
+}
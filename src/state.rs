@@ -0,0 +1,125 @@
+use crate::camera::FovAxis;
+use crate::config::Config;
+use crate::present::Glyph;
+use crate::world::BlockEdit;
+use crate::world_gen::WorldGenConfig;
+use anyhow::{Context, Result};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Runtime toggles to start with, normally all off. Restored along with the
+/// rest of a saved state.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ViewToggles {
+    pub walking: bool,
+    pub smooth_shading: bool,
+    pub outlines: bool,
+    #[serde(default)]
+    pub hover_highlight: bool,
+}
+
+/// Everything needed to reproduce a view: camera pose, world generation, the
+/// blocks edited by hand and the render settings that change what's on
+/// screen. Saved with Shift+S or when quitting, and restored with
+/// `--load-state`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    pub position: Vec3,
+    /// Heading, pitch and roll in degrees, as taken by `--yaw`, `--pitch`
+    /// and `--roll`.
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Missing from states saved before roll existed.
+    #[serde(default)]
+    pub roll: f32,
+    pub fov_degrees: f32,
+    pub fov_axis: FovAxis,
+    pub render_distance: Option<u32>,
+    pub glyph: Glyph,
+    pub view: ViewToggles,
+    pub world: WorldGenConfig,
+    /// Missing from states saved before edits were kept.
+    #[serde(default)]
+    pub edits: Vec<BlockEdit>,
+}
+
+impl State {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .with_context(|| format!("failed to write state to {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read state from {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("invalid state file {}", path.display()))
+    }
+
+    /// Overrides the matching startup settings of `config`.
+    pub fn apply(self, config: &mut Config) {
+        config.position = self.position;
+        config.yaw = self.yaw;
+        config.pitch = self.pitch;
+        config.roll = self.roll;
+        config.fov_degrees = self.fov_degrees;
+        config.fov_axis = self.fov_axis;
+        config.render_distance = self.render_distance;
+        config.glyph = self.glyph;
+        config.view = self.view;
+        config.world = self.world;
+        config.edits = self.edits;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_gen::{Axis, BakedLighting, Block, BlockType, FaceBrightness};
+    use glam::IVec3;
+
+    #[test]
+    fn state_round_trips_through_json() {
+        let state = State {
+            position: Vec3::new(1.5, 20.0, -3.0),
+            yaw: 135.0,
+            pitch: -30.0,
+            roll: 15.0,
+            fov_degrees: 90.0,
+            fov_axis: FovAxis::Horizontal,
+            render_distance: Some(4),
+            glyph: Glyph::Full,
+            view: ViewToggles {
+                walking: true,
+                ..ViewToggles::default()
+            },
+            world: WorldGenConfig {
+                seed: 42,
+                biome_size: Some(32.0),
+                baked_lighting: Some(BakedLighting::Faces(FaceBrightness::default())),
+                ..WorldGenConfig::default()
+            },
+            edits: vec![
+                BlockEdit {
+                    position: IVec3::new(-3, 12, 40),
+                    block: None,
+                },
+                BlockEdit {
+                    position: IVec3::new(5, 20, -7),
+                    block: Some(Block {
+                        block_type: BlockType::Log,
+                        axis: Axis::X,
+                    }),
+                },
+            ],
+        };
+        let path =
+            std::env::temp_dir().join(format!("minecraftty-state-{}.json", std::process::id()));
+        state.save(&path).unwrap();
+        let loaded = State::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), state);
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender};
+
+/// Mirrors each frame's terminal output to a Unix socket, named pipe or file
+/// so another process can display it. Writing happens on a background
+/// thread; frames arriving while it's busy or waiting for a receiver are
+/// dropped, so a slow or missing viewer never stalls rendering.
+pub struct FrameStream {
+    frames: SyncSender<Vec<u8>>,
+}
+
+impl FrameStream {
+    pub fn spawn(path: PathBuf) -> Self {
+        let (frames, received) = mpsc::sync_channel::<Vec<u8>>(1);
+        // The thread is left detached: opening a named pipe blocks until a
+        // reader shows up, which may be never
+        std::thread::Builder::new()
+            .name("frame-stream".into())
+            .spawn(move || {
+                let mut target = None;
+                for frame in received {
+                    if target.is_none() {
+                        target = connect(&path)
+                            .map_err(|e| log::debug!("stream {}: {e}", path.display()))
+                            .ok();
+                    }
+                    // A receiver that went away is dropped and reconnected to
+                    // on a later frame
+                    if let Some(out) = &mut target {
+                        if let Err(e) = out.write_all(&frame).and_then(|_| out.flush()) {
+                            log::info!("stream receiver disconnected: {e}");
+                            target = None;
+                        }
+                    }
+                }
+            })
+            .expect("failed to spawn frame stream thread");
+
+        Self { frames }
+    }
+
+    /// Queues a frame for the receiver unless the previous one is still
+    /// being sent.
+    pub fn send(&self, frame: &[u8]) {
+        let _ = self.frames.try_send(frame.to_vec());
+    }
+}
+
+fn connect(path: &Path) -> std::io::Result<Box<dyn Write>> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if std::fs::metadata(path)?.file_type().is_socket() {
+            return Ok(Box::new(std::os::unix::net::UnixStream::connect(path)?));
+        }
+    }
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    Ok(Box::new(file))
+}
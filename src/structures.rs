@@ -0,0 +1,274 @@
+use crate::rng::{coord_rng, Rng};
+use crate::world_gen::{
+    seed_from_str, Block, BlockType, Chunk, Surface, WorldGenConfig, CHUNK_SIZE_X, CHUNK_SIZE_Z,
+    WORLD_HEIGHT,
+};
+
+/// Blocks between a tree's trunk and the edge of its canopy.
+const CANOPY_RADIUS: usize = 2;
+
+/// How far outside its own column a structure may reach, so chunks know how
+/// many columns around them to consider.
+pub const STRUCTURE_REACH: usize = CANOPY_RADIUS;
+
+/// Places structures such as trees into a freshly generated chunk, before
+/// it's meshed. Chunks are generated independently, so a structure crossing
+/// a chunk edge is built by each chunk it reaches into, each placing only
+/// its own share: its choices have to depend on the world position and
+/// `surface`, never on which chunk is asking.
+pub trait StructureGenerator {
+    /// Identifies the generator; also salts its random numbers, so adding a
+    /// generator doesn't change what the others place.
+    fn name(&self) -> &'static str;
+
+    /// Adds structures to `chunk`, drawing every random choice from
+    /// `coord_rng` streams of `seed` so the same seed always gets the same
+    /// result. `surface` covers `STRUCTURE_REACH` columns past the chunk.
+    fn generate(&self, chunk: &mut Chunk, surface: &Surface, seed: u64);
+}
+
+/// The generators `config` enables, in the order they run.
+pub fn structure_generators(config: &WorldGenConfig) -> Vec<Box<dyn StructureGenerator>> {
+    let mut generators: Vec<Box<dyn StructureGenerator>> = Vec::new();
+    if let Some(density) = config.tree_density {
+        generators.push(Box::new(TreeGenerator { density }));
+    }
+    // After the trees, so plants don't end up under a trunk
+    if let Some(density) = config.decoration_density {
+        generators.push(Box::new(DecorationGenerator {
+            density,
+            flower_share: config.flower_share,
+        }));
+    }
+    generators
+}
+
+/// Runs every enabled generator over `chunk`.
+pub fn place_structures(chunk: &mut Chunk, surface: &Surface, config: &WorldGenConfig) {
+    for generator in structure_generators(config) {
+        let seed = config.seed ^ seed_from_str(generator.name());
+        generator.generate(chunk, surface, seed);
+    }
+}
+
+/// Grows trees with log trunks and leaf canopies on grass.
+pub struct TreeGenerator {
+    /// Chance that a grass column grows a tree, 0 to 1.
+    pub density: f32,
+}
+
+impl StructureGenerator for TreeGenerator {
+    fn name(&self) -> &'static str {
+        "trees"
+    }
+
+    fn generate(&self, chunk: &mut Chunk, surface: &Surface, seed: u64) {
+        // Trunks just outside the chunk can still spread leaves into it
+        let reach = CANOPY_RADIUS as i32;
+        let origin = chunk.origin();
+        for x in -reach..CHUNK_SIZE_X as i32 + reach {
+            for z in -reach..CHUNK_SIZE_Z as i32 + reach {
+                // Each column draws from its own stream, so neighbouring
+                // chunks agree on the trees along their shared edge
+                let mut rng = coord_rng(seed, origin.x + x, 0, origin.z + z);
+                let grows = rng.next_f32() < self.density;
+                let height = 4 + rng.below(3) as usize;
+                let Some((ground, BlockType::Grass)) = surface.top(x, z) else {
+                    continue;
+                };
+                let top = ground + height;
+                if grows && top + 1 < WORLD_HEIGHT {
+                    grow_tree(chunk, x, z, ground + 1, top, &mut rng);
+                }
+            }
+        }
+    }
+}
+
+/// A trunk from `bottom` up to `top` with a canopy around its upper end: two
+/// wide layers with some corners left off and a narrow one on top. `x` and
+/// `z` are chunk-local and may lie outside the chunk, which only gets the
+/// blocks inside it.
+fn grow_tree(chunk: &mut Chunk, x: i32, z: i32, bottom: usize, top: usize, rng: &mut Rng) {
+    for y in top - 1..=top + 1 {
+        let radius = if y <= top { CANOPY_RADIUS as i32 } else { 1 };
+        for dx in -radius..=radius {
+            for dz in -radius..=radius {
+                let corner = dx.abs() == radius && dz.abs() == radius;
+                if corner && (y > top || rng.below(2) == 0) {
+                    continue;
+                }
+                place(chunk, x + dx, y, z + dz, BlockType::Leaves, false);
+            }
+        }
+    }
+    for y in bottom..=top {
+        place(chunk, x, y, z, BlockType::Log, true);
+    }
+}
+
+/// Puts a block at chunk-local `x`, `y`, `z` if that's inside the chunk,
+/// over whatever is there with `replace` and only into air otherwise.
+fn place(chunk: &mut Chunk, x: i32, y: usize, z: i32, block_type: BlockType, replace: bool) {
+    let inside = (0..CHUNK_SIZE_X as i32).contains(&x) && (0..CHUNK_SIZE_Z as i32).contains(&z);
+    if !inside {
+        return;
+    }
+    let (x, z) = (x as usize, z as usize);
+    if replace || chunk.get(x, y, z).is_none() {
+        chunk.set(x, y, z, Some(Block::new(block_type)));
+    }
+}
+
+/// Scatters tall grass and flowers over open grass tops.
+pub struct DecorationGenerator {
+    /// Chance that an open grass top gets a plant, 0 to 1.
+    pub density: f32,
+    /// Share of the plants that are flowers, 0 to 1, split evenly between
+    /// poppies and dandelions.
+    pub flower_share: f32,
+}
+
+impl StructureGenerator for DecorationGenerator {
+    fn name(&self) -> &'static str {
+        "decorations"
+    }
+
+    fn generate(&self, chunk: &mut Chunk, _surface: &Surface, seed: u64) {
+        let mut rng = coord_rng(seed, chunk.position.x, 0, chunk.position.y);
+        for x in 0..CHUNK_SIZE_X {
+            for z in 0..CHUNK_SIZE_Z {
+                // Drawn for every column so each one's chance is independent
+                let grows = rng.next_f32() < self.density;
+                let kind = rng.next_f32();
+                let Some(ground) = (0..WORLD_HEIGHT - 1)
+                    .rev()
+                    .find(|&y| chunk.get(x, y, z).is_some())
+                else {
+                    continue;
+                };
+                let on_grass = chunk
+                    .get(x, ground, z)
+                    .is_some_and(|block| block.block_type == BlockType::Grass);
+                if !grows || !on_grass || chunk.get(x, ground + 1, z).is_some() {
+                    continue;
+                }
+                let plant = if kind >= self.flower_share {
+                    BlockType::TallGrass
+                } else if kind < self.flower_share / 2.0 {
+                    BlockType::Poppy
+                } else {
+                    BlockType::Dandelion
+                };
+                chunk.set(x, ground + 1, z, Some(Block::new(plant)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_gen::generate_chunk;
+    use glam::IVec2;
+
+    #[test]
+    fn trees_grow_on_grass_and_are_deterministic() {
+        let config = WorldGenConfig {
+            seed: 7,
+            tree_density: Some(0.3),
+            ..WorldGenConfig::default()
+        };
+        let chunk = generate_chunk(IVec2::new(2, -1), &config);
+        let again = generate_chunk(IVec2::new(2, -1), &config);
+        assert!(chunk.blocks().eq(again.blocks()));
+
+        let logs: Vec<_> = chunk
+            .blocks()
+            .filter(|(_, block)| block.block_type == BlockType::Log)
+            .collect();
+        assert!(!logs.is_empty());
+        let origin = chunk.origin();
+        for (position, _) in logs {
+            // Every trunk stands on grass or on more trunk
+            let local = position - origin;
+            let below = chunk.get(local.x as usize, local.y as usize - 1, local.z as usize);
+            let below = below.map(|block| block.block_type);
+            assert!(matches!(below, Some(BlockType::Grass | BlockType::Log)));
+        }
+
+        let bare = WorldGenConfig {
+            tree_density: None,
+            ..config
+        };
+        let bare = generate_chunk(IVec2::new(2, -1), &bare);
+        assert!(bare
+            .blocks()
+            .all(|(_, block)| block.block_type != BlockType::Leaves));
+    }
+
+    #[test]
+    fn trees_near_an_edge_spread_into_the_next_chunk() {
+        let config = WorldGenConfig {
+            seed: 7,
+            // Grass on every column, so trees are everywhere
+            soil_depth: Some(3),
+            tree_density: Some(0.3),
+            ..WorldGenConfig::default()
+        };
+        let (west, east) = (IVec2::new(2, -1), IVec2::new(3, -1));
+        let west = generate_chunk(west, &config);
+        let east = generate_chunk(east, &config);
+        let edge = CHUNK_SIZE_X - 1;
+
+        // Trunks grow right up to the edge, and the leaves beside each
+        // trunk top carry on across it into the other chunk
+        let mut crossings = 0;
+        for z in 0..CHUNK_SIZE_Z {
+            for x in edge - 1..=edge {
+                let Some(top) = (0..WORLD_HEIGHT).rev().find(|&y| {
+                    west.get(x, y, z)
+                        .is_some_and(|block| block.block_type == BlockType::Log)
+                }) else {
+                    continue;
+                };
+                for across in 0..=x + CANOPY_RADIUS - CHUNK_SIZE_X {
+                    let block = east.get(across, top, z).map(|block| block.block_type);
+                    assert!(
+                        matches!(block, Some(BlockType::Leaves | BlockType::Log)),
+                        "trunk at {x}, {z}"
+                    );
+                    crossings += 1;
+                }
+            }
+        }
+        assert!(crossings > 0);
+    }
+
+    #[test]
+    fn plants_grow_on_open_grass() {
+        let config = WorldGenConfig {
+            seed: 3,
+            decoration_density: Some(0.5),
+            flower_share: 0.5,
+            ..WorldGenConfig::default()
+        };
+        let chunk = generate_chunk(IVec2::new(-4, 9), &config);
+        let plants: Vec<_> = chunk
+            .blocks()
+            .filter(|(_, block)| block.block_type.is_decoration())
+            .collect();
+        // Each kind shows up among the 64 columns' worth of chances
+        for kind in [BlockType::TallGrass, BlockType::Poppy, BlockType::Dandelion] {
+            assert!(plants.iter().any(|(_, block)| block.block_type == kind));
+        }
+        let origin = chunk.origin();
+        for (position, _) in plants {
+            let local = position - origin;
+            let (x, y, z) = (local.x as usize, local.y as usize, local.z as usize);
+            let below = chunk.get(x, y - 1, z).map(|block| block.block_type);
+            assert_eq!(below, Some(BlockType::Grass));
+            assert_eq!(chunk.get(x, y + 1, z), None);
+        }
+    }
+}
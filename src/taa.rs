@@ -0,0 +1,117 @@
+use crate::camera::Camera;
+use glam::{Vec2, Vec3};
+
+/// Camera motion, in pixels of image shift, at which the previous frame no
+/// longer contributes. Below it the blend fades in linearly.
+const MAX_SHIFT_PIXELS: f32 = 4.0;
+
+/// Rough image shift caused by moving one world unit, for turning camera
+/// movement into pixels. Near blocks shift more and far ones less.
+const PIXELS_PER_UNIT: f32 = 4.0;
+
+/// Temporal anti-aliasing: the projection is shifted by a different
+/// sub-pixel offset every frame and each frame is blended with the previous
+/// result, so edges settle on their average coverage instead of crawling.
+pub struct TemporalAa {
+    /// Weight of the previous frame while the camera is still, 0 to 1.
+    blend: f32,
+    frame: u32,
+    history: Option<History>,
+}
+
+struct History {
+    pixels: Vec<u8>,
+    position: Vec3,
+    forward: Vec3,
+}
+
+impl TemporalAa {
+    pub fn new(blend: f32) -> Self {
+        Self {
+            blend,
+            frame: 0,
+            history: None,
+        }
+    }
+
+    /// Projection offset in normalized device coordinates for the next frame
+    /// of a `width` x `height` render, following a Halton (2, 3) sequence.
+    pub fn next_jitter(&mut self, width: u32, height: u32) -> Vec2 {
+        // The sequence starts at 1, as index 0 is the origin for every base
+        self.frame = self.frame % 16 + 1;
+        let offset = Vec2::new(halton(self.frame, 2), halton(self.frame, 3)) - 0.5;
+        // NDC spans 2 units across the image
+        offset * 2.0 / Vec2::new(width as f32, height as f32)
+    }
+
+    /// Blends `pixels` with the previous frame, less the more the camera has
+    /// moved since, and keeps the result for the next frame.
+    pub fn resolve(&mut self, mut pixels: Vec<u8>, camera: &Camera, height: u32) -> Vec<u8> {
+        if let Some(history) = &self.history {
+            // Turning by the angle one pixel covers shifts the image a pixel
+            let pixel_angle = camera.fovy() / height as f32;
+            let shift = camera.forward.angle_between(history.forward) / pixel_angle
+                + camera.position.distance(history.position) * PIXELS_PER_UNIT;
+            let weight = self.blend * (1.0 - shift / MAX_SHIFT_PIXELS).max(0.0);
+            if weight > 0.0 && history.pixels.len() == pixels.len() {
+                blend(&mut pixels, &history.pixels, weight);
+            }
+        }
+        self.history = Some(History {
+            pixels: pixels.clone(),
+            position: camera.position,
+            forward: camera.forward,
+        });
+        pixels
+    }
+}
+
+/// Mixes `history` into `pixels` with the given weight.
+fn blend(pixels: &mut [u8], history: &[u8], weight: f32) {
+    for (pixel, old) in pixels.iter_mut().zip(history) {
+        *pixel = (*pixel as f32 * (1.0 - weight) + *old as f32 * weight).round() as u8;
+    }
+}
+
+/// Element `index` of the Halton low-discrepancy sequence in `base`, in 0..1.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_stays_within_half_a_pixel() {
+        assert_eq!(halton(1, 2), 0.5);
+        assert_eq!(halton(2, 3), 2.0 / 3.0);
+
+        let mut taa = TemporalAa::new(0.5);
+        let jitters: Vec<Vec2> = (0..32).map(|_| taa.next_jitter(100, 60)).collect();
+        for jitter in &jitters {
+            assert!(jitter.x.abs() <= 1.0 / 100.0 && jitter.y.abs() <= 1.0 / 60.0);
+        }
+        // Consecutive frames sample different offsets
+        assert!(jitters.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn history_fades_out_with_motion() {
+        let mut taa = TemporalAa::new(0.5);
+        let mut camera = Camera::new(1.0, Vec3::ZERO);
+        taa.resolve(vec![0; 4], &camera, 60);
+        assert_eq!(taa.resolve(vec![200; 4], &camera, 60), vec![100; 4]);
+
+        // Moving far enough between frames drops the history entirely
+        camera.position.x += 10.0;
+        assert_eq!(taa.resolve(vec![50; 4], &camera, 60), vec![50; 4]);
+    }
+}
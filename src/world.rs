@@ -0,0 +1,369 @@
+use crate::light::SkyLight;
+use crate::world_gen::{
+    generate_chunk, Biome, BiomeNoise, Block, Chunk, WorldGenConfig, CHUNK_SIZE_X, CHUNK_SIZE_Z,
+    WORLD_HEIGHT,
+};
+use glam::{IVec2, IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RaycastHit {
+    /// Block-space coordinates of the hit block.
+    pub block: IVec3,
+    /// Outward normal of the face the ray entered through. Zero when the ray
+    /// started inside the block.
+    pub normal: IVec3,
+}
+
+/// A block broken or placed by hand. Saved states keep these, since
+/// generating the world again from its seed doesn't bring them back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockEdit {
+    /// Block-space position.
+    pub position: IVec3,
+    /// The block there now, `None` where one was broken.
+    pub block: Option<Block>,
+}
+
+/// The set of loaded chunks and block-level access across chunk boundaries.
+pub struct World {
+    pub config: WorldGenConfig,
+    chunks: HashMap<IVec2, Chunk>,
+    /// Built once per seed for looking up biomes outside generation.
+    biomes: Option<BiomeNoise>,
+}
+
+impl World {
+    pub fn new(config: WorldGenConfig) -> Self {
+        let biomes = config
+            .biome_size
+            .map(|size| BiomeNoise::new(config.seed, size));
+        Self {
+            config,
+            chunks: HashMap::new(),
+            biomes,
+        }
+    }
+
+    /// Switches to another seed for chunks generated from now on.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.config.seed = seed;
+        self.biomes = self
+            .config
+            .biome_size
+            .map(|size| BiomeNoise::new(seed, size));
+    }
+
+    /// Biome of the column at block-space `x`, `z`, or `None` with biomes off.
+    pub fn biome(&self, x: i32, z: i32) -> Option<&'static Biome> {
+        Some(self.biomes.as_ref()?.biome(x, z))
+    }
+
+    /// Adds a generated chunk, replacing any loaded copy at its position.
+    pub fn insert_chunk(&mut self, chunk: Chunk) {
+        self.chunks.insert(chunk.position, chunk);
+    }
+
+    pub fn remove_chunk(&mut self, chunk_pos: IVec2) -> Option<Chunk> {
+        self.chunks.remove(&chunk_pos)
+    }
+
+    pub fn chunk(&self, chunk_pos: IVec2) -> Option<&Chunk> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    /// Returns the chunk containing the given block-space position.
+    pub fn chunk_pos_of(block: IVec3) -> IVec2 {
+        IVec2::new(
+            block.x.div_euclid(CHUNK_SIZE_X as i32),
+            block.z.div_euclid(CHUNK_SIZE_Z as i32),
+        )
+    }
+
+    /// Splits a block position into its chunk and the local coordinates inside it.
+    fn locate(block: IVec3) -> Option<(IVec2, usize, usize, usize)> {
+        if block.y < 0 || block.y >= WORLD_HEIGHT as i32 {
+            return None;
+        }
+        Some((
+            Self::chunk_pos_of(block),
+            block.x.rem_euclid(CHUNK_SIZE_X as i32) as usize,
+            block.y as usize,
+            block.z.rem_euclid(CHUNK_SIZE_Z as i32) as usize,
+        ))
+    }
+
+    pub fn get_block(&self, block: IVec3) -> Option<Block> {
+        let (chunk_pos, x, y, z) = Self::locate(block)?;
+        self.chunks.get(&chunk_pos)?.get(x, y, z)
+    }
+
+    /// Sets or clears a block. Returns the chunk that changed and needs
+    /// re-meshing, or `None` if the position isn't loaded.
+    pub fn set_block(&mut self, block: IVec3, value: Option<Block>) -> Option<IVec2> {
+        let (chunk_pos, x, y, z) = Self::locate(block)?;
+        self.chunks.get_mut(&chunk_pos)?.set(x, y, z, value);
+        Some(chunk_pos)
+    }
+
+    /// Blocks of a loaded chunk that differ from generating it afresh, or
+    /// nothing if it isn't loaded.
+    pub fn edits(&self, chunk_pos: IVec2) -> Vec<BlockEdit> {
+        let Some(chunk) = self.chunks.get(&chunk_pos) else {
+            return Vec::new();
+        };
+        let generated = generate_chunk(chunk_pos, &self.config);
+        let origin = chunk.origin();
+        let mut edits = Vec::new();
+        for x in 0..CHUNK_SIZE_X {
+            for z in 0..CHUNK_SIZE_Z {
+                for y in 0..WORLD_HEIGHT {
+                    let block = chunk.get(x, y, z);
+                    if block != generated.get(x, y, z) {
+                        let local = IVec3::new(x as i32, y as i32, z as i32);
+                        edits.push(BlockEdit {
+                            position: origin + local,
+                            block,
+                        });
+                    }
+                }
+            }
+        }
+        edits
+    }
+
+    /// Sky light in and around a chunk, spread through the loaded blocks.
+    /// Chunks that aren't loaded count as open air.
+    pub fn sky_light(&self, chunk_pos: IVec2) -> SkyLight {
+        // Cells are looked up a column at a time, so the last chunk found
+        // saves most of the map lookups
+        let last = Cell::new(None);
+        SkyLight::spread(chunk_pos, |cell| {
+            let (chunk_pos, x, y, z) = Self::locate(cell)?;
+            let chunk = match last.get() {
+                Some((pos, chunk)) if pos == chunk_pos => chunk,
+                _ => {
+                    let chunk = self.chunks.get(&chunk_pos);
+                    last.set(Some((chunk_pos, chunk)));
+                    chunk
+                }
+            };
+            chunk?.get(x, y, z)
+        })
+    }
+
+    fn is_solid(&self, block: IVec3) -> bool {
+        self.get_block(block)
+            .is_some_and(|block| block.block_type.is_solid())
+    }
+
+    /// Top, in world units, of the highest solid block at or below `point`
+    /// (in world units) in its column. Blocks are `block_size` across, the
+    /// same scale their meshes are built at.
+    pub fn ground_below(&self, point: Vec3) -> Option<f32> {
+        let s = self.config.block_size;
+        let block = (point / s).floor().as_ivec3();
+        (0..=block.y.min(WORLD_HEIGHT as i32 - 1))
+            .rev()
+            .find(|&y| self.is_solid(IVec3::new(block.x, y, block.z)))
+            .map(|y| (y + 1) as f32 * s)
+    }
+
+    /// Whether a solid block overlaps the vertical segment from `bottom` up
+    /// to `top`, in world units. A block just below `bottom` doesn't count,
+    /// so standing on a surface isn't overlapping it.
+    pub fn column_blocked(&self, bottom: Vec3, top: f32) -> bool {
+        let s = self.config.block_size;
+        let block = (bottom / s).floor().as_ivec3();
+        let lowest = (bottom.y / s + 0.01).floor() as i32;
+        let highest = (top / s).floor() as i32;
+        (lowest..=highest).any(|y| self.is_solid(IVec3::new(block.x, y, block.z)))
+    }
+
+    /// Walks the voxel grid from `origin` (in block space) along `direction`
+    /// and returns the first solid block within `max_distance` blocks. Every
+    /// cell the ray passes through is visited in order, so no block is skipped
+    /// however thin the ray's path through it.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RaycastHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return None;
+        }
+
+        // Ray distance to where it leaves `cell` across each axis. Worked out
+        // from the cell every time rather than accumulated, so rounding can't
+        // build up along the ray and cross a boundary in the wrong order
+        let exit = |cell: IVec3, axis: usize| {
+            let d = direction[axis];
+            if d == 0.0 {
+                return f32::INFINITY;
+            }
+            let boundary = cell[axis] as f32 + if d > 0.0 { 1.0 } else { 0.0 };
+            (boundary - origin[axis]) / d
+        };
+
+        let mut cell = origin.floor().as_ivec3();
+        let mut normal = IVec3::ZERO;
+        let mut t = 0.0;
+        while t <= max_distance {
+            if self.get_block(cell).is_some() {
+                return Some(RaycastHit {
+                    block: cell,
+                    normal,
+                });
+            }
+
+            let t_exit = Vec3::new(exit(cell, 0), exit(cell, 1), exit(cell, 2));
+            let axis = if t_exit.x < t_exit.y && t_exit.x < t_exit.z {
+                0
+            } else if t_exit.y < t_exit.z {
+                1
+            } else {
+                2
+            };
+
+            let step = if direction[axis] > 0.0 { 1 } else { -1 };
+            t = t_exit[axis];
+            cell[axis] += step;
+            normal = IVec3::ZERO;
+            normal[axis] = -step;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_gen::BlockType;
+
+    /// The face a ray from `origin` towards `target` enters the unit block at
+    /// `block` through: the one along the axis the ray crosses into last.
+    fn entry_normal(block: IVec3, origin: Vec3, target: Vec3) -> IVec3 {
+        let direction = target - origin;
+        let min = block.as_vec3();
+        let enter = |axis: usize| {
+            let d = direction[axis];
+            let plane = if d > 0.0 { min[axis] } else { min[axis] + 1.0 };
+            (plane - origin[axis]) / d
+        };
+        let axis = (0..3)
+            .max_by(|&a, &b| enter(a).total_cmp(&enter(b)))
+            .unwrap();
+        let mut normal = IVec3::ZERO;
+        normal[axis] = -direction[axis].signum() as i32;
+        normal
+    }
+
+    #[test]
+    fn raycast_reports_the_hit_block_and_face_from_every_angle() {
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        let block = IVec3::new(4, 10, 4);
+        chunk.set(4, 10, 4, Some(Block::new(BlockType::Stone)));
+        let mut world = World::new(WorldGenConfig::default());
+        world.insert_chunk(chunk);
+
+        // Aimed off the block's center so no ray runs exactly through an edge
+        let target = block.as_vec3() + Vec3::new(0.61, 0.37, 0.52);
+        for elevation in [-70.0f32, -35.0, -5.0, 20.0, 55.0, 85.0] {
+            for heading in (0..24).map(|i| i as f32 * 15.0 + 2.0) {
+                let (elevation, heading) = (elevation.to_radians(), heading.to_radians());
+                let offset = Vec3::new(
+                    elevation.cos() * heading.cos(),
+                    elevation.sin(),
+                    elevation.cos() * heading.sin(),
+                );
+                let origin = target + offset * 3.5;
+                let context = format!("from {origin}");
+
+                let hit = world.raycast(origin, target - origin, 5.0);
+                let hit = hit.unwrap_or_else(|| panic!("{context}: missed"));
+                assert_eq!(hit.block, block, "{context}");
+                assert_eq!(hit.normal, entry_normal(block, origin, target), "{context}");
+
+                // Out of reach
+                assert_eq!(
+                    world.raycast(origin, target - origin, 2.0),
+                    None,
+                    "{context}"
+                );
+            }
+        }
+
+        // A ray skimming down onto the top face, crossing into the block a
+        // hair below its top, still hits it there
+        let origin = Vec3::new(0.2, 11.0005, 4.5);
+        let hit = world
+            .raycast(origin, Vec3::new(1.0, -1.11e-4, 0.0), 5.0)
+            .unwrap();
+        assert_eq!((hit.block, hit.normal), (block, IVec3::Y));
+    }
+
+    #[test]
+    fn collision_follows_the_block_size() {
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(2, 3, 2, Some(Block::new(BlockType::Stone)));
+        chunk.set(5, 3, 5, Some(Block::new(BlockType::Poppy)));
+        // Half-size blocks: the stone spans 1 to 1.5 along X and Z and 1.5
+        // to 2 up
+        let mut world = World::new(WorldGenConfig {
+            block_size: 0.5,
+            ..WorldGenConfig::default()
+        });
+        world.insert_chunk(chunk);
+
+        assert_eq!(world.ground_below(Vec3::new(1.2, 4.0, 1.4)), Some(2.0));
+        // Past the stone's edge at this scale, though inside it at size 1
+        assert_eq!(world.ground_below(Vec3::new(1.6, 4.0, 1.4)), None);
+        // Below the stone
+        assert_eq!(world.ground_below(Vec3::new(1.2, 1.4, 1.4)), None);
+        // Plants can be walked through
+        assert_eq!(world.ground_below(Vec3::new(2.7, 4.0, 2.7)), None);
+
+        let feet = Vec3::new(1.2, 2.0, 1.4);
+        // Standing on top of the stone
+        assert!(!world.column_blocked(feet, feet.y + 1.6));
+        // Sunk into it
+        assert!(world.column_blocked(feet - Vec3::Y * 0.2, feet.y + 1.4));
+        // Below it, with the head reaching into it
+        assert!(world.column_blocked(Vec3::new(1.2, 0.5, 1.4), 1.6));
+        assert!(!world.column_blocked(Vec3::new(1.2, 0.0, 1.4), 1.4));
+    }
+
+    #[test]
+    fn edits_are_the_blocks_that_differ_from_generation() {
+        let mut world = World::new(WorldGenConfig::default());
+        let chunk_pos = IVec2::new(-1, 2);
+        world.insert_chunk(generate_chunk(chunk_pos, &world.config));
+        assert!(world.edits(chunk_pos).is_empty());
+
+        let broken = IVec3::new(-3, 1, 20);
+        let placed = IVec3::new(-8, WORLD_HEIGHT as i32 - 1, 16);
+        let glass = Some(Block::new(BlockType::Glass));
+        world.set_block(broken, None);
+        world.set_block(placed, glass);
+        let mut edits = world.edits(chunk_pos);
+        edits.sort_by_key(|edit| edit.position.x);
+        assert_eq!(
+            edits,
+            [
+                BlockEdit {
+                    position: placed,
+                    block: glass,
+                },
+                BlockEdit {
+                    position: broken,
+                    block: None,
+                },
+            ]
+        );
+        assert!(world.edits(IVec2::ZERO).is_empty());
+    }
+}
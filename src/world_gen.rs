@@ -1,282 +1,1616 @@
-use crate::geometry::{Geometry, Vertex};
-use crate::perlin;
-use anyhow::Result;
-use glam::{Vec2, Vec3};
+use crate::geometry::{dedup_vertices, ChunkMesh, Geometry, Vertex};
+use crate::light::{SkyLight, MAX_SKY_LIGHT};
+use crate::perlin::Perlin;
+use crate::rng::coord_rng;
+use crate::structures::{place_structures, STRUCTURE_REACH};
+use anyhow::{anyhow, bail, Result};
+use glam::{IVec2, IVec3, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
 
-pub const CHUNK_SIZE: usize = 8;
+/// Chunk footprint in blocks along x and z. They're independent so chunks
+/// can be made rectangular; the defaults are square.
+pub const CHUNK_SIZE_X: usize = 8;
+pub const CHUNK_SIZE_Z: usize = 8;
 pub const CHUNK_HEIGHT: usize = 8;
+/// Number of block layers stored per chunk; leaves room above the terrain for building.
+pub const WORLD_HEIGHT: usize = CHUNK_HEIGHT * 4;
+/// Most vertices a chunk mesh can have; its 16-bit indices can't address more.
+pub const MAX_CHUNK_VERTICES: usize = u16::MAX as usize + 1;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BlockType {
     Grass,
     Dirt,
     Stone,
+    Log,
+    Ore,
+    Glass,
+    Leaves,
+    /// The unbreakable floor at the bottom of every column. Not placeable,
+    /// so it's left out of `ALL`.
+    Bedrock,
+    /// Plants scattered on grass, drawn as crossed quads (see
+    /// `is_decoration`). Only generated, so also left out of `ALL`.
+    TallGrass,
+    Poppy,
+    Dandelion,
 }
 
-#[derive(Debug, Clone)]
+impl BlockType {
+    /// Every block type that can be placed, in hotbar order. Must follow the
+    /// declaration order, as tables indexed by type rely on it.
+    pub const ALL: [BlockType; 7] = [
+        BlockType::Grass,
+        BlockType::Dirt,
+        BlockType::Stone,
+        BlockType::Log,
+        BlockType::Ore,
+        BlockType::Glass,
+        BlockType::Leaves,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlockType::Grass => "Grass",
+            BlockType::Dirt => "Dirt",
+            BlockType::Stone => "Stone",
+            BlockType::Log => "Log",
+            BlockType::Ore => "Ore",
+            BlockType::Glass => "Glass",
+            BlockType::Leaves => "Leaves",
+            BlockType::Bedrock => "Bedrock",
+            BlockType::TallGrass => "Tall grass",
+            BlockType::Poppy => "Poppy",
+            BlockType::Dandelion => "Dandelion",
+        }
+    }
+
+    /// Default time to break, relative to a block that takes ten break-key
+    /// events. Infinite for blocks that can't be broken.
+    pub fn hardness(self) -> f32 {
+        match self {
+            BlockType::Grass => 0.6,
+            BlockType::Dirt => 0.5,
+            BlockType::Stone => 1.5,
+            BlockType::Log => 2.0,
+            BlockType::Ore => 3.0,
+            BlockType::Glass => 0.3,
+            BlockType::Leaves => 0.2,
+            BlockType::Bedrock => f32::INFINITY,
+            BlockType::TallGrass | BlockType::Poppy | BlockType::Dandelion => 0.0,
+        }
+    }
+
+    /// Whether the block can be seen through. Transparent blocks are drawn
+    /// in the translucent pass and don't hide their neighbours' faces.
+    pub fn is_transparent(self) -> bool {
+        matches!(self, BlockType::Glass)
+    }
+
+    /// Whether the block is a plant drawn as two quads crossing diagonally
+    /// through its cell instead of a cube. Decorations are neither solid nor
+    /// opaque and cast no sky shadow.
+    pub fn is_decoration(self) -> bool {
+        matches!(
+            self,
+            BlockType::TallGrass | BlockType::Poppy | BlockType::Dandelion
+        )
+    }
+
+    /// Whether the block stops a walking body. Plants are walked through.
+    pub fn is_solid(self) -> bool {
+        !self.is_decoration()
+    }
+
+    /// Whether the block hides the faces of whatever it touches. Glass is
+    /// seen through and leaves have gaps between them, so neither is opaque
+    /// even though leaves are solid.
+    pub fn is_opaque(self) -> bool {
+        matches!(
+            self,
+            BlockType::Grass
+                | BlockType::Dirt
+                | BlockType::Stone
+                | BlockType::Log
+                | BlockType::Ore
+                | BlockType::Bedrock
+        )
+    }
+
+    /// Whether the block's textures look right turned any which way, so
+    /// `--rotate-textures` may turn them. Grass and logs have a direction.
+    pub fn is_rotatable(self) -> bool {
+        matches!(
+            self,
+            BlockType::Dirt
+                | BlockType::Stone
+                | BlockType::Ore
+                | BlockType::Leaves
+                | BlockType::Bedrock
+        )
+    }
+
+    /// Whether a face of this block is hidden by the `neighbour` it touches.
+    /// Opaque neighbours hide any face, others only faces of the same type,
+    /// so glass next to glass merges but stone behind glass shows.
+    pub fn face_hidden_by(self, neighbour: Option<Block>) -> bool {
+        neighbour.is_some_and(|neighbour| {
+            neighbour.block_type.is_opaque() || neighbour.block_type == self
+        })
+    }
+}
+
+impl FromStr for BlockType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        BlockType::ALL
+            .into_iter()
+            .find(|block_type| block_type.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| anyhow!("unknown block type '{s}'"))
+    }
+}
+
+/// Lighting computed per vertex while meshing and stored in vertex colors.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BakedLighting {
+    /// Directional light coming from this direction.
+    Sun(Vec3),
+    /// Fixed brightness per face direction, regardless of the sun.
+    Faces(FaceBrightness),
+}
+
+/// Brightness of each face direction for `BakedLighting::Faces`. The default
+/// matches classic Minecraft.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FaceBrightness {
+    pub top: f32,
+    /// Faces pointing along Z.
+    pub north_south: f32,
+    /// Faces pointing along X.
+    pub east_west: f32,
+    pub bottom: f32,
+}
+
+impl Default for FaceBrightness {
+    fn default() -> Self {
+        Self {
+            top: 1.0,
+            north_south: 0.8,
+            east_west: 0.6,
+            bottom: 0.5,
+        }
+    }
+}
+
+impl FaceBrightness {
+    /// Brightness of a face with the axis-aligned `normal`.
+    pub fn for_normal(&self, normal: Vec3) -> f32 {
+        if normal.y > 0.5 {
+            self.top
+        } else if normal.y < -0.5 {
+            self.bottom
+        } else if normal.x.abs() > 0.5 {
+            self.east_west
+        } else {
+            self.north_south
+        }
+    }
+}
+
+impl FromStr for FaceBrightness {
+    type Err = anyhow::Error;
+
+    /// Parses `TOP,NORTH_SOUTH,EAST_WEST,BOTTOM`.
+    fn from_str(s: &str) -> Result<Self> {
+        let values: Vec<f32> = s
+            .split(',')
+            .map(|v| v.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| anyhow!("invalid face brightness '{s}'"))?;
+        match values[..] {
+            [top, north_south, east_west, bottom] => Ok(Self {
+                top,
+                north_south,
+                east_west,
+                bottom,
+            }),
+            _ => bail!("invalid face brightness '{s}' (expected top,north_south,east_west,bottom)"),
+        }
+    }
+}
+
+/// Parameters shared by terrain generation and chunk meshing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+// Settings missing from older saved states keep their defaults
+#[serde(default)]
+pub struct WorldGenConfig {
+    /// Edge length of a single block in world units.
+    pub block_size: f32,
+    /// Terrain seed. 0 reproduces the reference world.
+    pub seed: u64,
+    /// Rough diameter of ore clusters in blocks.
+    pub ore_size: f32,
+    /// Noise level stone must exceed to become ore. 0 turns about half of the
+    /// deep stone into ore; higher values make ore rarer.
+    pub ore_rarity: f32,
+    /// Blocks of soil at the top of each column, with stone below. `None`
+    /// keeps the reference world's noise-mixed grass, dirt and stone.
+    pub soil_depth: Option<usize>,
+    /// Whether soil exposed at the surface is grass; otherwise it's dirt.
+    pub grass_top: bool,
+    /// Rough width of a biome in blocks. `None` disables biomes, keeping the
+    /// same terrain everywhere.
+    pub biome_size: Option<f32>,
+    /// Radius in columns of the box blur applied to the terrain heights
+    /// before blocks are placed; 0 keeps the raw noise.
+    pub erosion_radius: usize,
+    /// How far heights move towards their blurred value, 0 to 1.
+    pub erosion_strength: f32,
+    /// Lighting to bake into vertex colors. `None` leaves lighting to the
+    /// fragment shader.
+    pub baked_lighting: Option<BakedLighting>,
+    /// Brightness of faces sky light doesn't reach, baked along with
+    /// `baked_lighting`; faces partly lit by light spreading in from the sky
+    /// fall between it and 1. `None` lights every face as open sky.
+    pub sky_shadow: Option<f32>,
+    /// Darken every other chunk in a checkerboard, to make seams and culling
+    /// mistakes at chunk boundaries stand out.
+    pub chunk_checkerboard: bool,
+    /// Merge identical vertices after meshing to shrink vertex buffers.
+    pub dedup_vertices: bool,
+    /// Turn the textures of rotatable blocks by a quarter turn or more per
+    /// face, picked from the block's position, to hide the tile grid.
+    pub rotate_textures: bool,
+    /// Chance of a tree on each grass column. `None` grows no trees.
+    pub tree_density: Option<f32>,
+    /// Chance of a plant on each open grass top. `None` places none.
+    pub decoration_density: Option<f32>,
+    /// Share of the plants that are flowers rather than tall grass, 0 to 1.
+    pub flower_share: f32,
+    /// Vertex budget of a chunk mesh, at most `MAX_CHUNK_VERTICES`. Faces past
+    /// it are dropped with a warning instead of building a huge buffer.
+    pub max_chunk_vertices: usize,
+}
+
+impl Default for WorldGenConfig {
+    fn default() -> Self {
+        Self {
+            block_size: 1.0,
+            seed: 0,
+            ore_size: 3.0,
+            ore_rarity: 0.45,
+            soil_depth: None,
+            grass_top: true,
+            biome_size: None,
+            erosion_radius: 0,
+            erosion_strength: 1.0,
+            baked_lighting: None,
+            sky_shadow: None,
+            chunk_checkerboard: false,
+            dedup_vertices: false,
+            rotate_textures: false,
+            tree_density: None,
+            decoration_density: None,
+            flower_share: 0.25,
+            max_chunk_vertices: MAX_CHUNK_VERTICES,
+        }
+    }
+}
+
+/// Ore only replaces stone below this layer.
+const ORE_MAX_HEIGHT: usize = CHUNK_HEIGHT + 2;
+
+/// Mixed into the world seed so ore noise is independent of the terrain noise.
+const ORE_SEED_SALT: u64 = 0x6f72_6573;
+
+/// Mixed into the world seed for the biome temperature and humidity noise.
+const TEMPERATURE_SEED_SALT: u64 = 0x7465_6d70;
+const HUMIDITY_SEED_SALT: u64 = 0x6875_6d69;
+
+/// Terrain traits of a biome.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biome {
+    pub name: &'static str,
+    /// Top block of each column, when it has any soil.
+    pub surface: BlockType,
+    /// Blocks of soil over the stone. 0 leaves bare rock.
+    pub soil_depth: usize,
+    /// Multiplier for the height of the hills.
+    pub hill_scale: f32,
+}
+
+const fn biome(
+    name: &'static str,
+    surface: BlockType,
+    soil_depth: usize,
+    hill_scale: f32,
+) -> Biome {
+    Biome {
+        name,
+        surface,
+        soil_depth,
+        hill_scale,
+    }
+}
+
+/// Biomes by temperature (rows, cold to hot) and humidity (columns, dry to
+/// wet).
+pub const BIOME_TABLE: [[Biome; 3]; 3] = [
+    [
+        biome("Tundra", BlockType::Stone, 0, 0.5),
+        biome("Taiga", BlockType::Grass, 2, 1.0),
+        biome("Bog", BlockType::Dirt, 4, 0.3),
+    ],
+    [
+        biome("Steppe", BlockType::Dirt, 2, 0.6),
+        biome("Plains", BlockType::Grass, 3, 1.0),
+        biome("Forest", BlockType::Grass, 4, 1.2),
+    ],
+    [
+        biome("Desert", BlockType::Dirt, 5, 0.4),
+        biome("Savanna", BlockType::Grass, 2, 0.7),
+        biome("Jungle", BlockType::Grass, 5, 1.6),
+    ],
+];
+
+/// Temperature and humidity noise fields that pick a biome from
+/// `BIOME_TABLE` for each column.
+pub struct BiomeNoise {
+    temperature: Perlin,
+    humidity: Perlin,
+    size: f64,
+}
+
+impl BiomeNoise {
+    pub fn new(seed: u64, size: f32) -> Self {
+        Self {
+            temperature: Perlin::new(seed ^ TEMPERATURE_SEED_SALT),
+            humidity: Perlin::new(seed ^ HUMIDITY_SEED_SALT),
+            size: size.max(1.0) as f64,
+        }
+    }
+
+    /// Biome of the column at block-space `x`, `z`.
+    pub fn biome(&self, x: i32, z: i32) -> &'static Biome {
+        // Offset off the lattice, where Perlin noise is always zero
+        let (x, z) = (x as f64 / self.size + 0.43, z as f64 / self.size + 0.27);
+        let band = |noise: f64| match noise {
+            n if n < -0.15 => 0,
+            n if n > 0.15 => 2,
+            _ => 1,
+        };
+        let temperature = band(self.temperature.noise3d(x, 0.0, z));
+        let humidity = band(self.humidity.noise3d(x, 0.0, z));
+        &BIOME_TABLE[temperature][humidity]
+    }
+}
+
+/// Turns a user-supplied seed into a world seed. Numbers are used as-is and
+/// any other text is hashed (64-bit FNV-1a), so the same string always
+/// yields the same world.
+pub fn seed_from_str(text: &str) -> u64 {
+    if let Ok(seed) = text.trim().parse::<u64>() {
+        return seed;
+    }
+
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    text.bytes().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// The axis a directional block, such as a log, runs along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Axis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// The axis a face normal points along.
+    pub fn from_normal(normal: IVec3) -> Self {
+        if normal.x != 0 {
+            Axis::X
+        } else if normal.z != 0 {
+            Axis::Z
+        } else {
+            Axis::Y
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Block {
-    pub position: Vec3,
     pub block_type: BlockType,
+    /// Orientation for directional blocks; ignored by the others.
+    pub axis: Axis,
+}
+
+impl Block {
+    pub fn new(block_type: BlockType) -> Self {
+        Self {
+            block_type,
+            axis: Axis::default(),
+        }
+    }
+}
+
+/// A `CHUNK_SIZE_X` x `WORLD_HEIGHT` x `CHUNK_SIZE_Z` column of blocks.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    /// Chunk coordinates; scaled by the chunk size on each axis for the
+    /// block-space origin.
+    pub position: IVec2,
+    blocks: Vec<Option<Block>>,
+}
+
+impl Chunk {
+    pub fn new(position: IVec2) -> Self {
+        Self {
+            position,
+            blocks: vec![None; CHUNK_SIZE_X * WORLD_HEIGHT * CHUNK_SIZE_Z],
+        }
+    }
+
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        (x * CHUNK_SIZE_Z + z) * WORLD_HEIGHT + y
+    }
+
+    /// Block-space coordinates of the chunk's local (0, 0, 0) block.
+    pub fn origin(&self) -> IVec3 {
+        IVec3::new(
+            self.position.x * CHUNK_SIZE_X as i32,
+            0,
+            self.position.y * CHUNK_SIZE_Z as i32,
+        )
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<Block> {
+        self.blocks[Self::index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: Option<Block>) {
+        self.blocks[Self::index(x, y, z)] = block;
+    }
+
+    /// Iterates over every non-air block with its block-space position.
+    pub fn blocks(&self) -> impl Iterator<Item = (IVec3, Block)> + '_ {
+        let origin = self.origin();
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, block)| {
+                let y = i % WORLD_HEIGHT;
+                let z = (i / WORLD_HEIGHT) % CHUNK_SIZE_Z;
+                let x = i / (WORLD_HEIGHT * CHUNK_SIZE_Z);
+                block.map(|block| (origin + IVec3::new(x as i32, y as i32, z as i32), block))
+            })
+    }
 }
 
-pub fn generate_chunk_geometry(
+pub fn create_chunk_geometry(
     device: &wgpu::Device,
-    _queue: &wgpu::Queue,
-    chunk_pos: Vec2,
+    chunk: &Chunk,
+    light: Option<&SkyLight>,
+    config: &WorldGenConfig,
 ) -> Result<Geometry> {
-    let chunk = generate_chunk(chunk_pos);
+    Geometry::new(device, &build_lit_chunk_mesh(chunk, light, config))
+}
+
+/// Number of tile columns and rows in `textures/blocks.png`.
+const ATLAS_COLUMNS: f32 = 4.0;
+const ATLAS_ROWS: f32 = 4.0;
+
+type FaceUvs = [[f32; 2]; 4];
+
+/// Texture coordinates of an atlas tile, in the corner order the face
+/// vertices use: top-left, bottom-right, bottom-left, top-right.
+fn tile(column: u32, row: u32) -> FaceUvs {
+    let u0 = column as f32 / ATLAS_COLUMNS;
+    let v0 = row as f32 / ATLAS_ROWS;
+    let u1 = u0 + 1.0 / ATLAS_COLUMNS;
+    let v1 = v0 + 1.0 / ATLAS_ROWS;
+    [[u0, v0], [u1, v1], [u0, v1], [u1, v0]]
+}
+
+/// Turns a face's texture a quarter turn.
+fn rotated(uvs: FaceUvs) -> FaceUvs {
+    [uvs[2], uvs[3], uvs[1], uvs[0]]
+}
+
+/// Quarter turns for each face of the block at `position`. Picked from the
+/// position and seed alone, so a block looks the same every time its chunk
+/// is meshed.
+fn texture_turns(seed: u64, position: IVec3) -> [u32; 6] {
+    let seed = seed ^ seed_from_str("texture rotation");
+    let mut rng = coord_rng(seed, position.x, position.y, position.z);
+    [(); 6].map(|_| rng.below(4))
+}
+
+/// Texture coordinates for each face of a block, in the order front (+Z),
+/// back (-Z), left (-X), right (+X), bottom, top.
+fn face_textures(block: Block) -> [FaceUvs; 6] {
+    let grass_side = tile(0, 0);
+    let grass_top = tile(1, 0);
+    let stone = tile(0, 1);
+    let dirt = tile(1, 1);
+
+    match block.block_type {
+        BlockType::Grass => [
+            grass_side, grass_side, grass_side, grass_side, dirt, grass_top,
+        ],
+        BlockType::Dirt => [dirt; 6],
+        BlockType::Stone => [stone; 6],
+        BlockType::Ore => [tile(2, 1); 6],
+        BlockType::Glass => [tile(3, 1); 6],
+        BlockType::Bedrock => [tile(0, 2); 6],
+        BlockType::Leaves => [tile(1, 2); 6],
+        // Only the first two are used, one per crossed quad
+        BlockType::TallGrass => [tile(2, 2); 6],
+        BlockType::Poppy => [tile(3, 2); 6],
+        BlockType::Dandelion => [tile(0, 3); 6],
+        BlockType::Log => {
+            // The bark grain runs along the log, so faces parallel to a
+            // horizontal log show it turned on its side
+            let bark = tile(2, 0);
+            let bark_across = rotated(bark);
+            let end = tile(3, 0);
+            match block.axis {
+                Axis::Y => [bark, bark, bark, bark, end, end],
+                Axis::X => [bark_across, bark_across, end, end, bark_across, bark_across],
+                Axis::Z => [end, end, bark_across, bark_across, bark, bark],
+            }
+        }
+    }
+}
+
+/// Light reaching faces turned away from the sun. Must match `AMBIENT` in
+/// shader.wgsl.
+const AMBIENT: f32 = 0.55;
+
+/// Brightness of a surface facing `normal`, as the shader's `sunlight`.
+fn sunlight(normal: Vec3, sun: Vec3) -> f32 {
+    let diffuse = normal.normalize_or_zero().dot(sun).max(0.0);
+    AMBIENT + (1.0 - AMBIENT) * diffuse
+}
+
+/// Builds the CPU-side vertex and index data for a chunk. Faces hidden by
+/// the block next to them are left out. With `sky_shadow`, sky light is
+/// spread through the chunk alone, as if open sky surrounded it.
+pub fn build_chunk_mesh(chunk: &Chunk, config: &WorldGenConfig) -> ChunkMesh {
+    let origin = chunk.origin();
+    let light = config
+        .sky_shadow
+        .map(|_| SkyLight::spread(chunk.position, |cell| local_block(chunk, cell - origin)));
+    build_lit_chunk_mesh(chunk, light.as_ref(), config)
+}
+
+/// Like `build_chunk_mesh`, with sky light already spread, usually through
+/// the neighbouring chunks too. Without `light` every face is lit as open
+/// sky.
+pub fn build_lit_chunk_mesh(
+    chunk: &Chunk,
+    light: Option<&SkyLight>,
+    config: &WorldGenConfig,
+) -> ChunkMesh {
+    let s = config.block_size;
 
     let mut vertices = Vec::new();
     let mut indices = Vec::new();
-    let mut index_offset = 0u16;
-
-    // Texture coordinates for different block types
-    let grass_side_tc = [[0.0, 0.0], [0.5, 0.5], [0.0, 0.5], [0.5, 0.0]];
-    let grass_top_tc = [[0.5, 0.0], [1.0, 0.5], [0.5, 0.5], [1.0, 0.0]];
-    let stone_tc = [[0.0, 0.5], [0.5, 1.0], [0.0, 1.0], [0.5, 0.5]];
-    let dirt_tc = [[0.5, 0.5], [1.0, 1.0], [0.5, 1.0], [1.0, 0.5]];
-
-    for layer in &chunk {
-        for row in layer {
-            for block in row {
-                let x = block.position.x;
-                let y = block.position.y;
-                let z = block.position.z;
-
-                let tex_coords = match block.block_type {
-                    BlockType::Grass => [
-                        grass_side_tc,
-                        grass_side_tc,
-                        grass_side_tc,
-                        grass_side_tc,
-                        dirt_tc,
-                        grass_top_tc,
-                    ],
-                    BlockType::Dirt => [dirt_tc; 6],
-                    BlockType::Stone => [stone_tc; 6],
-                };
-
-                // Generate vertices for each face of the cube
-                let cube_vertices = vec![
-                    // Front face
-                    Vertex::new(
-                        Vec3::new(x, y + 1.0, z + 1.0),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        tex_coords[0][0],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y, z + 1.0),
-                        Vec3::new(0.0, 1.0, 0.0),
-                        tex_coords[0][1],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x, y, z + 1.0),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[0][2],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y + 1.0, z + 1.0),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[0][3],
-                    ),
-                    // Back face
-                    Vertex::new(
-                        Vec3::new(x, y + 1.0, z),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        tex_coords[1][0],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y, z),
-                        Vec3::new(0.0, 1.0, 0.0),
-                        tex_coords[1][1],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x, y, z),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[1][2],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y + 1.0, z),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[1][3],
-                    ),
-                    // Left face
-                    Vertex::new(
-                        Vec3::new(x, y + 1.0, z),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        tex_coords[2][0],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x, y, z + 1.0),
-                        Vec3::new(0.0, 1.0, 0.0),
-                        tex_coords[2][1],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x, y, z),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[2][2],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x, y + 1.0, z + 1.0),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[2][3],
-                    ),
-                    // Right face
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y + 1.0, z),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        tex_coords[3][0],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y, z + 1.0),
-                        Vec3::new(0.0, 1.0, 0.0),
-                        tex_coords[3][1],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y, z),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[3][2],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y + 1.0, z + 1.0),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[3][3],
-                    ),
-                    // Bottom face
-                    Vertex::new(
-                        Vec3::new(x, y, z + 1.0),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        tex_coords[4][0],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y, z),
-                        Vec3::new(0.0, 1.0, 0.0),
-                        tex_coords[4][1],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x, y, z),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[4][2],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y, z + 1.0),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[4][3],
-                    ),
-                    // Top face
-                    Vertex::new(
-                        Vec3::new(x, y + 1.0, z + 1.0),
-                        Vec3::new(1.0, 0.0, 0.0),
-                        tex_coords[5][0],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y + 1.0, z),
-                        Vec3::new(0.0, 1.0, 0.0),
-                        tex_coords[5][1],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x, y + 1.0, z),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[5][2],
-                    ),
-                    Vertex::new(
-                        Vec3::new(x + 1.0, y + 1.0, z + 1.0),
-                        Vec3::new(0.0, 0.0, 1.0),
-                        tex_coords[5][3],
-                    ),
-                ];
+    let mut translucent_indices = Vec::new();
+    let mut decoration_indices = Vec::new();
+    // Sum of exposed face normals at each corner, keyed by block-space corner
+    let mut corner_normals: HashMap<IVec3, Vec3> = HashMap::new();
+    // Sky light reaching each vertex's face, 1 in the open
+    let mut sky_light = Vec::new();
+    let origin = chunk.origin();
+    let sky = |cell: IVec3| match (config.sky_shadow, light) {
+        (Some(shadow), Some(light)) => {
+            shadow + (1.0 - shadow) * f32::from(light.level(cell)) / f32::from(MAX_SKY_LIGHT)
+        }
+        _ => 1.0,
+    };
+    let max_vertices = config.max_chunk_vertices.min(MAX_CHUNK_VERTICES);
+    let mut truncated = false;
 
-                vertices.extend(cube_vertices);
-
-                // Generate indices for the cube (6 faces, 2 triangles each)
-                // Match the reference implementation's winding order
-                let face_indices = [
-                    // Front face
-                    [0, 1, 2, 0, 3, 1],
-                    // Back face (reversed winding)
-                    [6, 5, 4, 5, 7, 4],
-                    // Left face
-                    [8, 9, 10, 8, 11, 9],
-                    // Right face (reversed winding)
-                    [14, 13, 12, 13, 15, 12],
-                    // Bottom face
-                    [16, 17, 18, 16, 19, 17],
-                    // Top face (reversed winding)
-                    [22, 21, 20, 21, 23, 20],
-                ];
+    'blocks: for (position, block) in chunk.blocks() {
+        // Block positions are grid coordinates; scale them into world units
+        let x = position.x as f32 * s;
+        let y = position.y as f32 * s;
+        let z = position.z as f32 * s;
 
-                for (face, face_idx) in face_indices.iter().enumerate() {
-                    let base = index_offset + (face * 4) as u16;
-                    for &idx in face_idx {
-                        indices.push(base + idx);
-                    }
+        let mut tex_coords = face_textures(block);
+        if config.rotate_textures && block.block_type.is_rotatable() {
+            let turns = texture_turns(config.seed, position);
+            for (uvs, turns) in tex_coords.iter_mut().zip(turns) {
+                for _ in 0..turns {
+                    *uvs = rotated(*uvs);
                 }
+            }
+        }
+
+        if block.block_type.is_decoration() {
+            if vertices.len() + 8 > max_vertices {
+                truncated = true;
+                break 'blocks;
+            }
+            let first = vertices.len() as u16;
+            decoration_indices.extend(CROSS_INDICES.map(|i| first + i));
+            vertices.extend(cross_vertices(Vec3::new(x, y, z), s, tex_coords));
+            sky_light.extend([sky(position); 8]);
+            continue;
+        }
+
+        // Generate vertices for each face of the cube
+        let mut cube_vertices = vec![
+            // Front face
+            Vertex::new(
+                Vec3::new(x, y + s, z + s),
+                Vec3::new(1.0, 0.0, 0.0),
+                tex_coords[0][0],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y, z + s),
+                Vec3::new(0.0, 1.0, 0.0),
+                tex_coords[0][1],
+            ),
+            Vertex::new(
+                Vec3::new(x, y, z + s),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[0][2],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y + s, z + s),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[0][3],
+            ),
+            // Back face
+            Vertex::new(
+                Vec3::new(x, y + s, z),
+                Vec3::new(1.0, 0.0, 0.0),
+                tex_coords[1][0],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y, z),
+                Vec3::new(0.0, 1.0, 0.0),
+                tex_coords[1][1],
+            ),
+            Vertex::new(
+                Vec3::new(x, y, z),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[1][2],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y + s, z),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[1][3],
+            ),
+            // Left face
+            Vertex::new(
+                Vec3::new(x, y + s, z),
+                Vec3::new(1.0, 0.0, 0.0),
+                tex_coords[2][0],
+            ),
+            Vertex::new(
+                Vec3::new(x, y, z + s),
+                Vec3::new(0.0, 1.0, 0.0),
+                tex_coords[2][1],
+            ),
+            Vertex::new(
+                Vec3::new(x, y, z),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[2][2],
+            ),
+            Vertex::new(
+                Vec3::new(x, y + s, z + s),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[2][3],
+            ),
+            // Right face
+            Vertex::new(
+                Vec3::new(x + s, y + s, z),
+                Vec3::new(1.0, 0.0, 0.0),
+                tex_coords[3][0],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y, z + s),
+                Vec3::new(0.0, 1.0, 0.0),
+                tex_coords[3][1],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y, z),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[3][2],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y + s, z + s),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[3][3],
+            ),
+            // Bottom face
+            Vertex::new(
+                Vec3::new(x, y, z + s),
+                Vec3::new(1.0, 0.0, 0.0),
+                tex_coords[4][0],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y, z),
+                Vec3::new(0.0, 1.0, 0.0),
+                tex_coords[4][1],
+            ),
+            Vertex::new(
+                Vec3::new(x, y, z),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[4][2],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y, z + s),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[4][3],
+            ),
+            // Top face
+            Vertex::new(
+                Vec3::new(x, y + s, z + s),
+                Vec3::new(1.0, 0.0, 0.0),
+                tex_coords[5][0],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y + s, z),
+                Vec3::new(0.0, 1.0, 0.0),
+                tex_coords[5][1],
+            ),
+            Vertex::new(
+                Vec3::new(x, y + s, z),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[5][2],
+            ),
+            Vertex::new(
+                Vec3::new(x + s, y + s, z + s),
+                Vec3::new(0.0, 0.0, 1.0),
+                tex_coords[5][3],
+            ),
+        ];
+
+        let face_indices = if block.block_type.is_transparent() {
+            &mut translucent_indices
+        } else {
+            &mut indices
+        };
+        for (face, normal) in FACE_NORMALS.iter().enumerate() {
+            if block
+                .block_type
+                .face_hidden_by(local_block(chunk, position - origin + *normal))
+            {
+                continue;
+            }
+            if vertices.len() + 4 > max_vertices {
+                truncated = true;
+                break 'blocks;
+            }
 
-                index_offset += 24; // 24 vertices per cube
+            let face_vertices = &mut cube_vertices[face * 4..face * 4 + 4];
+            for vertex in face_vertices.iter_mut() {
+                vertex.normal = normal.as_vec3().to_array();
+                *corner_normals.entry(corner(vertex, s)).or_default() += normal.as_vec3();
             }
+            let first = vertices.len() as u16;
+            face_indices.extend(FACE_INDICES[face].map(|i| first + i));
+            vertices.extend_from_slice(face_vertices);
+
+            sky_light.extend([sky(position + *normal); 4]);
         }
     }
 
-    Geometry::new(device, &vertices, &indices)
+    if truncated {
+        log::warn!(
+            "chunk {} needs more than {max_vertices} vertices, dropping the faces past them",
+            chunk.position
+        );
+    }
+
+    // Blue scales the brightness of the whole chunk
+    let tint = if config.chunk_checkerboard && (chunk.position.x + chunk.position.y) % 2 != 0 {
+        CHECKERBOARD_SHADE
+    } else {
+        1.0
+    };
+    for (vertex, sky) in vertices.iter_mut().zip(sky_light) {
+        let normal = Vec3::from(vertex.normal);
+        let smooth = corner_normals
+            .get(&corner(vertex, s))
+            .and_then(|sum| sum.try_normalize())
+            .unwrap_or(normal);
+        vertex.smooth_normal = smooth.to_array();
+        // Flat brightness goes in red and smooth in green, so switching
+        // shading modes doesn't need a re-mesh
+        match config.baked_lighting {
+            Some(BakedLighting::Sun(sun)) => {
+                vertex.color = [
+                    sunlight(normal, sun) * sky,
+                    sunlight(smooth, sun) * sky,
+                    tint,
+                ];
+            }
+            Some(BakedLighting::Faces(faces)) => {
+                let brightness = faces.for_normal(normal) * sky;
+                vertex.color = [brightness, brightness, tint];
+            }
+            None => vertex.color[2] = tint,
+        }
+    }
+
+    // Decorations follow the opaque faces, and translucent faces go last so
+    // they can be drawn after everything else
+    let decoration_start = indices.len();
+    indices.extend(decoration_indices);
+    let translucent_start = indices.len();
+    indices.extend(translucent_indices);
+    if config.dedup_vertices {
+        (vertices, indices) = dedup_vertices(&vertices, &indices);
+    }
+
+    ChunkMesh {
+        vertices,
+        indices,
+        decoration_start,
+        translucent_start,
+    }
 }
 
-fn generate_chunk(chunk_pos: Vec2) -> Vec<Vec<Vec<Block>>> {
-    let actual_chunk_pos = Vec3::new(
-        chunk_pos.x * CHUNK_SIZE as f32,
-        0.0,
-        chunk_pos.y * CHUNK_SIZE as f32,
-    );
+/// Two quads crossing diagonally through the cell at `min`, textured with
+/// the first two of `tex_coords`. They're lit as if facing up, like the
+/// ground they stand on.
+fn cross_vertices(min: Vec3, s: f32, tex_coords: [FaceUvs; 6]) -> [Vertex; 8] {
+    let quad = |from: Vec3, to: Vec3, uvs: FaceUvs| {
+        [
+            min + from + Vec3::Y * s,
+            min + to,
+            min + from,
+            min + to + Vec3::Y * s,
+        ]
+        .into_iter()
+        .zip(uvs)
+        .map(|(position, uv)| {
+            let mut vertex = Vertex::new(position, Vec3::ONE, uv);
+            vertex.normal = Vec3::Y.to_array();
+            vertex
+        })
+    };
+    let first = quad(Vec3::ZERO, Vec3::new(s, 0.0, s), tex_coords[0]);
+    let second = quad(Vec3::X * s, Vec3::Z * s, tex_coords[1]);
+    let mut vertices = first.chain(second);
+    std::array::from_fn(|_| vertices.next().unwrap())
+}
 
-    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+/// Triangles of the two crossed quads. They're drawn without culling, so
+/// each quad shows from both sides.
+const CROSS_INDICES: [u16; 12] = [0, 2, 1, 0, 1, 3, 4, 6, 5, 4, 5, 7];
 
-    for x in 0..CHUNK_SIZE {
-        let mut layer = Vec::with_capacity(CHUNK_SIZE);
+/// Triangles of each face, counter-clockwise seen from outside, counting from
+/// the face's first vertex.
+const FACE_INDICES: [[u16; 6]; 6] = [
+    // Front
+    [0, 2, 1, 0, 1, 3],
+    // Back
+    [2, 0, 1, 1, 0, 3],
+    // Left
+    [0, 2, 1, 0, 1, 3],
+    // Right
+    [2, 0, 1, 1, 0, 3],
+    // Bottom
+    [0, 2, 1, 0, 1, 3],
+    // Top
+    [2, 0, 1, 1, 0, 3],
+];
 
-        for z in 0..CHUNK_SIZE {
-            // Match height calculation exactly
-            let height_noise = perlin::noise3d(
-                ((x as f64) + 16.0) / 12.0,
-                0.0,
-                ((z as f64) + 12.0) / 8.0,
-            );
-            let height = (height_noise.abs() * 8.0 + CHUNK_HEIGHT as f64) as usize;
+/// Outward normals of the cube faces, in the order `build_chunk_mesh` emits them.
+const FACE_NORMALS: [IVec3; 6] = [
+    IVec3::Z,
+    IVec3::NEG_Z,
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::NEG_Y,
+    IVec3::Y,
+];
 
-            let mut column = Vec::with_capacity(height);
+/// Block-space grid corner a vertex sits on.
+fn corner(vertex: &Vertex, block_size: f32) -> IVec3 {
+    (Vec3::from(vertex.position) / block_size)
+        .round()
+        .as_ivec3()
+}
 
-            for y in 0..height {
-                let world_pos = actual_chunk_pos + Vec3::new(x as f32, y as f32, z as f32);
+/// Brightness of the darker chunks with `chunk_checkerboard`.
+const CHECKERBOARD_SHADE: f32 = 0.7;
+
+/// The block at a chunk-local position. Positions outside the chunk count as
+/// empty.
+fn local_block(chunk: &Chunk, local: IVec3) -> Option<Block> {
+    let in_bounds = (0..CHUNK_SIZE_X as i32).contains(&local.x)
+        && (0..WORLD_HEIGHT as i32).contains(&local.y)
+        && (0..CHUNK_SIZE_Z as i32).contains(&local.z);
+    if !in_bounds {
+        return None;
+    }
+    chunk.get(local.x as usize, local.y as usize, local.z as usize)
+}
+
+/// Noise fields and settings that decide which block goes where in the
+/// terrain, before any structures.
+struct Terrain<'a> {
+    perlin: Perlin,
+    ore_perlin: Perlin,
+    biomes: Option<BiomeNoise>,
+    config: &'a WorldGenConfig,
+}
+
+impl<'a> Terrain<'a> {
+    fn new(config: &'a WorldGenConfig) -> Self {
+        Self {
+            perlin: Perlin::new(config.seed),
+            ore_perlin: Perlin::new(config.seed ^ ORE_SEED_SALT),
+            biomes: config
+                .biome_size
+                .map(|size| BiomeNoise::new(config.seed, size)),
+            config,
+        }
+    }
+
+    /// Soil depth and surface block of the column at world `x`, `z`, or
+    /// `None` for the reference world's noise mix.
+    fn layers(&self, x: i32, z: i32) -> Option<(usize, BlockType)> {
+        let config = self.config;
+        let biome = self.biomes.as_ref().map(|biomes| biomes.biome(x, z));
+        let soil_surface = if config.grass_top {
+            BlockType::Grass
+        } else {
+            BlockType::Dirt
+        };
+        match biome {
+            Some(biome) if biome.surface == BlockType::Grass => {
+                Some((biome.soil_depth, soil_surface))
+            }
+            Some(biome) => Some((biome.soil_depth, biome.surface)),
+            None => config.soil_depth.map(|depth| (depth, soil_surface)),
+        }
+    }
+
+    /// Block at height `y` of the column at world `x`, `z`, which is
+    /// `height` blocks tall and has the given `layers`.
+    fn block(
+        &self,
+        x: i32,
+        y: usize,
+        z: i32,
+        height: usize,
+        layers: Option<(usize, BlockType)>,
+    ) -> BlockType {
+        let world_pos = Vec3::new(x as f32, y as f32, z as f32);
+        let stone = || {
+            if y < ORE_MAX_HEIGHT && is_ore(&self.ore_perlin, world_pos, self.config) {
+                BlockType::Ore
+            } else {
+                BlockType::Stone
+            }
+        };
 
+        match layers {
+            // The floor, whatever the noise says
+            _ if y == 0 => BlockType::Bedrock,
+            // Layered: soil on top, stone filling the rest of the column
+            Some((soil_depth, surface)) => {
+                let depth = height - 1 - y;
+                if depth >= soil_depth {
+                    stone()
+                } else if depth == 0 {
+                    surface
+                } else {
+                    BlockType::Dirt
+                }
+            }
+            None => {
                 // Match 3D noise-based block type determination exactly
-                let dirt_noise = perlin::noise3d(
+                let dirt_noise = self.perlin.noise3d(
                     world_pos.x as f64 / 12.0,
                     world_pos.y as f64 / 8.0,
                     world_pos.z as f64 / 12.0,
                 );
-                let stone_noise = perlin::noise3d(
+                let stone_noise = self.perlin.noise3d(
                     (world_pos.x + 8.0) as f64 / 12.0,
                     world_pos.y as f64 / 8.0,
                     (world_pos.z + 8.0) as f64 / 12.0,
                 );
 
-                let block_type = if dirt_noise.abs() > 0.2 {
+                if dirt_noise.abs() > 0.2 {
                     BlockType::Dirt
                 } else if stone_noise.abs() > 0.2 {
-                    BlockType::Stone
-                } else {
+                    stone()
+                } else if self.config.grass_top {
                     BlockType::Grass
-                };
+                } else {
+                    BlockType::Dirt
+                }
+            }
+        }
+    }
+}
+
+/// Top block of each terrain column in and around a chunk, before
+/// structures, so ones reaching across the chunk's edges can be placed
+/// alike by every chunk they reach into.
+pub struct Surface {
+    /// How many columns past each edge of the chunk are covered.
+    border: usize,
+    /// Height and block type of each top block, `border` columns in from the
+    /// corner of the covered area.
+    columns: Vec<Vec<(usize, BlockType)>>,
+}
+
+impl Surface {
+    /// Height and block type of the top block of the column at chunk-local
+    /// `x`, `z`, which may lie up to `border` columns outside the chunk.
+    pub fn top(&self, x: i32, z: i32) -> Option<(usize, BlockType)> {
+        let x = usize::try_from(x + self.border as i32).ok()?;
+        let z = usize::try_from(z + self.border as i32).ok()?;
+        self.columns.get(x)?.get(z).copied()
+    }
+}
+
+pub fn generate_chunk(chunk_pos: IVec2, config: &WorldGenConfig) -> Chunk {
+    let terrain = Terrain::new(config);
+    let mut chunk = Chunk::new(chunk_pos);
+    let origin = chunk.origin();
+    let border = STRUCTURE_REACH;
+    let heights = column_heights(
+        origin,
+        border,
+        &terrain.perlin,
+        terrain.biomes.as_ref(),
+        config,
+    );
 
-                column.push(Block {
-                    position: world_pos,
-                    block_type,
-                });
+    for x in 0..CHUNK_SIZE_X {
+        for z in 0..CHUNK_SIZE_Z {
+            let height = heights[x + border][z + border];
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            let layers = terrain.layers(world_x, world_z);
+            for y in 0..height {
+                let block_type = terrain.block(world_x, y, world_z, height, layers);
+                chunk.set(x, y, z, Some(Block::new(block_type)));
             }
+        }
+    }
+
+    let columns = heights
+        .iter()
+        .enumerate()
+        .map(|(x, column)| {
+            column
+                .iter()
+                .enumerate()
+                .map(|(z, &height)| {
+                    let world_x = origin.x + x as i32 - border as i32;
+                    let world_z = origin.z + z as i32 - border as i32;
+                    let layers = terrain.layers(world_x, world_z);
+                    let top = height - 1;
+                    (top, terrain.block(world_x, top, world_z, height, layers))
+                })
+                .collect()
+        })
+        .collect();
+    place_structures(&mut chunk, &Surface { border, columns }, config);
+    chunk
+}
+
+/// Terrain height of the column at world `x`, `z` before erosion.
+fn raw_height(perlin: &Perlin, biomes: Option<&BiomeNoise>, x: i32, z: i32) -> f64 {
+    let hill_scale = biomes.map_or(1.0, |biomes| biomes.biome(x, z).hill_scale as f64);
+    // Match height calculation exactly: the reference world repeats the same
+    // hills in every chunk
+    let (local_x, local_z) = (
+        x.rem_euclid(CHUNK_SIZE_X as i32),
+        z.rem_euclid(CHUNK_SIZE_Z as i32),
+    );
+    let height_noise = perlin.noise3d(
+        (local_x as f64 + 16.0) / 12.0,
+        0.0,
+        (local_z as f64 + 12.0) / 8.0,
+    );
+    height_noise.abs() * 8.0 * hill_scale + CHUNK_HEIGHT as f64
+}
 
-            layer.push(column);
+/// Number of blocks in each column of the chunk at `origin` and `border`
+/// columns around it, indexed from the corner of that area. With erosion,
+/// each height moves towards the average of the raw heights around it,
+/// including columns in neighbouring chunks, so slopes stay continuous
+/// across chunk edges.
+fn column_heights(
+    origin: IVec3,
+    border: usize,
+    perlin: &Perlin,
+    biomes: Option<&BiomeNoise>,
+    config: &WorldGenConfig,
+) -> Vec<Vec<usize>> {
+    let radius = config.erosion_radius;
+    let (side_x, side_z) = (CHUNK_SIZE_X + 2 * border, CHUNK_SIZE_Z + 2 * border);
+    // Raw heights of the area and a margin `radius` columns wide around it,
+    // each sampled once, with a summed-area table over them: `sums[x][z]` is
+    // the total of the raw heights before `x` and `z`, so any window's sum
+    // takes four lookups
+    let margin = border + radius;
+    let (padded_x, padded_z) = (side_x + 2 * radius, side_z + 2 * radius);
+    let mut raw = vec![vec![0.0; padded_z]; padded_x];
+    let mut sums = vec![vec![0.0; padded_z + 1]; padded_x + 1];
+    for x in 0..padded_x {
+        for z in 0..padded_z {
+            let height = raw_height(
+                perlin,
+                biomes,
+                origin.x + x as i32 - margin as i32,
+                origin.z + z as i32 - margin as i32,
+            );
+            raw[x][z] = height;
+            sums[x + 1][z + 1] = height + sums[x][z + 1] + sums[x + 1][z] - sums[x][z];
         }
+    }
 
-        chunk.push(layer);
+    let window = 2 * radius + 1;
+    let mut heights = vec![vec![0; side_z]; side_x];
+    for (x, column) in heights.iter_mut().enumerate() {
+        for (z, height) in column.iter_mut().enumerate() {
+            let center = raw[x + radius][z + radius];
+            let eroded = if radius == 0 {
+                center
+            } else {
+                let sum = sums[x + window][z + window] - sums[x][z + window] - sums[x + window][z]
+                    + sums[x][z];
+                let average = sum / (window * window) as f64;
+                center + (average - center) * config.erosion_strength as f64
+            };
+            *height = eroded as usize;
+        }
     }
+    heights
+}
 
-    chunk
+/// Whether the stone block at `world_pos` belongs to an ore cluster. Clusters
+/// are the peaks of a 3D noise field whose feature size follows `ore_size`.
+fn is_ore(perlin: &Perlin, world_pos: Vec3, config: &WorldGenConfig) -> bool {
+    let scale = config.ore_size.max(0.1) as f64;
+    // Offset off the lattice, where Perlin noise is always zero
+    let noise = perlin.noise3d(
+        world_pos.x as f64 / scale + 0.37,
+        world_pos.y as f64 / scale + 0.61,
+        world_pos.z as f64 / scale + 0.19,
+    );
+    noise > config.ore_rarity as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generates chunks over a spread of seeds, positions (including ones far
+    /// from the origin) and configs, checking every column and mesh.
+    #[test]
+    fn generate_chunk_fuzz() {
+        let seeds = [0, 1, 42, u64::MAX, seed_from_str("fuzz")];
+        let positions = [
+            IVec2::new(0, 0),
+            IVec2::new(-1, 3),
+            IVec2::new(17, -29),
+            IVec2::new(-1000, 1000),
+            IVec2::new(100_000, -100_000),
+        ];
+        let configs = [
+            WorldGenConfig::default(),
+            WorldGenConfig {
+                ore_size: 0.01,
+                ore_rarity: -1.0,
+                soil_depth: Some(0),
+                ..Default::default()
+            },
+            WorldGenConfig {
+                block_size: 0.5,
+                ore_size: 100.0,
+                soil_depth: Some(WORLD_HEIGHT + 5),
+                grass_top: false,
+                ..Default::default()
+            },
+            WorldGenConfig {
+                biome_size: Some(4.0),
+                ..Default::default()
+            },
+            WorldGenConfig {
+                biome_size: Some(16.0),
+                erosion_radius: 3,
+                erosion_strength: 0.7,
+                ..Default::default()
+            },
+        ];
+
+        for seed in seeds {
+            for position in positions {
+                for config in &configs {
+                    let config = WorldGenConfig {
+                        seed,
+                        ..config.clone()
+                    };
+                    let chunk = generate_chunk(position, &config);
+                    let context = format!("seed {seed}, chunk {position}");
+
+                    for x in 0..CHUNK_SIZE_X {
+                        for z in 0..CHUNK_SIZE_Z {
+                            let height = (0..WORLD_HEIGHT)
+                                .rev()
+                                .find(|&y| chunk.get(x, y, z).is_some())
+                                .map_or(0, |y| y + 1);
+                            assert!(
+                                (1..=WORLD_HEIGHT).contains(&height),
+                                "{context}: column {x},{z} has height {height}"
+                            );
+                            assert!(
+                                (0..height).all(|y| chunk.get(x, y, z).is_some()),
+                                "{context}: column {x},{z} has gaps"
+                            );
+                            assert_eq!(
+                                chunk.get(x, 0, z).map(|block| block.block_type),
+                                Some(BlockType::Bedrock),
+                                "{context}: column {x},{z} has no bedrock floor"
+                            );
+                        }
+                    }
+
+                    let ChunkMesh {
+                        vertices, indices, ..
+                    } = build_chunk_mesh(&chunk, &config);
+                    assert!(!vertices.is_empty(), "{context}: empty mesh");
+                    assert_eq!(vertices.len() % 4, 0, "{context}");
+                    assert_eq!(indices.len(), vertices.len() / 4 * 6, "{context}");
+                    assert!(
+                        indices.iter().all(|&i| (i as usize) < vertices.len()),
+                        "{context}: index out of range"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn face_lighting_bakes_brightness_per_direction() {
+        let faces: FaceBrightness = "1,0.8,0.6,0.5".parse().unwrap();
+        let config = WorldGenConfig {
+            baked_lighting: Some(BakedLighting::Faces(faces)),
+            ..Default::default()
+        };
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(0, 0, 0, Some(Block::new(BlockType::Stone)));
+        let mesh = build_chunk_mesh(&chunk, &config);
+
+        for vertex in mesh.vertices {
+            let expected = match vertex.normal.map(|n| n as i32) {
+                [0, 1, 0] => 1.0,
+                [0, -1, 0] => 0.5,
+                [_, 0, 0] => 0.6,
+                _ => 0.8,
+            };
+            assert_eq!(vertex.color, [expected, expected, 1.0]);
+        }
+        assert!("1,2,3".parse::<FaceBrightness>().is_err());
+    }
+
+    #[test]
+    fn sky_shadow_darkens_faces_under_a_roof() {
+        let config = WorldGenConfig {
+            baked_lighting: Some(BakedLighting::Faces(FaceBrightness::default())),
+            sky_shadow: Some(0.5),
+            ..Default::default()
+        };
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(4, 0, 4, Some(Block::new(BlockType::Stone)));
+        let top_brightness = |chunk: &Chunk, y: f32| {
+            let mesh = build_chunk_mesh(chunk, &config);
+            let top = mesh
+                .vertices
+                .iter()
+                .find(|vertex| vertex.normal == [0.0, 1.0, 0.0] && vertex.position[1] == y)
+                .unwrap();
+            top.color[0]
+        };
+        assert_eq!(top_brightness(&chunk, 1.0), 1.0);
+
+        // A block placed overhead shades the top face, but not its own, and
+        // light from the open cells beside it still reaches in
+        chunk.set(4, 3, 4, Some(Block::new(BlockType::Stone)));
+        assert_eq!(top_brightness(&chunk, 1.0), 0.5 + 0.5 * 5.0 / 6.0);
+        assert_eq!(top_brightness(&chunk, 4.0), 1.0);
+
+        // Walled in, no light reaches it at all
+        for (x, y, z) in [(3, 1, 4), (5, 1, 4), (4, 1, 3), (4, 1, 5), (4, 2, 4)] {
+            chunk.set(x, y, z, Some(Block::new(BlockType::Stone)));
+        }
+        assert_eq!(top_brightness(&chunk, 1.0), 0.5);
+
+        // Glass lets the sky through
+        chunk.set(4, 2, 4, Some(Block::new(BlockType::Glass)));
+        chunk.set(4, 3, 4, Some(Block::new(BlockType::Glass)));
+        assert_eq!(top_brightness(&chunk, 1.0), 1.0);
+    }
+
+    #[test]
+    fn checkerboard_darkens_alternate_chunks() {
+        let config = WorldGenConfig {
+            chunk_checkerboard: true,
+            ..Default::default()
+        };
+        let shade = |x, z| {
+            let mut chunk = Chunk::new(IVec2::new(x, z));
+            chunk.set(0, 0, 0, Some(Block::new(BlockType::Stone)));
+            build_chunk_mesh(&chunk, &config).vertices[0].color[2]
+        };
+        assert_eq!(shade(0, 0), 1.0);
+        assert_eq!(shade(-1, 0), CHECKERBOARD_SHADE);
+        assert_eq!(shade(1, 1), 1.0);
+        assert_eq!(shade(2, -1), CHECKERBOARD_SHADE);
+    }
+
+    #[test]
+    fn biomes_are_deterministic_and_varied() {
+        let biomes = BiomeNoise::new(7, 32.0);
+        let again = BiomeNoise::new(7, 32.0);
+        let mut seen = Vec::new();
+        for x in (-2000..2000).step_by(37) {
+            for z in (-2000..2000).step_by(41) {
+                let biome = biomes.biome(x, z);
+                assert_eq!(biome, again.biome(x, z));
+                if !seen.contains(&biome.name) {
+                    seen.push(biome.name);
+                }
+            }
+        }
+        // Every corner of the table shows up somewhere
+        for name in ["Tundra", "Bog", "Desert", "Jungle"] {
+            assert!(seen.contains(&name), "{name} never generated: {seen:?}");
+        }
+    }
+
+    #[test]
+    fn erosion_smooths_slopes_across_chunk_edges() {
+        // Heights of one row of columns running through two chunks along X.
+        // Biomes vary the hills, so the chunks don't repeat each other
+        let row = |config: &WorldGenConfig| {
+            let perlin = Perlin::new(config.seed);
+            let biomes = BiomeNoise::new(config.seed, config.biome_size.unwrap());
+            [IVec2::ZERO, IVec2::X]
+                .into_iter()
+                .flat_map(|chunk| {
+                    let origin = Chunk::new(chunk).origin();
+                    column_heights(origin, 0, &perlin, Some(&biomes), config)
+                        .into_iter()
+                        .map(|column| column[0] as i32)
+                })
+                .collect::<Vec<_>>()
+        };
+        let steepest = |heights: &[i32]| {
+            heights
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]).abs())
+                .max()
+                .unwrap()
+        };
+
+        let raw_config = WorldGenConfig {
+            seed: 3,
+            biome_size: Some(16.0),
+            ..Default::default()
+        };
+        let raw = row(&raw_config);
+        let eroded_config = WorldGenConfig {
+            erosion_radius: 2,
+            ..raw_config.clone()
+        };
+        let eroded = row(&eroded_config);
+        assert_eq!(eroded, row(&eroded_config));
+        // Each column holds the plain average of the 5x5 raw heights around it
+        let perlin = Perlin::new(3);
+        let biomes = BiomeNoise::new(3, 16.0);
+        for (x, &height) in eroded.iter().enumerate() {
+            let sum: f64 = (-2..=2)
+                .flat_map(|dx| (-2..=2).map(move |dz| (x as i32 + dx, dz)))
+                .map(|(x, z)| raw_height(&perlin, Some(&biomes), x, z))
+                .sum();
+            assert_eq!(height, (sum / 25.0) as i32, "column {x}");
+        }
+        assert!(steepest(&eroded) < steepest(&raw), "{raw:?} -> {eroded:?}");
+        // The step between the chunks shrinks like any other
+        let edge = |heights: &[i32]| (heights[CHUNK_SIZE_X] - heights[CHUNK_SIZE_X - 1]).abs();
+        assert!(edge(&eroded) < edge(&raw), "{raw:?} -> {eroded:?}");
+        // No strength keeps the raw terrain
+        let none = WorldGenConfig {
+            erosion_strength: 0.0,
+            ..eroded_config
+        };
+        assert_eq!(row(&none), raw);
+    }
+
+    #[test]
+    fn faces_between_blocks_are_culled() {
+        use BlockType::{Glass, Stone};
+
+        // Culling works on the block grid, so it's the same at any scale
+        for block_size in [1.0, 0.5, 3.0] {
+            let config = WorldGenConfig {
+                block_size,
+                ..Default::default()
+            };
+            // Opaque and translucent faces meshed for blocks in a row along X
+            let mesh = |blocks: &[(usize, BlockType)]| {
+                let mut chunk = Chunk::new(IVec2::ZERO);
+                for &(x, block_type) in blocks {
+                    chunk.set(x, 0, 0, Some(Block::new(block_type)));
+                }
+                build_chunk_mesh(&chunk, &config)
+            };
+            let faces = |blocks: &[(usize, BlockType)]| {
+                let mesh = mesh(blocks);
+                let translucent = mesh.indices.len() - mesh.translucent_start;
+                (mesh.translucent_start / 6, translucent / 6)
+            };
+
+            assert_eq!(faces(&[(0, Stone)]), (6, 0));
+            // Opaque neighbours hide the faces between them
+            assert_eq!(faces(&[(0, Stone), (1, Stone)]), (10, 0));
+            // Stone hides the glass face against it, but shows through the glass
+            assert_eq!(faces(&[(0, Stone), (1, Glass)]), (6, 5));
+            // Glass next to glass merges into one pane
+            assert_eq!(faces(&[(0, Glass), (1, Glass)]), (0, 10));
+            // Air hides nothing
+            assert_eq!(faces(&[(0, Glass), (2, Stone)]), (6, 6));
+
+            // The remaining faces span the blocks at their scale
+            let far = mesh(&[(0, Stone), (1, Stone)])
+                .vertices
+                .iter()
+                .map(|vertex| Vec3::from(vertex.position))
+                .fold(Vec3::ZERO, Vec3::max);
+            assert_eq!(far, Vec3::new(2.0, 1.0, 1.0) * block_size);
+        }
+        assert!(!Glass.face_hidden_by(None));
+    }
+
+    #[test]
+    fn solidity_and_opacity_are_independent() {
+        use BlockType::{Glass, Leaves, Poppy, Stone};
+
+        for (block_type, solid, opaque) in [
+            (Stone, true, true),
+            (Glass, true, false),
+            (Leaves, true, false),
+            (Poppy, false, false),
+        ] {
+            assert_eq!(block_type.is_solid(), solid, "{block_type:?}");
+            assert_eq!(block_type.is_opaque(), opaque, "{block_type:?}");
+        }
+
+        let faces = |blocks: &[(usize, BlockType)]| {
+            let mut chunk = Chunk::new(IVec2::ZERO);
+            for &(x, block_type) in blocks {
+                chunk.set(x, 0, 0, Some(Block::new(block_type)));
+            }
+            build_chunk_mesh(&chunk, &WorldGenConfig::default()).decoration_start / 6
+        };
+        // Leaves are solid but don't hide the stone behind them, and merge
+        // with each other like glass does
+        assert_eq!(faces(&[(0, Stone), (1, Leaves)]), 11);
+        assert_eq!(faces(&[(0, Leaves), (1, Leaves)]), 10);
+        // A plant hides nothing and is walked through
+        assert_eq!(faces(&[(0, Stone), (1, Poppy)]), 6);
+        assert!(!Stone.face_hidden_by(Some(Block::new(Poppy))));
+    }
+
+    #[test]
+    fn decorations_mesh_as_crossed_quads() {
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(0, 0, 0, Some(Block::new(BlockType::Stone)));
+        chunk.set(1, 0, 0, Some(Block::new(BlockType::Poppy)));
+        let mesh = build_chunk_mesh(&chunk, &WorldGenConfig::default());
+        // The stone keeps all six faces and the poppy adds two quads
+        assert_eq!(mesh.decoration_start, 6 * 6);
+        assert_eq!(mesh.translucent_start - mesh.decoration_start, 12);
+        assert_eq!(mesh.vertices.len(), 6 * 4 + 8);
+        let plant = &mesh.vertices[6 * 4..];
+        let cell = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 1.0, 1.0));
+        for vertex in plant {
+            let position = Vec3::from(vertex.position);
+            assert!(position.cmpge(cell.0).all() && position.cmple(cell.1).all());
+        }
+        // Each quad runs corner to corner through the cell, along the ground
+        let ends = |first: usize| {
+            // A quad's bottom-left and bottom-right corners
+            let ground = |i: usize| Vec3::from(plant[first + i].position) - cell.0;
+            [ground(2), ground(1)]
+        };
+        assert_eq!(ends(0), [Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0)]);
+        assert_eq!(ends(4), [Vec3::X, Vec3::Z]);
+    }
+
+    #[test]
+    fn texture_rotation_is_deterministic_per_position() {
+        let position = IVec3::new(-3, 40, 17);
+        assert_eq!(texture_turns(5, position), texture_turns(5, position));
+        // Faces and positions get every turn, not one shared choice
+        let turns: Vec<u32> = (0..8)
+            .flat_map(|x| texture_turns(5, IVec3::new(x, 40, 17)))
+            .collect();
+        assert!((0..4).all(|turn| turns.contains(&turn)));
+
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(0, 0, 0, Some(Block::new(BlockType::Stone)));
+        chunk.set(2, 0, 0, Some(Block::new(BlockType::Grass)));
+        let config = WorldGenConfig {
+            rotate_textures: true,
+            ..WorldGenConfig::default()
+        };
+        let uvs = |config: &WorldGenConfig| -> Vec<[f32; 2]> {
+            let vertices = build_chunk_mesh(&chunk, config).vertices;
+            vertices.iter().map(|vertex| vertex.tex_coord).collect()
+        };
+        // Remeshing gives the same turns; only the stone turns, not the grass
+        let rotated = uvs(&config);
+        assert_eq!(rotated, uvs(&config));
+        let plain = uvs(&WorldGenConfig::default());
+        assert_ne!(rotated[..24], plain[..24]);
+        assert_eq!(rotated[24..], plain[24..]);
+    }
+
+    #[test]
+    fn vertex_budget_drops_whole_faces() {
+        let chunk = generate_chunk(IVec2::ZERO, &WorldGenConfig::default());
+        let full = build_chunk_mesh(&chunk, &WorldGenConfig::default());
+        let config = WorldGenConfig {
+            max_chunk_vertices: 102,
+            ..WorldGenConfig::default()
+        };
+        let mesh = build_chunk_mesh(&chunk, &config);
+        assert!(full.vertices.len() > 102);
+        assert_eq!(mesh.vertices.len(), 100);
+        assert_eq!(mesh.indices.len(), 150);
+        let in_range = |&i: &u16| (i as usize) < mesh.vertices.len();
+        assert!(mesh.indices.iter().all(in_range));
+    }
+
+    #[test]
+    fn empty_chunk_meshes_to_nothing() {
+        let chunk = Chunk::new(IVec2::ZERO);
+        let config = WorldGenConfig {
+            dedup_vertices: true,
+            baked_lighting: Some(BakedLighting::Faces(FaceBrightness::default())),
+            ..WorldGenConfig::default()
+        };
+        for config in [WorldGenConfig::default(), config] {
+            let mesh = build_chunk_mesh(&chunk, &config);
+            assert!(mesh.vertices.is_empty());
+            assert!(mesh.indices.is_empty());
+            assert_eq!(mesh.translucent_start, 0);
+        }
+    }
+
+    #[test]
+    fn faces_wind_counter_clockwise_from_outside() {
+        let mut chunk = Chunk::new(IVec2::ZERO);
+        chunk.set(0, 0, 0, Some(Block::new(BlockType::Stone)));
+        let mesh = build_chunk_mesh(&chunk, &WorldGenConfig::default());
+        let center = Vec3::splat(0.5);
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] =
+                [0, 1, 2].map(|i| Vec3::from(mesh.vertices[triangle[i] as usize].position));
+            assert!((b - a).cross(c - a).dot(a - center) > 0.0);
+        }
+    }
 }